@@ -1,8 +1,36 @@
-use crate::client::BlackRoadClient;
+use crate::client::{path_segment, BlackRoadClient, Scope};
 use crate::errors::Error;
-use crate::types::{Agent, AgentListOptions, RegisterAgentOptions, Stats};
+use crate::polling::{page_stream, poll_stream, Batch, PageStart, DEFAULT_PAGE_SIZE};
+use crate::selection::{LeastLoaded, SelectionStrategy};
+use crate::types::{
+    Agent, AgentConfig, AgentListOptions, AgentPresence, AgentStats, AgentStatsOptions,
+    AgentStatus, BulkResult, CapacitySignal, FacetCount, Faceted, HeartbeatOptions, Page,
+    PresenceChange, RegisterAgentOptions, Snapshot, TaskStatsOptions, UpdateAgentOptions,
+};
+use crate::validation::validate_capabilities;
+use crate::TaskAPI;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    std::fmt,
+    std::sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    std::sync::{Arc, Mutex},
+    tokio::sync::mpsc,
+};
+
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const PRESENCE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const BULK_CONCURRENCY: usize = 8;
+
+/// Heartbeat failures an [`AgentSession`] holds until they are read.
+#[cfg(not(target_arch = "wasm32"))]
+const SESSION_ERROR_BUFFER: usize = 16;
+/// Shortest interval an [`AgentSession`] sends heartbeats at.
+#[cfg(not(target_arch = "wasm32"))]
+const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
 
 /// API for managing agents.
 #[derive(Debug, Clone)]
@@ -15,6 +43,36 @@ struct AgentsResponse {
     agents: Vec<Agent>,
 }
 
+#[derive(Deserialize)]
+struct AgentPageResponse {
+    agents: Vec<Agent>,
+    #[serde(default)]
+    total: Option<i64>,
+    #[serde(default)]
+    limit: Option<i32>,
+    #[serde(default)]
+    offset: Option<i32>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FacetedAgentsResponse {
+    agents: Vec<Agent>,
+    #[serde(default)]
+    facets: HashMap<String, Vec<FacetCount>>,
+}
+
+#[derive(Deserialize)]
+struct PresenceResponse {
+    agents: Vec<AgentPresence>,
+}
+
+#[derive(Deserialize)]
+struct NonceResponse {
+    nonce: String,
+}
+
 #[derive(Deserialize)]
 struct DeleteResponse {
     #[allow(dead_code)]
@@ -28,50 +86,159 @@ impl AgentAPI {
 
     /// Lists agents with optional filters.
     pub async fn list(&self, opts: Option<AgentListOptions>) -> Result<Vec<Agent>, Error> {
-        let mut params = HashMap::new();
-
-        if let Some(opts) = opts {
-            if let Some(t) = opts.agent_type {
-                params.insert("type".to_string(), t);
-            }
-            if let Some(d) = opts.division {
-                params.insert("division".to_string(), d);
-            }
-            if let Some(l) = opts.level {
-                params.insert("level".to_string(), l.to_string());
-            }
-            if let Some(s) = opts.status {
-                params.insert("status".to_string(), s);
-            }
-            if let Some(l) = opts.limit {
-                params.insert("limit".to_string(), l.to_string());
-            }
-            if let Some(o) = opts.offset {
-                params.insert("offset".to_string(), o.to_string());
-            }
-        }
-
-        let params_opt = if params.is_empty() { None } else { Some(&params) };
+        let params = list_params(opts, self.client.scope());
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
         let response: AgentsResponse = self.client.get("/agents", params_opt).await?;
         Ok(response.agents)
     }
 
+    /// Lists agents like [`list`](Self::list), keeping the total count and paging
+    /// position the API reports alongside them.
+    pub async fn list_page(&self, opts: Option<AgentListOptions>) -> Result<Page<Agent>, Error> {
+        let params = list_params(opts, self.client.scope());
+        let response: AgentPageResponse = self.client.get("/agents", Some(&params)).await?;
+        Ok(Page {
+            items: response.agents,
+            total: response.total,
+            limit: response.limit,
+            offset: response.offset,
+            next_cursor: response.next_cursor,
+        })
+    }
+
+    /// Returns a stream of every agent matching `opts`, fetching pages of
+    /// `opts.limit` (default 100) starting at `opts.cursor` or `opts.offset`.
+    /// Pages follow the API's `next_cursor` when it returns one. Agents that
+    /// move between pages while the stream runs are yielded only once.
+    pub fn list_all(
+        &self,
+        opts: Option<AgentListOptions>,
+    ) -> impl Stream<Item = Result<Agent, Error>> {
+        let mut opts = opts.unwrap_or_default();
+        let page_size = opts.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+        let start = match opts.cursor.take() {
+            Some(cursor) => PageStart::Cursor(cursor),
+            None => PageStart::Offset(opts.offset.unwrap_or(0).max(0)),
+        };
+        let api = self.clone();
+        page_stream(
+            start,
+            page_size,
+            |a: &Agent| a.id.clone(),
+            move |start| {
+                let api = api.clone();
+                let opts = AgentListOptions {
+                    limit: Some(page_size),
+                    offset: start.offset(),
+                    cursor: start.cursor(),
+                    ..opts.clone()
+                };
+                async move { api.list_page(Some(opts)).await }
+            },
+        )
+    }
+
+    /// Lists agents like [`list`](Self::list), also counting matches per
+    /// value of each field in `facets` (e.g. `"status"`, `"division"`).
+    pub async fn list_with_facets(
+        &self,
+        opts: Option<AgentListOptions>,
+        facets: &[&str],
+    ) -> Result<Faceted<Agent>, Error> {
+        let mut params = list_params(opts, self.client.scope());
+        params.insert("facets".to_string(), facets.join(","));
+        let response: FacetedAgentsResponse = self.client.get("/agents", Some(&params)).await?;
+        Ok(Faceted {
+            items: response.agents,
+            facets: response.facets,
+        })
+    }
+
     /// Gets a specific agent by ID.
     pub async fn get(&self, agent_id: &str) -> Result<Agent, Error> {
-        self.client.get(&format!("/agents/{}", agent_id), None).await
+        self.client
+            .get(&format!("/agents/{}", path_segment(agent_id)?), None)
+            .await
+    }
+
+    /// Gets several agents concurrently, at most eight requests at a time.
+    ///
+    /// Results are returned in the order of `agent_ids`; one failure does not
+    /// stop the others.
+    pub async fn get_many<I, S>(&self, agent_ids: I) -> Vec<BulkResult<Agent>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        stream::iter(agent_ids.into_iter().map(Into::into))
+            .map(|id: String| async move {
+                let result = self.get(&id).await;
+                BulkResult { id, result }
+            })
+            .buffered(BULK_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Lists agents like [`list`](Self::list), falling back to the last-known
+    /// result if the API is unreachable.
+    pub async fn list_snapshot(
+        &self,
+        opts: Option<AgentListOptions>,
+    ) -> Result<Snapshot<Vec<Agent>>, Error> {
+        let params = list_params(opts, self.client.scope());
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        let snapshot: Snapshot<AgentsResponse> =
+            self.client.get_snapshot("/agents", params_opt).await?;
+        Ok(snapshot.map(|r| r.agents))
+    }
+
+    /// Gets an agent like [`get`](Self::get), falling back to the last-known
+    /// result if the API is unreachable.
+    pub async fn get_snapshot(&self, agent_id: &str) -> Result<Snapshot<Agent>, Error> {
+        self.client
+            .get_snapshot(&format!("/agents/{}", path_segment(agent_id)?), None)
+            .await
+    }
+
+    /// Gets a single-use nonce to sign into an [`Attestation`](crate::Attestation).
+    pub async fn attestation_nonce(&self) -> Result<String, Error> {
+        let response: NonceResponse = self.client.get("/agents/nonce", None).await?;
+        Ok(response.nonce)
     }
 
     /// Registers a new agent.
     pub async fn register(&self, opts: RegisterAgentOptions) -> Result<Agent, Error> {
+        opts.validate()?;
         let mut body = serde_json::json!({
             "name": opts.name,
             "type": opts.agent_type.unwrap_or_else(|| "ai".to_string()),
             "level": opts.level.unwrap_or(4),
         });
 
-        if let Some(div) = opts.division {
+        if let Some(div) = opts
+            .division
+            .or_else(|| self.client.scope().division.clone())
+        {
             body["division"] = serde_json::Value::String(div);
         }
+        if let Some(version) = opts.version {
+            body["version"] = serde_json::Value::String(version);
+        }
+        if let Some(attestation) = opts.attestation {
+            body["attestation"] = serde_json::to_value(attestation)?;
+        }
+        if !opts.capabilities.is_empty() {
+            body["capabilities"] = serde_json::to_value(opts.capabilities)?;
+        }
         if let Some(meta) = opts.metadata {
             body["metadata"] = serde_json::to_value(meta)?;
         }
@@ -81,33 +248,477 @@ impl AgentAPI {
 
     /// Sends a heartbeat for an agent.
     pub async fn heartbeat(&self, agent_id: &str, load: Option<f64>) -> Result<(), Error> {
-        let body = match load {
-            Some(l) => serde_json::json!({ "load": l }),
-            None => serde_json::json!({}),
-        };
+        self.heartbeat_with(
+            agent_id,
+            HeartbeatOptions {
+                load,
+                ..Default::default()
+            },
+        )
+        .await
+    }
 
+    /// Starts sending heartbeats for `agent_id` every `interval` from a
+    /// background task, beginning immediately.
+    ///
+    /// Heartbeats stop when the returned session is dropped. Failed
+    /// heartbeats are reported through [`AgentSession::next_error`] and do not
+    /// stop the session. An `interval` under one second is raised to one
+    /// second.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_session(&self, agent_id: &str, interval: Duration) -> AgentSession {
+        self.start_session_with(agent_id, interval, HeartbeatPolicy::default())
+    }
+
+    /// Starts a session like [`start_session`](Self::start_session) that
+    /// handles repeated heartbeat failures according to `policy`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_session_with(
+        &self,
+        agent_id: &str,
+        interval: Duration,
+        policy: HeartbeatPolicy,
+    ) -> AgentSession {
+        let interval = interval.max(MIN_HEARTBEAT_INTERVAL);
+        let (errors_tx, errors) = mpsc::channel(SESSION_ERROR_BUFFER);
+        let state = Arc::new(SessionState {
+            agent_id: Mutex::new(agent_id.to_string()),
+            load: AtomicU64::new(f64::NAN.to_bits()),
+            degraded: AtomicBool::new(false),
+        });
+        let agents = self.clone();
+        let shared = Arc::clone(&state);
+
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut failures = 0;
+            loop {
+                ticks.tick().await;
+                let id = shared.agent_id();
+                let load = f64::from_bits(shared.load.load(Ordering::Relaxed));
+                let load = (!load.is_nan()).then_some(load);
+                let error = match agents.heartbeat(&id, load).await {
+                    Ok(()) => {
+                        failures = 0;
+                        if shared.degraded.swap(false, Ordering::Relaxed) {
+                            if let Some(observer) = &policy.observer {
+                                observer.on_recovered(&id);
+                            }
+                        }
+                        continue;
+                    }
+                    Err(e) => e,
+                };
+
+                if let (Error::NotFound(_), Some(opts)) = (&error, &policy.reregister) {
+                    if let Ok(agent) = agents.register(opts.clone()).await {
+                        if let Some(observer) = &policy.observer {
+                            observer.on_reregistered(&id, &agent);
+                        }
+                        *shared.agent_id.lock().unwrap() = agent.id;
+                        failures = 0;
+                        continue;
+                    }
+                }
+
+                failures += 1;
+                if failures >= policy.failure_threshold.max(1)
+                    && !shared.degraded.swap(true, Ordering::Relaxed)
+                {
+                    if let Some(observer) = &policy.observer {
+                        observer.on_degraded(&id, &error);
+                    }
+                }
+                // A full buffer means nobody is reading; drop the error.
+                let _ = errors_tx.try_send(error);
+            }
+        });
+
+        AgentSession {
+            state,
+            errors,
+            task,
+        }
+    }
+
+    /// Sends a heartbeat for an agent, reporting load and software version.
+    pub async fn heartbeat_with(
+        &self,
+        agent_id: &str,
+        opts: HeartbeatOptions,
+    ) -> Result<(), Error> {
         let _: serde_json::Value = self
             .client
-            .post(&format!("/agents/{}/heartbeat", agent_id), &body)
+            .post(
+                &format!("/agents/{}/heartbeat", path_segment(agent_id)?),
+                &opts,
+            )
             .await?;
         Ok(())
     }
 
+    /// Lists agents reporting a version older than `min_version`, including
+    /// agents that have never reported one.
+    pub async fn outdated(&self, min_version: &str) -> Result<Vec<Agent>, Error> {
+        let mut params = HashMap::new();
+        params.insert("version_lt".to_string(), min_version.to_string());
+        let response: AgentsResponse = self.client.get("/agents", Some(&params)).await?;
+        Ok(response.agents)
+    }
+
+    /// Asks an agent to upgrade to `target_version` on its next heartbeat.
+    pub async fn request_update(
+        &self,
+        agent_id: &str,
+        target_version: &str,
+    ) -> Result<Agent, Error> {
+        let body = serde_json::json!({ "target_version": target_version });
+        self.client
+            .post(
+                &format!("/agents/{}/update", path_segment(agent_id)?),
+                &body,
+            )
+            .await
+    }
+
+    /// Changes an agent's name, division, level, or metadata in place,
+    /// keeping its ID and history. Only the fields set in `opts` are sent.
+    pub async fn update(&self, agent_id: &str, opts: UpdateAgentOptions) -> Result<Agent, Error> {
+        opts.validate()?;
+        self.client
+            .patch(&format!("/agents/{}", path_segment(agent_id)?), &opts)
+            .await
+    }
+
+    /// Replaces the capabilities an agent declares, e.g. after it gains or
+    /// loses access to a GPU.
+    pub async fn update_capabilities(
+        &self,
+        agent_id: &str,
+        capabilities: Vec<String>,
+    ) -> Result<Agent, Error> {
+        validate_capabilities(&capabilities)?;
+        let body = serde_json::json!({ "capabilities": capabilities });
+        self.client
+            .put(
+                &format!("/agents/{}/capabilities", path_segment(agent_id)?),
+                &body,
+            )
+            .await
+    }
+
     /// Updates an agent's status.
-    pub async fn update_status(&self, agent_id: &str, status: &str) -> Result<Agent, Error> {
+    pub async fn update_status(&self, agent_id: &str, status: AgentStatus) -> Result<Agent, Error> {
         let body = serde_json::json!({ "status": status });
-        self.client.put(&format!("/agents/{}", agent_id), &body).await
+        self.client
+            .put(&format!("/agents/{}", path_segment(agent_id)?), &body)
+            .await
+    }
+
+    /// Updates the status of several agents concurrently.
+    ///
+    /// Results are returned in the order of `agent_ids`; one failure does not
+    /// stop the others.
+    pub async fn update_status_many<I, S>(
+        &self,
+        agent_ids: I,
+        status: AgentStatus,
+    ) -> Vec<BulkResult<Agent>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let status = &status;
+        stream::iter(agent_ids.into_iter().map(Into::into))
+            .map(|id: String| async move {
+                let result = self.update_status(&id, status.clone()).await;
+                BulkResult { id, result }
+            })
+            .buffered(BULK_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Updates the status of every agent matching `opts`, e.g. to put a whole
+    /// division into `maintenance`.
+    ///
+    /// Every page of matches is listed before any update is sent. Fails only
+    /// if the agents cannot be listed; per-agent failures are reported in the
+    /// results.
+    pub async fn update_status_where(
+        &self,
+        opts: AgentListOptions,
+        status: AgentStatus,
+    ) -> Result<Vec<BulkResult<Agent>>, Error> {
+        let agents: Vec<Agent> = self.list_all(Some(opts)).try_collect().await?;
+        Ok(self
+            .update_status_many(agents.into_iter().map(|a| a.id), status)
+            .await)
     }
 
-    /// Deletes an agent.
+    /// Pauses an agent, setting its status to [`AgentStatus::Paused`]. Paused
+    /// agents keep their registration and history but are not assigned new
+    /// work until resumed.
+    pub async fn pause(&self, agent_id: &str) -> Result<Agent, Error> {
+        let body = serde_json::json!({});
+        self.client
+            .post(&format!("/agents/{}/pause", path_segment(agent_id)?), &body)
+            .await
+    }
+
+    /// Resumes a [`AgentStatus::Paused`] agent so it becomes eligible for new
+    /// work again.
+    pub async fn resume(&self, agent_id: &str) -> Result<Agent, Error> {
+        let body = serde_json::json!({});
+        self.client
+            .post(
+                &format!("/agents/{}/resume", path_segment(agent_id)?),
+                &body,
+            )
+            .await
+    }
+
+    /// Deletes an agent. Deleted agents can be brought back with
+    /// [`restore`](Self::restore) until the API purges them.
     pub async fn delete(&self, agent_id: &str) -> Result<(), Error> {
-        let _: DeleteResponse = self.client.delete(&format!("/agents/{}", agent_id)).await?;
+        let _: DeleteResponse = self
+            .client
+            .delete(&format!("/agents/{}", path_segment(agent_id)?))
+            .await?;
         Ok(())
     }
 
-    /// Gets agent statistics.
-    pub async fn stats(&self) -> Result<Stats, Error> {
-        self.client.get("/agents/stats", None).await
+    /// Restores a deleted agent.
+    pub async fn restore(&self, agent_id: &str) -> Result<Agent, Error> {
+        let body = serde_json::json!({});
+        self.client
+            .post(
+                &format!("/agents/{}/restore", path_segment(agent_id)?),
+                &body,
+            )
+            .await
+    }
+
+    /// Deletes every agent matching `opts`, returning each one with the outcome
+    /// of its deletion.
+    ///
+    /// Every page of matches is listed before anything is deleted. With
+    /// `dry_run` set nothing is deleted, and every matching agent is returned
+    /// as `Ok`.
+    pub async fn delete_where(
+        &self,
+        opts: AgentListOptions,
+        dry_run: bool,
+    ) -> Result<Vec<BulkResult<Agent>>, Error> {
+        let agents: Vec<Agent> = self.list_all(Some(opts)).try_collect().await?;
+        if dry_run {
+            return Ok(agents
+                .into_iter()
+                .map(|a| BulkResult {
+                    id: a.id.clone(),
+                    result: Ok(a),
+                })
+                .collect());
+        }
+
+        Ok(stream::iter(agents)
+            .map(|agent| async move {
+                let id = agent.id.clone();
+                let result = self.delete(&id).await.map(|()| agent);
+                BulkResult { id, result }
+            })
+            .buffered(BULK_CONCURRENCY)
+            .collect()
+            .await)
+    }
+
+    /// Gets an agent's runtime configuration.
+    pub async fn get_config(&self, agent_id: &str) -> Result<AgentConfig, Error> {
+        self.client
+            .get(&format!("/agents/{}/config", path_segment(agent_id)?), None)
+            .await
+    }
+
+    /// Replaces an agent's runtime configuration, returning the stored revision.
+    pub async fn set_config(
+        &self,
+        agent_id: &str,
+        config: &AgentConfig,
+    ) -> Result<AgentConfig, Error> {
+        self.client
+            .put(
+                &format!("/agents/{}/config", path_segment(agent_id)?),
+                config,
+            )
+            .await
+    }
+
+    /// Streams an agent's configuration as it changes.
+    ///
+    /// The current configuration is yielded first, followed by each new
+    /// revision as it is published. Configurations without a revision are
+    /// compared by content.
+    pub fn config_changes(&self, agent_id: &str) -> impl Stream<Item = Result<AgentConfig, Error>> {
+        let agents = self.clone();
+        let agent_id = agent_id.to_string();
+
+        poll_stream(
+            None,
+            CONFIG_POLL_INTERVAL,
+            move |seen: Option<AgentConfig>| {
+                let agents = agents.clone();
+                let agent_id = agent_id.clone();
+                async move {
+                    let config = agents.get_config(&agent_id).await?;
+                    let changed = seen.as_ref() != Some(&config);
+                    Ok(Batch {
+                        items: if changed {
+                            vec![config.clone()]
+                        } else {
+                            Vec::new()
+                        },
+                        cursor: Some(config),
+                        done: false,
+                    })
+                }
+            },
+        )
+    }
+
+    /// Gets a liveness snapshot of the whole fleet.
+    pub async fn presence(&self) -> Result<Vec<AgentPresence>, Error> {
+        let response: PresenceResponse = self.client.get("/agents/presence", None).await?;
+        Ok(response.agents)
+    }
+
+    /// Streams changes to fleet presence.
+    ///
+    /// Every agent in the first snapshot is reported as [`PresenceChange::Joined`];
+    /// later snapshots are diffed against the previous one.
+    pub fn presence_changes(&self) -> impl Stream<Item = Result<PresenceChange, Error>> {
+        let agents = self.clone();
+
+        poll_stream(
+            HashMap::new(),
+            PRESENCE_POLL_INTERVAL,
+            move |previous: HashMap<String, AgentPresence>| {
+                let agents = agents.clone();
+                async move {
+                    let current: HashMap<String, AgentPresence> = agents
+                        .presence()
+                        .await?
+                        .into_iter()
+                        .map(|p| (p.id.clone(), p))
+                        .collect();
+                    Ok(Batch {
+                        items: diff_presence(&previous, &current),
+                        cursor: current,
+                        done: false,
+                    })
+                }
+            },
+        )
+    }
+
+    /// Gets agent statistics, optionally filtered by type, division, or level.
+    pub async fn stats(&self, opts: Option<AgentStatsOptions>) -> Result<AgentStats, Error> {
+        let mut params = HashMap::new();
+
+        if let Some(opts) = opts {
+            if let Some(t) = opts.agent_type {
+                params.insert("type".to_string(), t);
+            }
+            if let Some(d) = opts.division {
+                params.insert("division".to_string(), d);
+            }
+            if let Some(l) = opts.level {
+                params.insert("level".to_string(), l.to_string());
+            }
+        }
+
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        self.client.get("/agents/stats", params_opt).await
+    }
+
+    /// Computes an autoscaling signal for a division (or the whole fleet).
+    ///
+    /// Pending tasks are treated as demand for one unit of agent capacity each.
+    /// The recommended delta never scales the active fleet below one agent.
+    pub async fn pressure(&self, division: Option<&str>) -> Result<CapacitySignal, Error> {
+        let agent_opts = AgentStatsOptions {
+            division: division.map(str::to_string),
+            ..Default::default()
+        };
+        let task_opts = TaskStatsOptions {
+            division: division.map(str::to_string),
+            ..Default::default()
+        };
+        let tasks = TaskAPI::new(self.client.clone());
+        let (agent_stats, task_stats) =
+            tokio::try_join!(self.stats(Some(agent_opts)), tasks.stats(Some(task_opts)))?;
+
+        let queue_depth = task_stats
+            .pending
+            .or_else(|| {
+                task_stats
+                    .by_status
+                    .as_ref()
+                    .and_then(|s| s.get("pending").copied())
+            })
+            .unwrap_or(0);
+        let active_agents = agent_stats.active.unwrap_or(agent_stats.total);
+        let avg_load = agent_stats.avg_load.unwrap_or(0.0).clamp(0.0, 1.0);
+        let available_capacity = active_agents as f64 * (1.0 - avg_load);
+
+        let shortfall = queue_depth as f64 - available_capacity;
+        let recommended_delta = if shortfall > 0.0 {
+            shortfall.ceil() as i64
+        } else {
+            let removable = (active_agents - 1).max(0);
+            -((-shortfall).floor() as i64).min(removable)
+        };
+
+        Ok(CapacitySignal {
+            division: division.map(str::to_string),
+            queue_depth,
+            active_agents,
+            avg_load,
+            available_capacity,
+            recommended_delta,
+        })
+    }
+
+    /// Picks the least-loaded active agent matching `opts`.
+    ///
+    /// Shorthand for [`pick`](Self::pick) with [`LeastLoaded`].
+    pub async fn pick_least_loaded(
+        &self,
+        opts: Option<AgentListOptions>,
+    ) -> Result<Option<Agent>, Error> {
+        self.pick(opts, &LeastLoaded::default()).await
+    }
+
+    /// Picks an agent matching `opts` using `strategy`.
+    ///
+    /// Only `active` agents are considered unless `opts.status` says otherwise.
+    /// Every page of matches is passed to `strategy`; `opts.limit` sets the
+    /// page size, not a cap on the candidates.
+    pub async fn pick(
+        &self,
+        opts: Option<AgentListOptions>,
+        strategy: &dyn SelectionStrategy,
+    ) -> Result<Option<Agent>, Error> {
+        let mut opts = opts.unwrap_or_default();
+        if opts.status.is_none() {
+            opts.status = Some(AgentStatus::Active);
+        }
+        let candidates: Vec<Agent> = self.list_all(Some(opts)).try_collect().await?;
+        Ok(strategy.select(&candidates).cloned())
     }
 
     /// Gets agents by division.
@@ -119,6 +730,15 @@ impl AgentAPI {
         .await
     }
 
+    /// Gets agents that declare `capability`, e.g. `"gpu"`.
+    pub async fn with_capability(&self, capability: &str) -> Result<Vec<Agent>, Error> {
+        self.list(Some(AgentListOptions {
+            capability: Some(capability.to_string()),
+            ..Default::default()
+        }))
+        .await
+    }
+
     /// Gets Level 2 commander agents.
     pub async fn commanders(&self) -> Result<Vec<Agent>, Error> {
         self.list(Some(AgentListOptions {
@@ -146,3 +766,184 @@ impl AgentAPI {
         .await
     }
 }
+
+fn diff_presence(
+    previous: &HashMap<String, AgentPresence>,
+    current: &HashMap<String, AgentPresence>,
+) -> Vec<PresenceChange> {
+    let mut changes = Vec::new();
+    for (id, presence) in current {
+        match previous.get(id) {
+            None => changes.push(PresenceChange::Joined(presence.clone())),
+            Some(prev) if prev.online != presence.online => {
+                if presence.online {
+                    changes.push(PresenceChange::Online(presence.clone()));
+                } else {
+                    changes.push(PresenceChange::Offline(presence.clone()));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    for id in previous.keys() {
+        if !current.contains_key(id) {
+            changes.push(PresenceChange::Left(id.clone()));
+        }
+    }
+    changes
+}
+
+/// How an [`AgentSession`] reacts to heartbeats that keep failing, passed to
+/// [`AgentAPI::start_session_with`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct HeartbeatPolicy {
+    /// Consecutive failures after which the session is degraded. Defaults
+    /// to 3.
+    pub failure_threshold: u32,
+    /// Registers the agent again with these options when a heartbeat finds
+    /// it deleted (404), and sends later heartbeats for the new registration.
+    /// Disabled by default.
+    pub reregister: Option<RegisterAgentOptions>,
+    /// Told when the session becomes degraded, recovers, or re-registers.
+    pub observer: Option<Arc<dyn HeartbeatObserver>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            reregister: None,
+            observer: None,
+        }
+    }
+}
+
+/// Hook for changes in an [`AgentSession`]'s health, installed with
+/// [`HeartbeatPolicy::observer`]. Called from the session's task, so keep it
+/// cheap.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait HeartbeatObserver: Send + Sync + fmt::Debug {
+    /// Called once `failure_threshold` heartbeats in a row have failed.
+    fn on_degraded(&self, _agent_id: &str, _error: &Error) {}
+
+    /// Called when a heartbeat succeeds after the session was degraded.
+    fn on_recovered(&self, _agent_id: &str) {}
+
+    /// Called after a deleted agent was registered again as `agent`.
+    fn on_reregistered(&self, _previous_id: &str, _agent: &Agent) {}
+}
+
+/// Background heartbeats for one agent, started with
+/// [`AgentAPI::start_session`]. Heartbeats stop when the session is dropped.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct AgentSession {
+    state: Arc<SessionState>,
+    errors: mpsc::Receiver<Error>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// State shared between an [`AgentSession`] and its heartbeat task.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+struct SessionState {
+    agent_id: Mutex<String>,
+    load: AtomicU64,
+    degraded: AtomicBool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SessionState {
+    fn agent_id(&self) -> String {
+        self.agent_id.lock().unwrap().clone()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AgentSession {
+    /// The agent this session sends heartbeats for. Changes if the session
+    /// re-registers a deleted agent.
+    pub fn agent_id(&self) -> String {
+        self.state.agent_id()
+    }
+
+    /// Sets the load reported by subsequent heartbeats.
+    pub fn set_load(&self, load: f64) {
+        self.state.load.store(load.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns true while the last `failure_threshold` or more heartbeats
+    /// have failed, so the host can report the agent as degraded.
+    pub fn is_degraded(&self) -> bool {
+        self.state.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Waits for the next failed heartbeat. Up to 16 failures are held while
+    /// nobody is waiting; later ones are dropped.
+    pub async fn next_error(&mut self) -> Option<Error> {
+        self.errors.recv().await
+    }
+
+    /// Stops sending heartbeats.
+    pub fn stop(self) {}
+
+    /// Stops sending heartbeats and waits until one in flight is dropped.
+    pub(crate) async fn close(mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for AgentSession {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn list_params(opts: Option<AgentListOptions>, scope: &Scope) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+
+    if let Some(opts) = opts {
+        if let Some(t) = opts.agent_type {
+            params.insert("type".to_string(), t);
+        }
+        if let Some(d) = opts.division {
+            params.insert("division".to_string(), d);
+        }
+        if let Some(l) = opts.level {
+            params.insert("level".to_string(), l.to_string());
+        }
+        if let Some(s) = opts.status {
+            params.insert("status".to_string(), s.to_string());
+        }
+        if let Some(c) = opts.capability {
+            params.insert("capability".to_string(), c);
+        }
+        if let Some(t) = opts.seen_before {
+            params.insert("last_seen_before".to_string(), t.to_rfc3339());
+        }
+        if let Some(d) = opts.include_deleted {
+            params.insert("include_deleted".to_string(), d.to_string());
+        }
+        if let Some(l) = opts.limit {
+            params.insert("limit".to_string(), l.to_string());
+        }
+        if let Some(o) = opts.offset {
+            params.insert("offset".to_string(), o.to_string());
+        }
+        if let Some(c) = opts.cursor {
+            params.insert("cursor".to_string(), c);
+        }
+    }
+
+    if let Some(d) = &scope.division {
+        params
+            .entry("division".to_string())
+            .or_insert_with(|| d.clone());
+    }
+
+    params
+}
@@ -0,0 +1,79 @@
+use crate::errors::Error;
+use crate::types::Agent;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const MACHINE_ID_PATHS: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// Proof of an agent's identity, sent with registration and heartbeats.
+///
+/// The fingerprint identifies the machine; the signature is an HMAC-SHA256
+/// over a server-issued nonce and the fingerprint, keyed with the agent's
+/// secret. The platform records the fingerprint in [`Agent::hash`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub fingerprint: String,
+    pub nonce: String,
+    pub signature: String,
+}
+
+impl Attestation {
+    /// Signs `nonce` for a machine with the given fingerprint.
+    pub fn new(fingerprint: impl Into<String>, nonce: impl Into<String>, secret: &[u8]) -> Self {
+        let fingerprint = fingerprint.into();
+        let nonce = nonce.into();
+        let signature = hex::encode(mac(secret, &nonce, &fingerprint).finalize().into_bytes());
+        Self {
+            fingerprint,
+            nonce,
+            signature,
+        }
+    }
+
+    /// Signs `nonce` for this machine, using [`machine_fingerprint`](Self::machine_fingerprint).
+    pub async fn for_this_machine(nonce: impl Into<String>, secret: &[u8]) -> Result<Self, Error> {
+        Ok(Self::new(Self::machine_fingerprint().await?, nonce, secret))
+    }
+
+    /// Returns a stable fingerprint for this machine: the SHA-256 of its
+    /// systemd/D-Bus machine ID.
+    pub async fn machine_fingerprint() -> Result<String, Error> {
+        let mut last_error = None;
+        for path in MACHINE_ID_PATHS {
+            match crate::rt::fs::read_to_string(path).await {
+                Ok(id) if !id.trim().is_empty() => {
+                    return Ok(hex::encode(Sha256::digest(id.trim().as_bytes())));
+                }
+                Ok(_) => {}
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error
+            .map(Error::Io)
+            .unwrap_or_else(|| Error::Io(std::io::ErrorKind::NotFound.into())))
+    }
+
+    /// Returns true if the signature matches `secret`.
+    pub fn verify(&self, secret: &[u8]) -> bool {
+        let mac = mac(secret, &self.nonce, &self.fingerprint);
+        hex::decode(&self.signature)
+            .map(|signature| mac.verify_slice(&signature).is_ok())
+            .unwrap_or(false)
+    }
+
+    /// Returns true if `agent` was registered from the machine this
+    /// attestation describes.
+    pub fn matches(&self, agent: &Agent) -> bool {
+        agent.hash.as_deref() == Some(self.fingerprint.as_str())
+    }
+}
+
+fn mac(secret: &[u8], nonce: &str, fingerprint: &str) -> Hmac<Sha256> {
+    // HMAC accepts keys of any length, so this cannot fail.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(nonce.as_bytes());
+    mac.update(&[0]);
+    mac.update(fingerprint.as_bytes());
+    mac
+}
@@ -0,0 +1,401 @@
+use crate::errors::Error;
+use crate::rt::Instant;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const CLIENT_CREDENTIALS_GRANT: &str = "client_credentials";
+const TOKEN_EXCHANGE_GRANT: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+const JWT_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:jwt";
+const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+const KUBERNETES_TOKEN_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+const DEFAULT_REFRESH_SKEW: Duration = Duration::from_secs(60);
+/// Longest wait for a token, including any fetch already in flight.
+const DEFAULT_TOKEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Supplies the bearer token sent with every API request.
+///
+/// Implementations are called before each request attempt and should cache
+/// tokens internally.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait AuthProvider: Send + Sync + fmt::Debug {
+    /// Returns a bearer token for the `Authorization` header.
+    async fn credentials(&self) -> Result<String, Error>;
+
+    /// Called when the API rejects `token` with `401 Unauthorized`, e.g.
+    /// because it was revoked before its expiry. Drop it from any cache so the
+    /// next call to [`credentials`](Self::credentials) fetches a new one; the
+    /// client then retries the request once.
+    async fn invalidate(&self, _token: &str) {}
+}
+
+/// A long-lived API key.
+#[derive(Clone)]
+pub struct StaticApiKey(String);
+
+impl StaticApiKey {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self(api_key.into())
+    }
+}
+
+impl fmt::Debug for StaticApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StaticApiKey").field(&"<redacted>").finish()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl AuthProvider for StaticApiKey {
+    async fn credentials(&self) -> Result<String, Error> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Where an [`OidcTokenExchange`] reads the workload identity token from.
+#[derive(Debug, Clone)]
+pub enum SubjectToken {
+    /// A file re-read on every exchange, e.g. a projected service account token.
+    File(PathBuf),
+    /// An environment variable read on every exchange.
+    Env(String),
+}
+
+impl SubjectToken {
+    async fn read(&self) -> Result<String, Error> {
+        let token = match self {
+            SubjectToken::File(path) => crate::rt::fs::read_to_string(path).await.map_err(|e| {
+                Error::Authentication(format!(
+                    "failed to read subject token from {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?,
+            SubjectToken::Env(var) => std::env::var(var).map_err(|_| {
+                Error::Authentication(format!("subject token variable {} is not set", var))
+            })?,
+        };
+        Ok(token.trim().to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// A token issued by an OAuth 2.0 token endpoint, kept until shortly before
+/// it expires.
+#[derive(Default)]
+struct TokenCache {
+    cached: Mutex<Option<(String, Option<Instant>)>>,
+}
+
+impl TokenCache {
+    /// Returns the cached token, calling `fetch` when there is none or it is
+    /// due for refresh. Concurrent callers wait for a single fetch.
+    ///
+    /// Waiting for another caller's fetch and running `fetch` are each
+    /// limited to `timeout`, so a hung token endpoint fails requests instead
+    /// of blocking them.
+    async fn get<F, Fut>(&self, timeout: Duration, fetch: F) -> Result<String, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(String, Option<Instant>), Error>>,
+    {
+        let mut cached = tokio::select! {
+            cached = self.cached.lock() => cached,
+            _ = crate::rt::sleep(timeout) => return Err(timed_out(timeout)),
+        };
+        if let Some((token, refresh_at)) = cached.as_ref() {
+            let expired = matches!(refresh_at, Some(at) if Instant::now() >= *at);
+            if !expired {
+                return Ok(token.clone());
+            }
+        }
+        let (token, refresh_at) = tokio::select! {
+            fetched = fetch() => fetched?,
+            _ = crate::rt::sleep(timeout) => return Err(timed_out(timeout)),
+        };
+        *cached = Some((token.clone(), refresh_at));
+        Ok(token)
+    }
+
+    /// Drops the cached token if it is `token`, leaving a newer one alone.
+    async fn invalidate(&self, token: &str) {
+        let mut cached = self.cached.lock().await;
+        if matches!(cached.as_ref(), Some((cached, _)) if cached == token) {
+            *cached = None;
+        }
+    }
+}
+
+fn timed_out(timeout: Duration) -> Error {
+    Error::Connection(format!("no access token within {:?}", timeout))
+}
+
+/// Posts a token request and returns the token with its refresh time.
+/// `what` names the flow in error messages.
+async fn request_token(
+    request: reqwest::RequestBuilder,
+    refresh_skew: Duration,
+    what: &str,
+) -> Result<(String, Option<Instant>), Error> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| Error::Connection(format!("{} failed: {}", what, e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::Authentication(format!(
+            "{} rejected ({}): {}",
+            what,
+            status.as_u16(),
+            body
+        )));
+    }
+
+    let token: TokenResponse = crate::encoding::decode_json(&response.bytes().await?)?;
+    // A lifetime too long to represent never needs refreshing.
+    let refresh_at = token.expires_in.and_then(|secs| {
+        Instant::now().checked_add(Duration::from_secs(secs).saturating_sub(refresh_skew))
+    });
+    Ok((token.access_token, refresh_at))
+}
+
+/// Exchanges a workload identity token (Kubernetes service account JWT or a
+/// cloud OIDC token) for a BlackRoad API token using OAuth 2.0 Token Exchange
+/// (RFC 8693), refreshing shortly before the issued token expires.
+pub struct OidcTokenExchange {
+    token_url: String,
+    subject_token: SubjectToken,
+    audience: Option<String>,
+    scope: Option<String>,
+    refresh_skew: Duration,
+    timeout: Duration,
+    http_client: reqwest::Client,
+    cache: TokenCache,
+}
+
+impl OidcTokenExchange {
+    /// Creates a provider that posts `subject_token` to `token_url`.
+    pub fn new(token_url: impl Into<String>, subject_token: SubjectToken) -> Self {
+        Self {
+            token_url: token_url.into(),
+            subject_token,
+            audience: None,
+            scope: None,
+            refresh_skew: DEFAULT_REFRESH_SKEW,
+            timeout: DEFAULT_TOKEN_TIMEOUT,
+            http_client: reqwest::Client::new(),
+            cache: TokenCache::default(),
+        }
+    }
+
+    /// Creates a provider using the pod's Kubernetes service account token.
+    pub fn kubernetes(token_url: impl Into<String>) -> Self {
+        Self::new(
+            token_url,
+            SubjectToken::File(PathBuf::from(KUBERNETES_TOKEN_PATH)),
+        )
+    }
+
+    /// Sets the `audience` parameter of the exchange request.
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    /// Sets the `scope` parameter of the exchange request.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Sets how long before expiry the token is refreshed. Defaults to 60 seconds.
+    pub fn refresh_before(mut self, skew: Duration) -> Self {
+        self.refresh_skew = skew;
+        self
+    }
+
+    /// Sets how long an API request waits for a token before failing,
+    /// including a refresh already in flight. Defaults to 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends token requests through `client` instead of a default one, e.g.
+    /// to trust a private CA or present a client certificate to the token
+    /// endpoint.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    async fn exchange(&self) -> Result<(String, Option<Instant>), Error> {
+        let subject_token = self.subject_token.read().await?;
+        let mut form = vec![
+            ("grant_type", TOKEN_EXCHANGE_GRANT.to_string()),
+            ("subject_token", subject_token),
+            ("subject_token_type", JWT_TOKEN_TYPE.to_string()),
+            ("requested_token_type", ACCESS_TOKEN_TYPE.to_string()),
+        ];
+        if let Some(audience) = &self.audience {
+            form.push(("audience", audience.clone()));
+        }
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope.clone()));
+        }
+        let request = self.http_client.post(&self.token_url).form(&form);
+        request_token(request, self.refresh_skew, "token exchange").await
+    }
+}
+
+impl fmt::Debug for OidcTokenExchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OidcTokenExchange")
+            .field("token_url", &self.token_url)
+            .field("subject_token", &self.subject_token)
+            .field("audience", &self.audience)
+            .field("scope", &self.scope)
+            .field("refresh_skew", &self.refresh_skew)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl AuthProvider for OidcTokenExchange {
+    async fn credentials(&self) -> Result<String, Error> {
+        self.cache.get(self.timeout, || self.exchange()).await
+    }
+
+    async fn invalidate(&self, token: &str) {
+        self.cache.invalidate(token).await;
+    }
+}
+
+/// Obtains short-lived API tokens with the OAuth 2.0 client credentials grant
+/// (RFC 6749, section 4.4), refreshing shortly before each token expires.
+///
+/// The client ID and secret are sent with HTTP Basic authentication.
+pub struct ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    audience: Option<String>,
+    scope: Option<String>,
+    refresh_skew: Duration,
+    timeout: Duration,
+    http_client: reqwest::Client,
+    cache: TokenCache,
+}
+
+impl ClientCredentials {
+    /// Creates a provider that requests tokens from `token_url`.
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            audience: None,
+            scope: None,
+            refresh_skew: DEFAULT_REFRESH_SKEW,
+            timeout: DEFAULT_TOKEN_TIMEOUT,
+            http_client: reqwest::Client::new(),
+            cache: TokenCache::default(),
+        }
+    }
+
+    /// Sets the `audience` parameter of the token request.
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    /// Sets the `scope` parameter of the token request.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Sets how long before expiry the token is refreshed. Defaults to 60 seconds.
+    pub fn refresh_before(mut self, skew: Duration) -> Self {
+        self.refresh_skew = skew;
+        self
+    }
+
+    /// Sets how long an API request waits for a token before failing,
+    /// including a refresh already in flight. Defaults to 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends token requests through `client` instead of a default one, e.g.
+    /// to trust a private CA or present a client certificate to the token
+    /// endpoint.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    async fn fetch(&self) -> Result<(String, Option<Instant>), Error> {
+        let mut form = vec![("grant_type", CLIENT_CREDENTIALS_GRANT.to_string())];
+        if let Some(audience) = &self.audience {
+            form.push(("audience", audience.clone()));
+        }
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope.clone()));
+        }
+
+        let request = self
+            .http_client
+            .post(&self.token_url)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&form);
+        request_token(request, self.refresh_skew, "token request").await
+    }
+}
+
+impl fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("audience", &self.audience)
+            .field("scope", &self.scope)
+            .field("refresh_skew", &self.refresh_skew)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl AuthProvider for ClientCredentials {
+    async fn credentials(&self) -> Result<String, Error> {
+        self.cache.get(self.timeout, || self.fetch()).await
+    }
+
+    async fn invalidate(&self, token: &str) {
+        self.cache.invalidate(token).await;
+    }
+}
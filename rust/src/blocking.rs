@@ -0,0 +1,530 @@
+//! A blocking client for programs without an async runtime, such as CLI tools
+//! and scripts.
+//!
+//! Each client owns a small Tokio runtime and drives the async client on it,
+//! like `reqwest::blocking`. Methods mirror the async API of the same name.
+//! Watch streams (`config_changes`, `presence_changes`, `stream_output`, and
+//! so on) are async-only; the `list_all` walks are offered as iterators.
+//!
+//! Do not call these methods from inside an async runtime: they block the
+//! thread and panic when nested in another runtime.
+//!
+//! ```rust,no_run
+//! use blackroad::blocking::BlackRoadClient;
+//! use blackroad::ClientConfig;
+//!
+//! let client = BlackRoadClient::new(ClientConfig::default())?;
+//! for agent in client.agents().list(None)? {
+//!     println!("{} ({})", agent.name, agent.status);
+//! }
+//! # Ok::<(), blackroad::Error>(())
+//! ```
+
+use crate::errors::Error;
+use crate::pinning::ChainHeadKey;
+use crate::selection::SelectionStrategy;
+use crate::types::{
+    Agent, AgentConfig, AgentListOptions, AgentPresence, AgentStats, AgentStatsOptions,
+    AgentStatus, Attachment, BroadcastStatus, BulkResult, CapacitySignal, ChainHead, ClaimOptions,
+    CreateDivisionOptions, DispatchTaskOptions, Division, DivisionStats, ExportFormat, Faceted,
+    HealthStatus, HeartbeatOptions, ImportReport, LogMemoryOptions, MemoryEntry, MemoryPage,
+    MemoryQueryOptions, MemoryStats, MemoryStatsOptions, Organization, Page, PeekOptions,
+    PinVerification, Project, RegisterAgentOptions, Schedule, ScheduleSpec, ScoredMemoryEntry,
+    SlaBreachOptions, Snapshot, StateDelta, Task, TaskEvent, TaskGraph, TaskListOptions,
+    TaskLogLine, TaskOutputChunk, TaskStats, TaskStatsOptions, Timeline, TimelineOptions,
+    UpdateAgentOptions, UpdateDivisionOptions, VerifyChainResult, WaitOptions,
+};
+use crate::ClientConfig;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Generates blocking methods that forward to the async method of the same
+/// name on `self.inner`.
+macro_rules! blocking {
+    ($(
+        $(#[$meta:meta])*
+        fn $name:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) -> $ret:ty;
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                self.runtime.block_on(self.inner.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+/// Turns a stream into an iterator that blocks on each item.
+fn iterate<T>(
+    runtime: Arc<Runtime>,
+    stream: impl Stream<Item = Result<T, Error>> + Send + 'static,
+) -> impl Iterator<Item = Result<T, Error>> {
+    let mut stream = Box::pin(stream);
+    std::iter::from_fn(move || runtime.block_on(stream.next()))
+}
+
+/// The blocking BlackRoad API client.
+#[derive(Debug, Clone)]
+pub struct BlackRoadClient {
+    inner: crate::BlackRoadClient,
+    runtime: Arc<Runtime>,
+}
+
+impl BlackRoadClient {
+    /// Creates a new blocking client.
+    pub fn new(config: ClientConfig) -> Result<Self, Error> {
+        Self::from_async(crate::BlackRoadClient::new(config)?)
+    }
+
+    /// Wraps an existing async client, e.g. one derived with
+    /// [`scoped`](crate::BlackRoadClient::scoped).
+    pub fn from_async(client: crate::BlackRoadClient) -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            inner: client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Returns the async client this one drives.
+    pub fn as_async(&self) -> &crate::BlackRoadClient {
+        &self.inner
+    }
+
+    /// Returns the agents API.
+    pub fn agents(&self) -> AgentAPI {
+        AgentAPI {
+            inner: self.inner.agents(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Returns the tasks API.
+    pub fn tasks(&self) -> TaskAPI {
+        TaskAPI {
+            inner: self.inner.tasks(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Returns the memory API.
+    pub fn memory(&self) -> MemoryAPI {
+        MemoryAPI {
+            inner: self.inner.memory(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Returns the divisions API.
+    pub fn divisions(&self) -> DivisionAPI {
+        DivisionAPI {
+            inner: self.inner.divisions(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Returns the organizations API.
+    pub fn organizations(&self) -> OrganizationAPI {
+        OrganizationAPI {
+            inner: self.inner.organizations(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    blocking! {
+        /// Checks API health.
+        fn health(&self) -> Result<HealthStatus, Error>;
+        /// Gets the API version.
+        fn version(&self) -> Result<String, Error>;
+    }
+}
+
+/// Blocking API for managing agents.
+#[derive(Debug, Clone)]
+pub struct AgentAPI {
+    inner: crate::AgentAPI,
+    runtime: Arc<Runtime>,
+}
+
+impl AgentAPI {
+    /// Iterates over every agent matching `opts`, fetching pages as needed.
+    pub fn list_all(
+        &self,
+        opts: Option<AgentListOptions>,
+    ) -> impl Iterator<Item = Result<Agent, Error>> {
+        iterate(self.runtime.clone(), self.inner.list_all(opts))
+    }
+
+    /// Gets several agents concurrently, in the order of `agent_ids`.
+    pub fn get_many<I, S>(&self, agent_ids: I) -> Vec<BulkResult<Agent>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.runtime.block_on(self.inner.get_many(agent_ids))
+    }
+
+    blocking! {
+        /// Lists agents with optional filters.
+        fn list(&self, opts: Option<AgentListOptions>) -> Result<Vec<Agent>, Error>;
+        /// Lists agents with the total count and paging position.
+        fn list_page(&self, opts: Option<AgentListOptions>) -> Result<Page<Agent>, Error>;
+        /// Lists agents, also counting matches per value of each facet.
+        fn list_with_facets(
+            &self,
+            opts: Option<AgentListOptions>,
+            facets: &[&str],
+        ) -> Result<Faceted<Agent>, Error>;
+        /// Gets a specific agent by ID.
+        fn get(&self, agent_id: &str) -> Result<Agent, Error>;
+        /// Lists agents, falling back to the last-known result if the API is
+        /// unreachable.
+        fn list_snapshot(
+            &self,
+            opts: Option<AgentListOptions>,
+        ) -> Result<Snapshot<Vec<Agent>>, Error>;
+        /// Gets an agent, falling back to the last-known result if the API is
+        /// unreachable.
+        fn get_snapshot(&self, agent_id: &str) -> Result<Snapshot<Agent>, Error>;
+        /// Gets a single-use nonce to sign into an attestation.
+        fn attestation_nonce(&self) -> Result<String, Error>;
+        /// Registers a new agent.
+        fn register(&self, opts: RegisterAgentOptions) -> Result<Agent, Error>;
+        /// Sends a heartbeat for an agent.
+        fn heartbeat(&self, agent_id: &str, load: Option<f64>) -> Result<(), Error>;
+        /// Sends a heartbeat for an agent, reporting load and software version.
+        fn heartbeat_with(&self, agent_id: &str, opts: HeartbeatOptions) -> Result<(), Error>;
+        /// Lists agents reporting a version older than `min_version`.
+        fn outdated(&self, min_version: &str) -> Result<Vec<Agent>, Error>;
+        /// Asks an agent to upgrade to `target_version` on its next heartbeat.
+        fn request_update(&self, agent_id: &str, target_version: &str) -> Result<Agent, Error>;
+        /// Changes an agent's name, division, level, or metadata in place.
+        fn update(&self, agent_id: &str, opts: UpdateAgentOptions) -> Result<Agent, Error>;
+        /// Replaces the capabilities an agent declares.
+        fn update_capabilities(&self, agent_id: &str, capabilities: Vec<String>) -> Result<Agent, Error>;
+        /// Updates an agent's status.
+        fn update_status(&self, agent_id: &str, status: AgentStatus) -> Result<Agent, Error>;
+        /// Updates the status of every agent matching `opts`.
+        fn update_status_where(
+            &self,
+            opts: AgentListOptions,
+            status: AgentStatus,
+        ) -> Result<Vec<BulkResult<Agent>>, Error>;
+        /// Pauses an agent.
+        fn pause(&self, agent_id: &str) -> Result<Agent, Error>;
+        /// Resumes a paused agent.
+        fn resume(&self, agent_id: &str) -> Result<Agent, Error>;
+        /// Deletes an agent.
+        fn delete(&self, agent_id: &str) -> Result<(), Error>;
+        /// Restores a deleted agent.
+        fn restore(&self, agent_id: &str) -> Result<Agent, Error>;
+        /// Deletes every agent matching `opts`.
+        fn delete_where(
+            &self,
+            opts: AgentListOptions,
+            dry_run: bool,
+        ) -> Result<Vec<BulkResult<Agent>>, Error>;
+        /// Gets an agent's configuration.
+        fn get_config(&self, agent_id: &str) -> Result<AgentConfig, Error>;
+        /// Replaces an agent's configuration.
+        fn set_config(&self, agent_id: &str, config: &AgentConfig) -> Result<AgentConfig, Error>;
+        /// Gets the presence of every agent.
+        fn presence(&self) -> Result<Vec<AgentPresence>, Error>;
+        /// Gets agent statistics.
+        fn stats(&self, opts: Option<AgentStatsOptions>) -> Result<AgentStats, Error>;
+        /// Computes a scaling signal from queue depth and agent load.
+        fn pressure(&self, division: Option<&str>) -> Result<CapacitySignal, Error>;
+        /// Picks the least-loaded agent matching `opts`.
+        fn pick_least_loaded(&self, opts: Option<AgentListOptions>) -> Result<Option<Agent>, Error>;
+        /// Picks an agent matching `opts` with a custom strategy.
+        fn pick(
+            &self,
+            opts: Option<AgentListOptions>,
+            strategy: &dyn SelectionStrategy,
+        ) -> Result<Option<Agent>, Error>;
+        /// Lists agents in a division.
+        fn by_division(&self, division: &str) -> Result<Vec<Agent>, Error>;
+        /// Lists agents that declare a capability.
+        fn with_capability(&self, capability: &str) -> Result<Vec<Agent>, Error>;
+        /// Lists level 2 commander agents.
+        fn commanders(&self) -> Result<Vec<Agent>, Error>;
+        /// Lists level 3 manager agents.
+        fn managers(&self) -> Result<Vec<Agent>, Error>;
+        /// Lists level 4 worker agents.
+        fn workers(&self) -> Result<Vec<Agent>, Error>;
+    }
+}
+
+/// Blocking API for managing tasks.
+#[derive(Debug, Clone)]
+pub struct TaskAPI {
+    inner: crate::TaskAPI,
+    runtime: Arc<Runtime>,
+}
+
+impl TaskAPI {
+    /// Iterates over every task matching `opts`, fetching pages as needed.
+    pub fn list_all(
+        &self,
+        opts: Option<TaskListOptions>,
+    ) -> impl Iterator<Item = Result<Task, Error>> {
+        iterate(self.runtime.clone(), self.inner.list_all(opts))
+    }
+
+    /// Gets several tasks concurrently, in the order of `task_ids`.
+    pub fn get_many<I, S>(&self, task_ids: I) -> Vec<BulkResult<Task>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.runtime.block_on(self.inner.get_many(task_ids))
+    }
+
+    /// Marks a task as completed with a structured result.
+    pub fn complete_with<T: Serialize>(&self, task_id: &str, result: &T) -> Result<Task, Error> {
+        self.runtime
+            .block_on(self.inner.complete_with(task_id, result))
+    }
+
+    blocking! {
+        /// Dispatches a new task.
+        fn dispatch(&self, opts: DispatchTaskOptions) -> Result<Task, Error>;
+        /// Dispatches many tasks, returning one result per task.
+        fn dispatch_batch(&self, tasks: Vec<DispatchTaskOptions>) -> Vec<Result<Task, Error>>;
+        /// Schedules a task to be dispatched later or on a recurrence.
+        fn schedule(&self, opts: DispatchTaskOptions, spec: ScheduleSpec) -> Result<Schedule, Error>;
+        /// Lists active schedules.
+        fn schedules(&self) -> Result<Vec<Schedule>, Error>;
+        /// Cancels a schedule.
+        fn cancel_schedule(&self, schedule_id: &str) -> Result<(), Error>;
+        /// Gets the dependency graph rooted at a task.
+        fn graph(&self, root_id: &str) -> Result<TaskGraph, Error>;
+        /// Lists the tasks a task depends on.
+        fn dependencies(&self, task_id: &str) -> Result<Vec<Task>, Error>;
+        /// Lists the tasks waiting on a task.
+        fn dependents(&self, task_id: &str) -> Result<Vec<Task>, Error>;
+        /// Gets a specific task by ID.
+        fn get(&self, task_id: &str) -> Result<Task, Error>;
+        /// Lists tasks, falling back to the last-known result if the API is
+        /// unreachable.
+        fn list_snapshot(&self, opts: Option<TaskListOptions>) -> Result<Snapshot<Vec<Task>>, Error>;
+        /// Gets a task, falling back to the last-known result if the API is
+        /// unreachable.
+        fn get_snapshot(&self, task_id: &str) -> Result<Snapshot<Task>, Error>;
+        /// Lists tasks with optional filters.
+        fn list(&self, opts: Option<TaskListOptions>) -> Result<Vec<Task>, Error>;
+        /// Lists tasks with the total count and paging position.
+        fn list_page(&self, opts: Option<TaskListOptions>) -> Result<Page<Task>, Error>;
+        /// Lists tasks, also counting matches per value of each facet.
+        fn list_with_facets(
+            &self,
+            opts: Option<TaskListOptions>,
+            facets: &[&str],
+        ) -> Result<Faceted<Task>, Error>;
+        /// Waits up to `timeout` for a matching task and claims it.
+        fn wait_for_task(
+            &self,
+            agent_id: &str,
+            opts: Option<ClaimOptions>,
+            timeout: Duration,
+        ) -> Result<Option<Task>, Error>;
+        /// Claims the next matching task, or returns `None` if nothing is queued.
+        fn claim_next(&self, agent_id: &str, opts: Option<ClaimOptions>) -> Result<Option<Task>, Error>;
+        /// Extends the lease on a claimed task.
+        fn renew_lease(&self, task_id: &str) -> Result<Task, Error>;
+        /// Polls a task until it reaches a terminal status.
+        fn wait_for_completion(
+            &self,
+            task_id: &str,
+            opts: Option<WaitOptions>,
+        ) -> Result<Task, Error>;
+        /// Returns the task that would be assigned next, without claiming it.
+        fn peek(&self, opts: PeekOptions) -> Result<Option<Task>, Error>;
+        /// Lists tasks that missed an SLA deadline.
+        fn sla_breaches(&self, opts: Option<SlaBreachOptions>) -> Result<Vec<Task>, Error>;
+        /// Marks a task as completed.
+        fn complete(&self, task_id: &str, result: Option<&str>) -> Result<Task, Error>;
+        /// Marks a task as failed.
+        fn fail(&self, task_id: &str, reason: Option<&str>) -> Result<Task, Error>;
+        /// Assigns a task to an agent.
+        fn assign(&self, task_id: &str, agent_id: &str) -> Result<Task, Error>;
+        /// Cancels a task.
+        fn cancel(&self, task_id: &str) -> Result<(), Error>;
+        /// Restores a cancelled task.
+        fn restore(&self, task_id: &str) -> Result<Task, Error>;
+        /// Appends a chunk of output to a task.
+        fn append_output(&self, task_id: &str, chunk: &str) -> Result<TaskOutputChunk, Error>;
+        /// Gets a task's log lines.
+        fn logs(&self, task_id: &str) -> Result<Vec<TaskLogLine>, Error>;
+        /// Gets a task's history of status changes, assignments, and notes.
+        fn events(&self, task_id: &str) -> Result<Vec<TaskEvent>, Error>;
+        /// Gets task statistics.
+        fn stats(&self, opts: Option<TaskStatsOptions>) -> Result<TaskStats, Error>;
+        /// Lists pending tasks.
+        fn pending(&self) -> Result<Vec<Task>, Error>;
+        /// Lists tasks in progress.
+        fn in_progress(&self) -> Result<Vec<Task>, Error>;
+        /// Lists tasks in a division.
+        fn by_division(&self, division: &str) -> Result<Vec<Task>, Error>;
+        /// Lists urgent tasks.
+        fn urgent(&self) -> Result<Vec<Task>, Error>;
+    }
+}
+
+/// Blocking API for memory operations.
+#[derive(Debug, Clone)]
+pub struct MemoryAPI {
+    inner: crate::MemoryAPI,
+    runtime: Arc<Runtime>,
+}
+
+impl MemoryAPI {
+    /// Iterates over every entry matching `opts`, following cursors.
+    pub fn list_all(
+        &self,
+        opts: Option<MemoryQueryOptions>,
+    ) -> impl Iterator<Item = Result<MemoryEntry, Error>> {
+        iterate(self.runtime.clone(), self.inner.list_all(opts))
+    }
+
+    /// Writes every entry matching `opts` to `writer` as JSON Lines or CSV.
+    /// Returns the number of entries written.
+    pub fn export<W: std::io::Write>(
+        &self,
+        opts: Option<MemoryQueryOptions>,
+        format: ExportFormat,
+        writer: W,
+    ) -> Result<u64, Error> {
+        let mut writer = std::io::BufWriter::new(writer);
+        writer.write_all(crate::memory::export_header(format).as_bytes())?;
+        let mut written = 0;
+        for entry in self.list_all(opts) {
+            writer.write_all(&crate::memory::export_record(&entry?, format)?)?;
+            written += 1;
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Logs the JSON Lines in `reader`, one entry per line, in order.
+    pub fn import<R: BufRead>(&self, reader: R) -> ImportReport {
+        let lines = futures::stream::iter(reader.lines());
+        self.runtime.block_on(self.inner.import_lines(lines))
+    }
+
+    blocking! {
+        /// Logs a memory entry.
+        fn log(&self, opts: LogMemoryOptions) -> Result<MemoryEntry, Error>;
+        /// Stores `data` as attachment `name` on an entry.
+        fn attach(
+            &self,
+            entry_hash: &str,
+            name: &str,
+            data: Vec<u8>,
+            content_type: &str,
+        ) -> Result<Attachment, Error>;
+        /// Downloads attachment `name` of an entry.
+        fn download_attachment(&self, entry_hash: &str, name: &str) -> Result<bytes::Bytes, Error>;
+        /// Searches memory by meaning, returning entries with relevance scores.
+        fn search_semantic(&self, opts: MemoryQueryOptions) -> Result<Vec<ScoredMemoryEntry>, Error>;
+        /// Logs entries in order, up to 100 per request.
+        fn log_batch(&self, entries: Vec<LogMemoryOptions>) -> Result<Vec<MemoryEntry>, Error>;
+        /// Queries memory entries.
+        fn query(&self, opts: Option<MemoryQueryOptions>) -> Result<Vec<MemoryEntry>, Error>;
+        /// Queries one page of memory entries.
+        fn query_page(&self, opts: Option<MemoryQueryOptions>) -> Result<MemoryPage, Error>;
+        /// Queries memory, also counting matches per value of each facet.
+        fn query_with_facets(
+            &self,
+            opts: Option<MemoryQueryOptions>,
+            facets: &[&str],
+        ) -> Result<Faceted<MemoryEntry>, Error>;
+        /// Builds a timeline of matching entries.
+        fn timeline(&self, opts: TimelineOptions) -> Result<Timeline, Error>;
+        /// Gets a memory entry by hash.
+        fn get(&self, entry_hash: &str) -> Result<MemoryEntry, Error>;
+        /// Gets recent memory entries.
+        fn recent(&self, limit: Option<i32>) -> Result<Vec<MemoryEntry>, Error>;
+        /// Gets agent state.
+        fn agent_state(&self, agent_id: &str) -> Result<HashMap<String, serde_json::Value>, Error>;
+        /// Syncs agent state.
+        fn sync_state(
+            &self,
+            agent_id: &str,
+            state: HashMap<String, serde_json::Value>,
+        ) -> Result<(), Error>;
+        /// Gets changes to an agent's state since a version.
+        fn state_changes(&self, agent_id: &str, since_version: i64) -> Result<StateDelta, Error>;
+        /// Pushes changes to an agent's state.
+        fn push_state_changes(&self, agent_id: &str, delta: &StateDelta) -> Result<i64, Error>;
+        /// Broadcasts a message to all agents.
+        fn broadcast(&self, msg_type: &str, payload: &str) -> Result<String, Error>;
+        /// Gets the delivery status of a broadcast.
+        fn broadcast_status(&self, broadcast_id: &str) -> Result<BroadcastStatus, Error>;
+        /// Acknowledges a broadcast on behalf of an agent.
+        fn ack(&self, broadcast_id: &str, agent_id: &str) -> Result<(), Error>;
+        /// Logs a TIL (Today I Learned) entry.
+        fn til(&self, category: &str, learning: &str) -> Result<MemoryEntry, Error>;
+        /// Gets memory statistics.
+        fn stats(&self, opts: Option<MemoryStatsOptions>) -> Result<MemoryStats, Error>;
+        /// Verifies hash chain integrity.
+        fn verify_chain(&self, start_hash: Option<&str>) -> Result<VerifyChainResult, Error>;
+        /// Gets the signed head of the hash chain.
+        fn head(&self) -> Result<ChainHead, Error>;
+        /// Checks the chain against an earlier pin signed by `key`.
+        fn verify_against_pin(&self, pin: &ChainHead, key: &ChainHeadKey) -> Result<PinVerification, Error>;
+    }
+}
+
+/// Blocking API for divisions, their members, and rollups.
+#[derive(Debug, Clone)]
+pub struct DivisionAPI {
+    inner: crate::DivisionAPI,
+    runtime: Arc<Runtime>,
+}
+
+impl DivisionAPI {
+    blocking! {
+        /// Lists all divisions.
+        fn list(&self) -> Result<Vec<Division>, Error>;
+        /// Gets a division by name.
+        fn get(&self, division: &str) -> Result<Division, Error>;
+        /// Creates a division.
+        fn create(&self, opts: CreateDivisionOptions) -> Result<Division, Error>;
+        /// Changes a division's name, description, lead, or metadata.
+        fn update(&self, division: &str, opts: UpdateDivisionOptions) -> Result<Division, Error>;
+        /// Deletes a division.
+        fn delete(&self, division: &str) -> Result<(), Error>;
+        /// Lists the agents that belong to a division.
+        fn members(&self, division: &str) -> Result<Vec<Agent>, Error>;
+        /// Gets statistics for a division.
+        fn stats(&self, division: &str) -> Result<DivisionStats, Error>;
+    }
+}
+
+/// Blocking API for organizations and their projects.
+#[derive(Debug, Clone)]
+pub struct OrganizationAPI {
+    inner: crate::OrganizationAPI,
+    runtime: Arc<Runtime>,
+}
+
+impl OrganizationAPI {
+    blocking! {
+        /// Lists the organizations the credentials can access.
+        fn list(&self) -> Result<Vec<Organization>, Error>;
+        /// Gets an organization by ID.
+        fn get(&self, org_id: &str) -> Result<Organization, Error>;
+        /// Lists the projects of an organization.
+        fn list_projects(&self, org_id: &str) -> Result<Vec<Project>, Error>;
+        /// Gets a project of an organization by ID.
+        fn get_project(&self, org_id: &str, project_id: &str) -> Result<Project, Error>;
+    }
+}
@@ -0,0 +1,98 @@
+use crate::errors::Error;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::sync::Mutex;
+
+/// When the client stops sending requests to an API that keeps failing.
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive connection failures or 5xx responses that open the
+    /// circuit. Defaults to 5.
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a single probe request is let
+    /// through. Defaults to 30 seconds, at most one day.
+    pub open_secs: u64,
+}
+
+/// Longest allowed [`CircuitBreakerConfig::open_secs`].
+pub(crate) const MAX_OPEN_SECS: u64 = 24 * 60 * 60;
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_secs: 30,
+        }
+    }
+}
+
+/// Circuit breaker shared by every clone of a client.
+///
+/// Closed, requests flow and failures are counted. Open, requests fail with
+/// [`Error::CircuitOpen`] until the open period ends. Then it is half-open:
+/// one probe is sent, and its outcome closes or reopens the circuit.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    open_for: ChronoDuration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: DateTime<Utc>,
+    },
+    /// A probe is in flight. If it never reports back, e.g. because its
+    /// caller gave up, another probe is allowed after the open period.
+    HalfOpen {
+        probe_started: DateTime<Utc>,
+    },
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            threshold: config.failure_threshold.max(1),
+            open_for: ChronoDuration::seconds(config.open_secs.min(MAX_OPEN_SECS) as i64),
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Fails fast while the circuit is open, and admits one probe once it
+    /// may close again.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        let retry_at = match *state {
+            State::Closed { .. } => return Ok(()),
+            State::Open { until } => until,
+            State::HalfOpen { probe_started } => probe_started + self.open_for,
+        };
+        if now < retry_at {
+            return Err(Error::CircuitOpen { retry_at });
+        }
+        *state = State::HalfOpen { probe_started: now };
+        Ok(())
+    }
+
+    /// Records the outcome of a request that reached, or tried to reach, the
+    /// API. Failures are connection errors and 5xx responses.
+    pub(crate) fn record(&self, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match (&*state, success) {
+            (_, true) => 0,
+            (State::Closed { failures }, false) => failures + 1,
+            (_, false) => self.threshold,
+        };
+        *state = if failures >= self.threshold {
+            State::Open {
+                until: Utc::now() + self.open_for,
+            }
+        } else {
+            State::Closed { failures }
+        };
+    }
+}
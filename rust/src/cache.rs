@@ -0,0 +1,380 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use reqwest::header::{HeaderMap, CACHE_CONTROL, ETAG};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Default size cap for the disk cache: 50 MiB.
+pub(crate) const DEFAULT_CACHE_MAX_BYTES: u64 = 50 * 1024 * 1024;
+/// Longest `max-age` honored, in seconds; larger values, however many
+/// digits, are treated as this (RFC 9111, section 1.2.2).
+const MAX_AGE_CAP: i64 = 1 << 31;
+
+/// A GET response body stored on disk with its validators.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedResponse {
+    pub etag: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub body: String,
+    #[serde(default = "Utc::now")]
+    pub stored_at: DateTime<Utc>,
+}
+
+impl CachedResponse {
+    /// Builds a cache entry from response headers, or `None` if the response
+    /// must not be stored or carries nothing that makes caching useful.
+    pub fn from_headers(headers: &HeaderMap, body: String) -> Option<Self> {
+        let directives = CacheDirectives::parse(headers);
+        if directives.no_store {
+            return None;
+        }
+        let etag = header_str(headers, ETAG);
+        let expires_at = directives.expires_at();
+        if etag.is_none() && expires_at.is_none() {
+            return None;
+        }
+        Some(Self {
+            etag,
+            expires_at,
+            body,
+            stored_at: Utc::now(),
+        })
+    }
+
+    /// Builds an entry that is never served fresh, only as a fallback.
+    pub fn snapshot(body: String) -> Self {
+        Self {
+            etag: None,
+            expires_at: None,
+            body,
+            stored_at: Utc::now(),
+        }
+    }
+
+    /// Returns true if the entry can be served without revalidation.
+    pub fn is_fresh(&self) -> bool {
+        self.expires_at.is_some_and(|at| at > Utc::now())
+    }
+
+    /// Updates the freshness lifetime after a `304 Not Modified`.
+    pub fn refresh(&mut self, headers: &HeaderMap) {
+        self.expires_at = CacheDirectives::parse(headers).expires_at();
+        if let Some(etag) = header_str(headers, ETAG) {
+            self.etag = Some(etag);
+        }
+    }
+}
+
+struct CacheDirectives {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<i64>,
+}
+
+impl CacheDirectives {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut directives = Self {
+            no_store: false,
+            no_cache: false,
+            max_age: None,
+        };
+        let value = header_str(headers, CACHE_CONTROL).unwrap_or_default();
+        for directive in value.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+            if directive == "no-store" {
+                directives.no_store = true;
+            } else if directive == "no-cache" {
+                directives.no_cache = true;
+            } else if let Some(age) = directive.strip_prefix("max-age=") {
+                let digits = !age.is_empty() && age.bytes().all(|b| b.is_ascii_digit());
+                directives.max_age = digits.then(|| {
+                    age.parse::<i64>()
+                        .map_or(MAX_AGE_CAP, |age| age.min(MAX_AGE_CAP))
+                });
+            }
+        }
+        directives
+    }
+
+    fn expires_at(&self) -> Option<DateTime<Utc>> {
+        if self.no_cache {
+            return None;
+        }
+        self.max_age
+            .and_then(|secs| Utc::now().checked_add_signed(ChronoDuration::seconds(secs)))
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Somewhere GET responses are kept between requests.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub(crate) trait ResponseCache: Send + Sync {
+    async fn load(&self, key: &str) -> Option<CachedResponse>;
+    async fn store(&self, key: &str, entry: &CachedResponse);
+}
+
+/// Derives the cache key for a URL. The caller's principal is mixed in so
+/// that clients acting as different identities never share entries.
+pub(crate) fn cache_key(credential: &str, url: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(principal(credential).as_bytes());
+    hasher.update(b"\n");
+    hasher.update(url.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Claims that change when a token is refreshed without changing what it
+/// grants.
+const REFRESH_CLAIMS: [&str; 5] = ["exp", "iat", "nbf", "jti", "auth_time"];
+
+/// Identifies who a credential acts as and what it may see. For a JWT with a
+/// subject this is every claim except [`REFRESH_CLAIMS`], so a refreshed token
+/// keeps using the entries of the one it replaced, while a token for another
+/// audience, scope, or tenant does not. Opaque API keys are their own
+/// principal.
+fn principal(credential: &str) -> String {
+    let mut parts = credential.split('.');
+    let claims = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(_), Some(payload), Some(_), None) => URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .ok()
+            .and_then(|json| {
+                serde_json::from_slice::<BTreeMap<String, serde_json::Value>>(&json).ok()
+            }),
+        _ => None,
+    };
+    match claims {
+        Some(mut claims) if claims.get("sub").is_some_and(|sub| sub.is_string()) => {
+            claims.retain(|name, _| !REFRESH_CLAIMS.contains(&name.as_str()));
+            format!("jwt {}", serde_json::Value::from_iter(claims))
+        }
+        _ => format!("key {}", credential),
+    }
+}
+
+/// In-process cache for GET responses, holding up to `max_entries` and
+/// evicting the least recently used.
+#[derive(Debug)]
+pub(crate) struct MemoryCache {
+    max_entries: usize,
+    /// Most recently used last.
+    entries: Mutex<VecDeque<(String, CachedResponse)>>,
+}
+
+impl MemoryCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ResponseCache for MemoryCache {
+    async fn load(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|(k, _)| k == key)?;
+        let entry = entries.remove(index)?;
+        let response = entry.1.clone();
+        entries.push_back(entry);
+        Some(response)
+    }
+
+    async fn store(&self, key: &str, entry: &CachedResponse) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|(k, _)| k != key);
+        entries.push_back((key.to_string(), entry.clone()));
+        while entries.len() > self.max_entries {
+            entries.pop_front();
+        }
+    }
+}
+
+/// Best-effort on-disk cache for GET responses, shared across processes.
+///
+/// I/O failures are ignored: a broken cache degrades to no cache.
+#[derive(Debug, Clone)]
+pub(crate) struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Removes the least recently written entries until the cache fits its cap.
+    async fn evict(&self) {
+        let mut entries = match Self::entries(&self.dir).await {
+            Some(entries) => entries,
+            None => return,
+        };
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return;
+        }
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if crate::rt::fs::remove_file(&path).await.is_ok() {
+                total -= size;
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn entries(_dir: &Path) -> Option<Vec<(PathBuf, u64, SystemTime)>> {
+        None
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn entries(dir: &Path) -> Option<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut read_dir = tokio::fs::read_dir(dir).await.ok()?;
+        let mut entries = Vec::new();
+        while let Ok(Some(entry)) = read_dir.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            if let Ok(meta) = entry.metadata().await {
+                let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((path, meta.len(), modified));
+            }
+        }
+        Some(entries)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ResponseCache for DiskCache {
+    async fn load(&self, key: &str) -> Option<CachedResponse> {
+        let bytes = crate::rt::fs::read(self.path(key)).await.ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    async fn store(&self, key: &str, entry: &CachedResponse) {
+        let bytes = match serde_json::to_vec(entry) {
+            Ok(bytes) => bytes,
+            Err(_) => return,
+        };
+        if bytes.len() as u64 > self.max_bytes {
+            return;
+        }
+        if crate::rt::fs::create_dir_all(&self.dir).await.is_err() {
+            return;
+        }
+        // Write to a temporary file and rename so concurrent readers never
+        // observe a partially written entry.
+        let tmp = self.dir.join(format!("{}.tmp", key));
+        if crate::rt::fs::write(&tmp, &bytes).await.is_err() {
+            return;
+        }
+        if crate::rt::fs::rename(&tmp, self.path(key)).await.is_err() {
+            let _ = crate::rt::fs::remove_file(&tmp).await;
+            return;
+        }
+        self.evict().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jwt(claims: &str) -> String {
+        format!(
+            "eyJhbGciOiJIUzI1NiJ9.{}.sig",
+            URL_SAFE_NO_PAD.encode(claims)
+        )
+    }
+
+    fn cached(cache_control: &str) -> Option<CachedResponse> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, cache_control.parse().unwrap());
+        CachedResponse::from_headers(&headers, "{}".to_string())
+    }
+
+    #[test]
+    fn caps_max_age() {
+        for max_age in [
+            "max-age=9223372036854775807",
+            "max-age=99999999999999999999999",
+        ] {
+            let expires_at = cached(max_age).unwrap().expires_at.unwrap();
+            let lifetime = expires_at - Utc::now();
+            assert!(lifetime <= ChronoDuration::seconds(MAX_AGE_CAP));
+            assert!(lifetime > ChronoDuration::seconds(MAX_AGE_CAP - 60));
+        }
+    }
+
+    #[test]
+    fn ignores_malformed_max_age() {
+        assert!(cached("max-age=-5").is_none());
+        assert!(cached("max-age=").is_none());
+        assert!(cached("max-age=60").unwrap().is_fresh());
+        assert!(cached("no-cache, max-age=60").is_none());
+    }
+
+    #[test]
+    fn refreshed_tokens_share_entries() {
+        let url = "https://api.blackroad.io/v1/agents";
+        let first = jwt(r#"{"iss":"idp","sub":"svc-1","exp":100}"#);
+        let refreshed = jwt(r#"{"iss":"idp","sub":"svc-1","exp":200}"#);
+        assert_eq!(cache_key(&first, url), cache_key(&refreshed, url));
+    }
+
+    #[test]
+    fn principals_do_not_share_entries() {
+        let url = "https://api.blackroad.io/v1/agents";
+        let one = jwt(r#"{"iss":"idp","sub":"svc-1"}"#);
+        let other = jwt(r#"{"iss":"idp","sub":"svc-2"}"#);
+        assert_ne!(cache_key(&one, url), cache_key(&other, url));
+        assert_ne!(cache_key("key-a", url), cache_key("key-b", url));
+        assert_ne!(
+            cache_key("key-a", url),
+            cache_key("key-a", "https://x/v1/tasks")
+        );
+    }
+
+    #[test]
+    fn audiences_and_scopes_do_not_share_entries() {
+        let url = "https://api.blackroad.io/v1/agents";
+        let read = jwt(r#"{"iss":"idp","sub":"svc-1","aud":"api","scope":"read"}"#);
+        let admin = jwt(r#"{"iss":"idp","sub":"svc-1","aud":"api","scope":"admin"}"#);
+        let other = jwt(r#"{"iss":"idp","sub":"svc-1","aud":"other","scope":"read"}"#);
+        let tenant = jwt(r#"{"iss":"idp","sub":"svc-1","aud":"api","scope":"read","tid":"t2"}"#);
+        assert_ne!(cache_key(&read, url), cache_key(&admin, url));
+        assert_ne!(cache_key(&read, url), cache_key(&other, url));
+        assert_ne!(cache_key(&read, url), cache_key(&tenant, url));
+    }
+
+    #[test]
+    fn tokens_without_a_subject_are_keyed_by_value() {
+        let url = "https://api.blackroad.io/v1/agents";
+        let first = jwt(r#"{"iss":"idp","exp":100}"#);
+        let second = jwt(r#"{"iss":"idp","exp":200}"#);
+        assert_ne!(cache_key(&first, url), cache_key(&second, url));
+    }
+}
@@ -1,50 +1,332 @@
-use crate::errors::Error;
-use crate::types::HealthStatus;
-use crate::{AgentAPI, MemoryAPI, TaskAPI};
-use reqwest::{Client, Response, StatusCode};
+use crate::auth::{AuthProvider, StaticApiKey};
+use crate::breaker::{CircuitBreaker, CircuitBreakerConfig, MAX_OPEN_SECS};
+use crate::cache::{
+    cache_key, CachedResponse, DiskCache, MemoryCache, ResponseCache, DEFAULT_CACHE_MAX_BYTES,
+};
+use crate::encoding::{JsonCodec, WireFormat};
+use crate::errors::{Error, ValidationErrorBody};
+use crate::metrics::{MetricsSink, RequestMetrics};
+use crate::middleware::{Interceptor, ResponseInfo};
+use crate::polling::StreamTransport;
+use crate::queue::{RequestPriority, RequestQueue, RequestQueueConfig};
+use crate::ratelimit::{RateLimiter, RateLimiterConfig};
+use crate::retry::RetryObserver;
+use crate::rt::Instant;
+use crate::tls::ClientIdentity;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::tls::TlsSettings;
+use crate::transport::{HttpTransport, Transport};
+use crate::types::{HealthChange, HealthStatus, RateLimitStatus, ServiceTransition, Snapshot};
+#[cfg(feature = "tracing")]
+use crate::wirelog::{WireLogConfig, WireLogger};
+use crate::{AgentAPI, DivisionAPI, EventAPI, MemoryAPI, OrganizationAPI, TaskAPI};
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_ENCODING, CONTENT_TYPE, IF_MATCH,
+    IF_NONE_MATCH, RETRY_AFTER,
+};
+use reqwest::{Client, Request, Response, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
 use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::watch;
 
 const DEFAULT_BASE_URL: &str = "https://api.blackroad.io/v1";
+const DRY_RUN_HEADER: &str = "X-Dry-Run";
+const ORGANIZATION_HEADER: &str = "x-blackroad-organization";
+const PROJECT_HEADER: &str = "x-blackroad-project";
+const CONSISTENCY_TOKEN_HEADER: &str = "x-consistency-token";
+const CONSISTENCY_AFTER_HEADER: &str = "x-consistency-after";
+const REGION_URL_TEMPLATE: &str = "https://api.{region}.blackroad.io/v1";
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_MAX_RETRY_ELAPSED_SECS: u64 = 60;
+/// Snapshots kept in memory per client, least recently used evicted first.
+const MAX_SNAPSHOTS: usize = 256;
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// `X-RateLimit-Reset` values above this are Unix timestamps, not delays.
+const RATE_LIMIT_EPOCH_THRESHOLD: u64 = 1_000_000_000;
+
+/// A request body. Bytes are resent on retries; a stream can be sent once.
+pub(crate) enum Payload {
+    Bytes(Bytes),
+    #[cfg(not(target_arch = "wasm32"))]
+    Stream(Option<reqwest::Body>),
+}
+
+impl Payload {
+    fn is_repeatable(&self) -> bool {
+        matches!(self, Payload::Bytes(_))
+    }
+
+    /// Returns the body for the next attempt.
+    fn next_body(&mut self) -> Option<reqwest::Body> {
+        match self {
+            Payload::Bytes(bytes) => Some(bytes.clone().into()),
+            #[cfg(not(target_arch = "wasm32"))]
+            Payload::Stream(body) => body.take(),
+        }
+    }
+}
+
+/// Per-call state shared by the attempts of one request.
+struct Exchange<'a> {
+    method: reqwest::Method,
+    url: &'a str,
+    endpoint: &'a str,
+    started: Instant,
+    /// Attempts made so far.
+    attempts: u32,
+}
+
+impl Exchange<'_> {
+    fn metrics(&self) -> RequestMetrics<'_> {
+        RequestMetrics {
+            method: &self.method,
+            endpoint: self.endpoint,
+            attempts: self.attempts,
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
 
 /// Configuration for the BlackRoad client.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ClientConfig {
     /// API key. If None, reads from BLACKROAD_API_KEY env var.
+    /// Ignored when `auth_provider` is set.
     pub api_key: Option<String>,
-    /// Base URL for the API. Defaults to https://api.blackroad.io/v1
+    /// Source of bearer tokens, for credentials other than a static API key
+    /// (e.g. [`OidcTokenExchange`](crate::OidcTokenExchange)).
+    pub auth_provider: Option<Arc<dyn AuthProvider>>,
+    /// Base URL for the API. Defaults to <https://api.blackroad.io/v1>
     pub base_url: Option<String>,
     /// Request timeout in seconds. Defaults to 30.
+    ///
+    /// Streamed attachment transfers are not limited as a whole: the timeout
+    /// bounds connecting, waiting for a download to start, and each wait for
+    /// its next chunk. Event streams such as
+    /// [`TaskAPI::watch`](crate::TaskAPI::watch) are only limited until they
+    /// open.
     pub timeout_secs: Option<u64>,
     /// Maximum retry attempts. Defaults to 3.
     pub max_retries: Option<u32>,
-}
-
-impl Default for ClientConfig {
-    fn default() -> Self {
-        Self {
-            api_key: None,
-            base_url: None,
-            timeout_secs: None,
-            max_retries: None,
-        }
-    }
+    /// Upper bound on the time spent retrying one request, including waits,
+    /// in seconds. A retry that would end later is not attempted. Defaults
+    /// to 60.
+    pub max_retry_elapsed_secs: Option<u64>,
+    /// Application name appended to the `User-Agent` header.
+    pub app_name: Option<String>,
+    /// Application version appended after `app_name`. Ignored without `app_name`.
+    pub app_version: Option<String>,
+    /// Hedging delay in milliseconds for GET requests. If set, a GET that has not
+    /// answered within this delay is sent a second time and the first response
+    /// wins. Disabled by default.
+    pub hedge_delay_ms: Option<u64>,
+    /// Fixed addresses for host names, bypassing DNS. The port of each address
+    /// is ignored in favour of the port in the request URL.
+    pub resolve_overrides: Option<HashMap<String, SocketAddr>>,
+    /// Idle connections kept open per host. Raise it for high-throughput
+    /// workers that otherwise churn through connections. Defaults to
+    /// reqwest's unlimited pool.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Seconds an idle pooled connection is kept before closing. Defaults
+    /// to 90.
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// Speak HTTP/2 from the first byte instead of negotiating it, for
+    /// deployments known to support it (e.g. h2c behind a sidecar).
+    pub http2_prior_knowledge: Option<bool>,
+    /// Size HTTP/2 flow-control windows from the measured bandwidth-delay
+    /// product, which helps large bodies on fast links.
+    pub http2_adaptive_window: Option<bool>,
+    /// Interval in seconds for TCP keepalive probes, so idle connections
+    /// through NATs and load balancers are not silently dropped. Disabled
+    /// by default.
+    pub tcp_keepalive_secs: Option<u64>,
+    /// PEM certificates to trust in addition to the system roots, e.g. the
+    /// internal CA of a self-hosted deployment. An entry may hold several
+    /// certificates. Ignored on wasm32, where the browser handles TLS.
+    pub root_certificates: Option<Vec<Vec<u8>>>,
+    /// Client certificate for mutual TLS. Ignored on wasm32.
+    pub client_identity: Option<ClientIdentity>,
+    /// Skips server certificate validation entirely, so any server can
+    /// impersonate the API. Only for local testing against self-signed
+    /// certificates. Ignored on wasm32.
+    pub danger_accept_invalid_certs: Option<bool>,
+    /// Directory for a persistent GET response cache honoring `Cache-Control`
+    /// and `ETag`. Disabled when unset.
+    pub cache_dir: Option<PathBuf>,
+    /// Size cap for the disk cache in bytes. Defaults to 50 MiB.
+    pub cache_max_bytes: Option<u64>,
+    /// Number of GET responses to keep in memory and revalidate with
+    /// `If-None-Match`, so that unchanged resources come back as an empty
+    /// `304`. Ignored when `cache_dir` is set, which does the same on disk.
+    /// Disabled when unset.
+    pub etag_cache_entries: Option<usize>,
+    /// Ask for gzip or brotli compressed responses and decompress them.
+    /// Defaults to `true`. Browsers always do this on wasm32.
+    pub decompress_responses: Option<bool>,
+    /// Gzip request bodies of at least this many bytes, sending them with
+    /// `Content-Encoding: gzip`. Worth setting for large metadata and bulk
+    /// imports. Disabled by default.
+    pub compress_requests_from: Option<usize>,
+    /// Region to send requests to, e.g. `"eu"`. Takes precedence over
+    /// `base_url`.
+    pub region: Option<String>,
+    /// Base URLs per region. Regions not listed here use
+    /// `https://api.{region}.blackroad.io/v1`.
+    pub region_urls: Option<HashMap<String, String>>,
+    /// Hook for writing and reading JSON bodies, e.g.
+    /// [`CanonicalJson`](crate::CanonicalJson). Defaults to plain serde_json.
+    pub json_codec: Option<Arc<dyn JsonCodec>>,
+    /// Send every mutating request as a dry run: the API validates it and
+    /// returns what would have happened without applying it.
+    pub dry_run: Option<bool>,
+    /// Attach a generated `Idempotency-Key` to mutating requests that lack
+    /// one, reusing it across retries so the API applies a retried request
+    /// at most once. This also lets POSTs be retried after a 5xx. Defaults
+    /// to `true`.
+    pub idempotency_keys: Option<bool>,
+    /// Encoding for request and response bodies. Defaults to JSON. Cached and
+    /// snapshot reads always use JSON.
+    pub wire_format: Option<WireFormat>,
+    /// How watch and subscribe streams receive updates. Defaults to
+    /// [`StreamTransport::Auto`].
+    pub stream_transport: Option<StreamTransport>,
+    /// Caps requests in flight and queues the rest by priority, holding them
+    /// while the rate limit is exhausted. Disabled by default.
+    pub request_queue: Option<RequestQueueConfig>,
+    /// Spaces out requests with a token bucket shared by every clone of the
+    /// client, so bursts stay under the API's rate limit. Retries count
+    /// against it too. Disabled by default.
+    pub rate_limiter: Option<RateLimiterConfig>,
+    /// Fails requests fast with [`Error::CircuitOpen`] after repeated
+    /// connection failures or 5xx responses, instead of adding load to an API
+    /// that is down. Shared by every clone of the client. Disabled by
+    /// default.
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Hook called before each retry with the attempt, delay, cause, and
+    /// endpoint.
+    pub retry_observer: Option<Arc<dyn RetryObserver>>,
+    /// Hook that receives request counts, retries, errors, and latencies.
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+    /// Logs every request and response, headers and bodies included, with
+    /// credentials and the configured JSON fields redacted. Disabled by
+    /// default.
+    #[cfg(feature = "tracing")]
+    pub wire_log: Option<WireLogConfig>,
+    /// Hooks run in order on every outgoing request and response.
+    pub interceptors: Vec<Arc<dyn Interceptor>>,
+    /// A preconfigured reqwest client to send requests with, e.g. one shared
+    /// with the rest of the application for its connection pool, proxy, or
+    /// DNS resolver. `resolve_overrides`, the pool and HTTP/2 settings, and
+    /// the TLS settings are not applied to it (the TLS settings still cover
+    /// the event stream), while `timeout_secs` is set per request and still
+    /// holds.
+    pub http_client: Option<Client>,
+    /// Sends requests instead of the built-in reqwest transport, e.g. a
+    /// [`MockTransport`](crate::MockTransport) in tests.
+    pub transport: Option<Arc<dyn Transport>>,
 }
 
 /// The BlackRoad API client.
 #[derive(Debug, Clone)]
 pub struct BlackRoadClient {
-    api_key: String,
+    auth: Arc<dyn AuthProvider>,
     base_url: String,
+    region: Option<String>,
+    region_urls: Arc<HashMap<String, String>>,
+    timeout: Duration,
     max_retries: u32,
+    max_retry_elapsed: Duration,
+    user_agent: String,
+    hedge_delay: Option<Duration>,
+    cache: Option<DiskCache>,
+    etag_cache: Option<Arc<MemoryCache>>,
+    wire_format: WireFormat,
+    stream_transport: StreamTransport,
+    json_codec: Option<Arc<dyn JsonCodec>>,
+    dry_run: bool,
+    /// Set for requests whose response body is streamed, which get no total
+    /// timeout.
+    streaming: bool,
+    idempotency_keys: bool,
+    compress_requests_from: Option<usize>,
+    default_headers: HeaderMap,
+    snapshots: Arc<MemoryCache>,
+    rate_limit: Arc<watch::Sender<Option<RateLimitStatus>>>,
+    consistency_token: Arc<Mutex<Option<String>>>,
+    scope: Arc<Scope>,
+    queue: Option<Arc<RequestQueue>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    breaker: Option<Arc<CircuitBreaker>>,
+    priority: Option<RequestPriority>,
+    retry_observer: Option<Arc<dyn RetryObserver>>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    #[cfg(feature = "tracing")]
+    wire_log: Option<Arc<WireLogger>>,
+    interceptors: Arc<[Arc<dyn Interceptor>]>,
+    transport: Arc<dyn Transport>,
+    #[cfg(not(target_arch = "wasm32"))]
+    websocket_tls: Option<native_tls::TlsConnector>,
     http_client: Client,
 }
 
+/// Defaults applied by a client returned from [`BlackRoadClient::scoped`].
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    /// Division used by `dispatch`, `register`, and agent/task listings when
+    /// the call does not name one.
+    pub division: Option<String>,
+    /// Agent recorded on memory entries logged without one.
+    pub agent_id: Option<String>,
+    /// Tags added to every memory entry logged.
+    pub default_tags: Vec<String>,
+}
+
+/// Options applied to reads made through [`BlackRoadClient::read_with`].
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    after: Option<String>,
+}
+
+impl ReadOptions {
+    /// Reads reflect at least the write that returned `token`.
+    pub fn after(token: impl Into<String>) -> Self {
+        Self {
+            after: Some(token.into()),
+        }
+    }
+}
+
+/// Overrides applied to calls made through [`BlackRoadClient::with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Extra headers sent with each request, replacing any the client would
+    /// otherwise send under the same name.
+    pub headers: HashMap<String, String>,
+    /// Request timeout in seconds, replacing `ClientConfig::timeout_secs`.
+    pub timeout_secs: Option<u64>,
+    /// Maximum attempts per request, replacing `ClientConfig::max_retries`.
+    /// `Some(1)` sends each request once, without retries.
+    pub max_retries: Option<u32>,
+    /// `Idempotency-Key` to send instead of a generated one, e.g. a key
+    /// derived from an upstream message ID.
+    pub idempotency_key: Option<String>,
+    /// Body encoding, replacing `ClientConfig::wire_format`, e.g. CBOR for
+    /// a bulk memory log while other calls stay JSON.
+    pub wire_format: Option<WireFormat>,
+}
+
 impl BlackRoadClient {
     /// Creates a new BlackRoad client.
     ///
@@ -59,38 +341,400 @@ impl BlackRoadClient {
     /// }).expect("Failed to create client");
     /// ```
     pub fn new(config: ClientConfig) -> Result<Self, Error> {
-        let api_key = config
-            .api_key
-            .or_else(|| env::var("BLACKROAD_API_KEY").ok())
-            .ok_or_else(|| {
-                Error::Authentication(
-                    "API key required. Set BLACKROAD_API_KEY environment variable or pass api_key in config.".to_string()
-                )
-            })?;
+        let auth: Arc<dyn AuthProvider> = match config.auth_provider {
+            Some(provider) => provider,
+            None => {
+                let api_key = config
+                    .api_key
+                    .or_else(|| env::var("BLACKROAD_API_KEY").ok())
+                    .ok_or_else(|| {
+                        Error::Authentication(
+                            "API key required. Set BLACKROAD_API_KEY environment variable or pass api_key in config.".to_string()
+                        )
+                    })?;
+                Arc::new(StaticApiKey::new(api_key))
+            }
+        };
 
-        let base_url = config
-            .base_url
-            .or_else(|| env::var("BLACKROAD_API_URL").ok())
-            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
-            .trim_end_matches('/')
-            .to_string();
+        let region_urls = config.region_urls.unwrap_or_default();
+        let base_url = match &config.region {
+            Some(region) => region_url(&region_urls, region),
+            None => config
+                .base_url
+                .or_else(|| env::var("BLACKROAD_API_URL").ok())
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+        };
 
         let timeout_secs = config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS);
         let max_retries = config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES);
 
-        let http_client = Client::builder()
-            .timeout(Duration::from_secs(timeout_secs))
-            .build()
-            .map_err(|e| Error::Connection(format!("Failed to create HTTP client: {}", e)))?;
+        let mut user_agent = format!("blackroad-rust/{}", env!("CARGO_PKG_VERSION"));
+        if let Some(app_name) = config.app_name {
+            user_agent.push(' ');
+            user_agent.push_str(&app_name);
+            if let Some(app_version) = config.app_version {
+                user_agent.push('/');
+                user_agent.push_str(&app_version);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let tls = TlsSettings {
+            root_certificates: config.root_certificates.as_deref().unwrap_or_default(),
+            identity: config.client_identity.as_ref(),
+            accept_invalid_certs: config.danger_accept_invalid_certs.unwrap_or(false),
+        };
+        let http_client = match config.http_client {
+            Some(client) => client,
+            None => {
+                let builder = Client::builder();
+                // On wasm32 the browser does the networking: the timeout is
+                // applied by `execute` and DNS overrides are not supported.
+                #[cfg(not(target_arch = "wasm32"))]
+                let builder = {
+                    let decompress = config.decompress_responses.unwrap_or(true);
+                    // The total timeout is set per request, so streams can
+                    // go without one.
+                    let mut builder = builder
+                        .connect_timeout(Duration::from_secs(timeout_secs))
+                        .gzip(decompress)
+                        .brotli(decompress);
+                    for (host, addr) in config.resolve_overrides.iter().flatten() {
+                        builder = builder.resolve(host, *addr);
+                    }
+                    if let Some(max) = config.pool_max_idle_per_host {
+                        builder = builder.pool_max_idle_per_host(max);
+                    }
+                    if let Some(secs) = config.pool_idle_timeout_secs {
+                        builder = builder.pool_idle_timeout(Duration::from_secs(secs));
+                    }
+                    if config.http2_prior_knowledge.unwrap_or(false) {
+                        builder = builder.http2_prior_knowledge();
+                    }
+                    if let Some(adaptive) = config.http2_adaptive_window {
+                        builder = builder.http2_adaptive_window(adaptive);
+                    }
+                    if let Some(secs) = config.tcp_keepalive_secs {
+                        builder = builder.tcp_keepalive(Duration::from_secs(secs));
+                    }
+                    tls.configure(builder)?
+                };
+                builder.build().map_err(|e| {
+                    Error::Connection(format!("Failed to create HTTP client: {}", e))
+                })?
+            }
+        };
+
+        if config
+            .request_queue
+            .as_ref()
+            .is_some_and(|q| q.max_concurrent == 0)
+        {
+            return Err(Error::Validation(ValidationErrorBody::new(
+                "request_queue.max_concurrent must be at least 1",
+            )));
+        }
+        let rate_limit = watch::channel(None).0;
+        let queue = config
+            .request_queue
+            .map(|q| Arc::new(RequestQueue::new(q, rate_limit.subscribe())));
+        if let Some(limiter) = &config.rate_limiter {
+            let rate = limiter.requests_per_second;
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(Error::Validation(ValidationErrorBody::new(
+                    "rate_limiter.requests_per_second must be positive",
+                )));
+            }
+        }
+        if let Some(breaker) = &config.circuit_breaker {
+            if breaker.failure_threshold == 0 {
+                return Err(Error::Validation(ValidationErrorBody::new(
+                    "circuit_breaker.failure_threshold must be at least 1",
+                )));
+            }
+            if breaker.open_secs > MAX_OPEN_SECS {
+                return Err(Error::Validation(ValidationErrorBody::new(format!(
+                    "circuit_breaker.open_secs must be at most {MAX_OPEN_SECS}"
+                ))));
+            }
+        }
 
         Ok(Self {
-            api_key,
+            auth,
             base_url,
+            region: config.region,
+            region_urls: Arc::new(region_urls),
+            timeout: Duration::from_secs(timeout_secs),
             max_retries,
+            max_retry_elapsed: Duration::from_secs(
+                config
+                    .max_retry_elapsed_secs
+                    .unwrap_or(DEFAULT_MAX_RETRY_ELAPSED_SECS),
+            ),
+            user_agent,
+            hedge_delay: config.hedge_delay_ms.map(Duration::from_millis),
+            cache: config.cache_dir.map(|dir| {
+                DiskCache::new(
+                    dir,
+                    config.cache_max_bytes.unwrap_or(DEFAULT_CACHE_MAX_BYTES),
+                )
+            }),
+            etag_cache: config
+                .etag_cache_entries
+                .filter(|&entries| entries > 0)
+                .map(|entries| Arc::new(MemoryCache::new(entries))),
+            wire_format: config.wire_format.unwrap_or_default(),
+            stream_transport: config.stream_transport.unwrap_or_default(),
+            json_codec: config.json_codec,
+            dry_run: config.dry_run.unwrap_or(false),
+            streaming: false,
+            idempotency_keys: config.idempotency_keys.unwrap_or(true),
+            compress_requests_from: config.compress_requests_from,
+            default_headers: HeaderMap::new(),
+            snapshots: Arc::new(MemoryCache::new(MAX_SNAPSHOTS)),
+            rate_limit: Arc::new(rate_limit),
+            consistency_token: Arc::new(Mutex::new(None)),
+            scope: Arc::new(Scope::default()),
+            queue,
+            rate_limiter: config.rate_limiter.map(|c| Arc::new(RateLimiter::new(c))),
+            breaker: config
+                .circuit_breaker
+                .map(|c| Arc::new(CircuitBreaker::new(c))),
+            priority: None,
+            retry_observer: config.retry_observer,
+            metrics: config.metrics,
+            #[cfg(feature = "tracing")]
+            wire_log: config
+                .wire_log
+                .map(|config| Arc::new(WireLogger::new(config))),
+            interceptors: config.interceptors.into(),
+            transport: config
+                .transport
+                .unwrap_or_else(|| Arc::new(HttpTransport::new(http_client.clone()))),
+            #[cfg(not(target_arch = "wasm32"))]
+            websocket_tls: tls.websocket_connector()?,
             http_client,
         })
     }
 
+    /// Returns a bearer credential from the configured auth provider.
+    pub(crate) async fn credentials(&self) -> Result<String, Error> {
+        self.auth.credentials().await
+    }
+
+    /// Returns the `User-Agent` sent with every request.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    /// Returns the TLS connector for event stream sockets, if TLS settings
+    /// differ from the defaults.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn websocket_tls(&self) -> Option<native_tls::TlsConnector> {
+        self.websocket_tls.clone()
+    }
+
+    /// Returns the per-request timeout.
+    pub(crate) fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns how watch and subscribe streams receive updates.
+    pub(crate) fn stream_transport(&self) -> StreamTransport {
+        self.stream_transport
+    }
+
+    /// Returns the region requests are sent to, if one was selected.
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// Returns a copy of the client that sends requests to `region`, for
+    /// resources pinned to a specific region.
+    pub fn in_region(&self, region: &str) -> BlackRoadClient {
+        let mut client = self.clone();
+        client.base_url = region_url(&self.region_urls, region);
+        client.region = Some(region.to_string());
+        client
+    }
+
+    /// Probes the health endpoint of every region in
+    /// [`ClientConfig::region_urls`] and returns a client for the region that
+    /// answered fastest.
+    pub async fn fastest_region(&self) -> Result<BlackRoadClient, Error> {
+        if self.region_urls.is_empty() {
+            return Err(Error::Validation(ValidationErrorBody::new(
+                "no regions configured in region_urls",
+            )));
+        }
+
+        let probes = self.region_urls.keys().map(|region| async move {
+            let client = self.in_region(region);
+            let started = Instant::now();
+            client.health().await.map(|_| (started.elapsed(), client))
+        });
+
+        let mut last_error = None;
+        let mut fastest: Option<(Duration, BlackRoadClient)> = None;
+        for result in futures::future::join_all(probes).await {
+            match result {
+                Ok((latency, client)) => {
+                    if !matches!(&fastest, Some((best, _)) if *best <= latency) {
+                        fastest = Some((latency, client));
+                    }
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        match fastest {
+            Some((_, client)) => Ok(client),
+            None => {
+                Err(last_error
+                    .unwrap_or_else(|| Error::Connection("no region answered".to_string())))
+            }
+        }
+    }
+
+    /// Returns a copy of the client that applies `scope`'s defaults, so
+    /// per-division services need not repeat the division on every call.
+    ///
+    /// Values passed explicitly to a call still take precedence.
+    pub fn scoped(&self, scope: Scope) -> BlackRoadClient {
+        let mut client = self.clone();
+        client.scope = Arc::new(scope);
+        client
+    }
+
+    /// Returns the defaults applied to calls made through this client.
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    /// Returns a copy of the client whose requests use `priority` in the
+    /// request queue instead of the default for each endpoint, e.g.
+    /// [`RequestPriority::Background`] for bulk exports. Has no effect unless
+    /// `request_queue` is configured.
+    pub fn with_priority(&self, priority: RequestPriority) -> BlackRoadClient {
+        let mut client = self.clone();
+        client.priority = Some(priority);
+        client
+    }
+
+    /// Returns a copy of the client whose calls act within organization
+    /// `org_id`, for services that manage several tenants.
+    pub fn with_org(&self, org_id: &str) -> Result<BlackRoadClient, Error> {
+        self.with_header(HeaderName::from_static(ORGANIZATION_HEADER), org_id)
+    }
+
+    /// Returns a copy of the client whose calls act within project
+    /// `project_id`. Combine with [`with_org`](Self::with_org) when the
+    /// project ID is only unique within its organization.
+    pub fn with_project(&self, project_id: &str) -> Result<BlackRoadClient, Error> {
+        self.with_header(HeaderName::from_static(PROJECT_HEADER), project_id)
+    }
+
+    /// Returns the consistency token from the most recent write, if the API
+    /// returned one.
+    ///
+    /// Pass it to [`ReadOptions::after`] so a following read is served by a
+    /// replica that has seen the write.
+    pub fn consistency_token(&self) -> Option<String> {
+        self.consistency_token.lock().ok()?.clone()
+    }
+
+    /// Returns a copy of the client whose requests honour `opts`.
+    pub fn read_with(&self, opts: ReadOptions) -> Result<BlackRoadClient, Error> {
+        match opts.after {
+            Some(token) => {
+                self.with_header(HeaderName::from_static(CONSISTENCY_AFTER_HEADER), &token)
+            }
+            None => Ok(self.clone()),
+        }
+    }
+
+    /// Returns a copy of the client whose requests honour `opts`, e.g. a
+    /// longer timeout for a single slow call:
+    ///
+    /// ```rust,no_run
+    /// # async fn run(client: blackroad::BlackRoadClient) -> Result<(), blackroad::Error> {
+    /// use blackroad::RequestOptions;
+    ///
+    /// let report = client
+    ///     .with_options(RequestOptions {
+    ///         timeout_secs: Some(300),
+    ///         ..Default::default()
+    ///     })?
+    ///     .memory()
+    ///     .verify_chain(None)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// An `idempotency_key` is sent with every request made through the
+    /// returned client, so use it for one logical operation only.
+    pub fn with_options(&self, opts: RequestOptions) -> Result<BlackRoadClient, Error> {
+        let mut client = self.clone();
+        for (name, value) in &opts.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                Error::Validation(ValidationErrorBody::new(format!(
+                    "invalid header name: {:?}",
+                    name
+                )))
+            })?;
+            client = client.with_header(name, value)?;
+        }
+        if let Some(key) = &opts.idempotency_key {
+            client = client.with_header(HeaderName::from_static(IDEMPOTENCY_KEY_HEADER), key)?;
+        }
+        if let Some(secs) = opts.timeout_secs {
+            client.timeout = Duration::from_secs(secs);
+        }
+        if let Some(max_retries) = opts.max_retries {
+            client.max_retries = max_retries.max(1);
+        }
+        if let Some(format) = opts.wire_format {
+            client.wire_format = format;
+        }
+        Ok(client)
+    }
+
+    fn record_consistency_token(&self, headers: &HeaderMap) {
+        let token = headers
+            .get(CONSISTENCY_TOKEN_HEADER)
+            .and_then(|v| v.to_str().ok());
+        if let (Some(token), Ok(mut latest)) = (token, self.consistency_token.lock()) {
+            *latest = Some(token.to_string());
+        }
+    }
+
+    /// Returns a copy of the client whose mutating requests are dry runs.
+    ///
+    /// The API validates each request and returns the resource as it would
+    /// have been created or changed, without applying anything.
+    pub fn dry_run(&self) -> BlackRoadClient {
+        let mut client = self.clone();
+        client.dry_run = true;
+        client
+    }
+
+    /// Returns a copy of the client that sends `name: value` on every request.
+    pub(crate) fn with_header(&self, name: HeaderName, value: &str) -> Result<Self, Error> {
+        let value = HeaderValue::from_str(value).map_err(|_| {
+            Error::Validation(ValidationErrorBody::new(format!(
+                "invalid value for header {}: {:?}",
+                name, value
+            )))
+        })?;
+        let mut client = self.clone();
+        client.default_headers.insert(name, value);
+        Ok(client)
+    }
+
     /// Returns the agents API.
     pub fn agents(&self) -> AgentAPI {
         AgentAPI::new(self.clone())
@@ -106,6 +750,21 @@ impl BlackRoadClient {
         MemoryAPI::new(self.clone())
     }
 
+    /// Returns the divisions API.
+    pub fn divisions(&self) -> DivisionAPI {
+        DivisionAPI::new(self.clone())
+    }
+
+    /// Returns the organizations API.
+    pub fn organizations(&self) -> OrganizationAPI {
+        OrganizationAPI::new(self.clone())
+    }
+
+    /// Returns the realtime events API.
+    pub fn events(&self) -> EventAPI {
+        EventAPI::new(self.clone())
+    }
+
     /// Makes an HTTP request to the API.
     pub(crate) async fn request<T, B>(
         &self,
@@ -118,41 +777,435 @@ impl BlackRoadClient {
         T: DeserializeOwned,
         B: Serialize,
     {
+        let url = self.url(endpoint, params);
+
+        if method == reqwest::Method::GET {
+            if let Some(cache) = &self.cache {
+                return self.cached_get(cache, &url).await;
+            }
+            if let Some(cache) = &self.etag_cache {
+                return self.cached_get(cache.as_ref(), &url).await;
+            }
+        }
+
+        let mut response = self
+            .send(method.clone(), &url, body, None, self.wire_format)
+            .await?;
+        if response.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE
+            && self.wire_format != WireFormat::Json
+        {
+            response = self
+                .send(method, &url, body, None, WireFormat::Json)
+                .await?;
+        }
+        self.handle_response(response).await
+    }
+
+    /// Makes a typed request to an endpoint the SDK does not wrap yet, such as
+    /// a preview API.
+    ///
+    /// `endpoint` must be a path relative to the base URL (e.g.
+    /// `/previews/insights`); absolute URLs, `..` segments, and inline query
+    /// strings are rejected so credentials are never sent elsewhere. Pass query
+    /// parameters through `params`. Requests get the same authentication,
+    /// retries, and error mapping as the built-in APIs.
+    ///
+    /// Requires the `unstable-endpoints` feature. The endpoints themselves are
+    /// not covered by this crate's compatibility guarantees.
+    #[cfg(feature = "unstable-endpoints")]
+    pub async fn custom_request<T, B>(
+        &self,
+        method: crate::Method,
+        endpoint: &str,
+        body: Option<&B>,
+        params: Option<&HashMap<String, String>>,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        B: Serialize,
+    {
+        let valid = endpoint.starts_with('/')
+            && !endpoint.starts_with("//")
+            && !endpoint.contains(['?', '#', '\\'])
+            && !endpoint.chars().any(char::is_control)
+            && endpoint.split('/').all(|s| s != "." && s != "..");
+        if !valid {
+            return Err(Error::Validation(ValidationErrorBody::new(format!(
+                "invalid endpoint path: {:?}",
+                endpoint
+            ))));
+        }
+        self.request(method, endpoint, body, params).await
+    }
+
+    /// Builds the URL for `endpoint`. Parameters are sorted by name so the
+    /// same query always yields the same URL, which the caches key on.
+    pub(crate) fn url(&self, endpoint: &str, params: Option<&HashMap<String, String>>) -> String {
         let mut url = format!("{}/{}", self.base_url, endpoint.trim_start_matches('/'));
 
         if let Some(params) = params {
-            let query: Vec<String> = params
+            let mut query: Vec<String> = params
                 .iter()
                 .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
                 .collect();
+            query.sort_unstable();
             if !query.is_empty() {
                 url = format!("{}?{}", url, query.join("&"));
             }
         }
 
+        url
+    }
+
+    /// Makes a GET request, falling back to the last-known response for the
+    /// same URL if the API is unreachable.
+    pub(crate) async fn get_snapshot<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: Option<&HashMap<String, String>>,
+    ) -> Result<Snapshot<T>, Error> {
+        let url = self.url(endpoint, params);
+
+        let result = async {
+            let response = self
+                .send::<()>(reqwest::Method::GET, &url, None, None, WireFormat::Json)
+                .await?;
+            if !response.status().is_success() {
+                return Err(self.response_error(response).await);
+            }
+            let body = response.text().await?;
+            self.log_response_body(body.as_bytes());
+            Ok(body)
+        }
+        .await;
+
+        match result {
+            Ok(body) => {
+                let value = self.decode_json(&body)?;
+                let entry = CachedResponse::snapshot(body);
+                let fetched_at = entry.stored_at;
+                self.store_snapshot(&url, entry).await;
+                Ok(Snapshot {
+                    value,
+                    stale: false,
+                    fetched_at,
+                })
+            }
+            Err(e) if e.is_transient() => match self.load_snapshot(&url).await {
+                Some(entry) => Ok(Snapshot {
+                    value: self.decode_json(&entry.body)?,
+                    stale: true,
+                    fetched_at: entry.stored_at,
+                }),
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Snapshots are keyed like cached responses, by credential, URL, and
+    /// scoping headers, so that a client scoped to one organization never
+    /// falls back to another's data.
+    async fn store_snapshot(&self, url: &str, entry: CachedResponse) {
+        let Ok(credential) = self.auth.credentials().await else {
+            return;
+        };
+        let key = cache_key(&credential, &format!("snapshot {}", self.cache_url(url)));
+        if let Some(cache) = &self.cache {
+            cache.store(&key, &entry).await;
+        }
+        self.snapshots.store(&key, &entry).await;
+    }
+
+    async fn load_snapshot(&self, url: &str) -> Option<CachedResponse> {
+        let credential = self.auth.credentials().await.ok()?;
+        let key = cache_key(&credential, &format!("snapshot {}", self.cache_url(url)));
+        if let Some(entry) = self.snapshots.load(&key).await {
+            return Some(entry);
+        }
+        self.cache.as_ref()?.load(&key).await
+    }
+
+    /// Encodes `body` in `format`, gzipping it if it is large enough, and
+    /// sends it with [`send_payload`](Self::send_payload).
+    async fn send<B: Serialize>(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<&B>,
+        if_none_match: Option<&str>,
+        format: WireFormat,
+    ) -> Result<Response, Error> {
+        let mut body = body
+            .map(|b| format.encode(b, self.json_codec.as_deref()))
+            .transpose()?;
+        let compress = matches!(
+            (&body, self.compress_requests_from),
+            (Some(bytes), Some(min)) if bytes.len() >= min
+        );
+        let gzipping;
+        let client = if compress {
+            body = body.as_deref().map(crate::encoding::gzip).transpose()?;
+            gzipping = self.with_header(CONTENT_ENCODING, "gzip")?;
+            &gzipping
+        } else {
+            self
+        };
+        let payload = body.map(|bytes| Payload::Bytes(bytes.into()));
+        client
+            .send_payload(
+                method,
+                url,
+                payload,
+                format.content_type(),
+                if_none_match,
+                format,
+            )
+            .await
+    }
+
+    /// Sends a request with [`send_attempts`](Self::send_attempts), reporting
+    /// it to the metrics sink. With the `tracing` feature, the whole exchange
+    /// runs in a `blackroad.request` span that records the final status,
+    /// attempt count, and latency.
+    async fn send_payload(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        payload: Option<Payload>,
+        content_type: &str,
+        if_none_match: Option<&str>,
+        format: WireFormat,
+    ) -> Result<Response, Error> {
+        let mut exchange = Exchange {
+            endpoint: self.endpoint(url),
+            method,
+            url,
+            started: Instant::now(),
+            attempts: 0,
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.on_request(&exchange.method, exchange.endpoint);
+        }
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "blackroad.request",
+            method = %exchange.method,
+            endpoint = exchange.endpoint,
+            status = tracing::field::Empty,
+            attempts = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+
+        let sending =
+            self.send_attempts(&mut exchange, payload, content_type, if_none_match, format);
+        #[cfg(feature = "tracing")]
+        let sending = tracing::Instrument::instrument(sending, span.clone());
+        let result = sending.await;
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("attempts", exchange.attempts);
+            span.record("latency_ms", exchange.started.elapsed().as_millis() as u64);
+            match &result {
+                Ok(response) => span.record("status", response.status().as_u16()),
+                Err(error) => span.record("error", tracing::field::display(error)),
+            };
+        }
+        if let Some(metrics) = &self.metrics {
+            let request = exchange.metrics();
+            match &result {
+                Ok(response) => metrics.on_response(&request, response.status()),
+                Err(error) => metrics.on_error(&request, error),
+            }
+        }
+        result
+    }
+
+    /// Sends a request, retrying connection failures, 429s, and (for requests
+    /// that are safe to repeat) 5xx responses. A streamed payload is sent
+    /// once, without retries. A 401 is retried once with fresh credentials
+    /// after telling the auth provider that its token was rejected.
+    ///
+    /// Waits follow `Retry-After` when the API sends it, and otherwise back
+    /// off exponentially with jitter. No retry is started that would exceed
+    /// `max_retry_elapsed`.
+    async fn send_attempts(
+        &self,
+        exchange: &mut Exchange<'_>,
+        mut payload: Option<Payload>,
+        content_type: &str,
+        if_none_match: Option<&str>,
+        format: WireFormat,
+    ) -> Result<Response, Error> {
+        let method = exchange.method.clone();
+        let url = exchange.url;
         let mut last_error: Option<Error> = None;
+        let mut reauthenticated = false;
 
-        for attempt in 0..self.max_retries {
-            let mut request = self
+        let priority = self
+            .priority
+            .unwrap_or_else(|| RequestPriority::classify(&method, url));
+        let idempotency_key = (self.idempotency_keys
+            && !method.is_safe()
+            && !self.default_headers.contains_key(IDEMPOTENCY_KEY_HEADER))
+        .then(idempotency_key);
+        // `If-Match` only guards writes. A conditional write that failed with
+        // a 5xx may still have been applied, so repeating it would conflict
+        // with its own result.
+        let mut headers = self.default_headers.clone();
+        let conditional = if method.is_safe() {
+            headers.remove(IF_MATCH);
+            false
+        } else {
+            headers.contains_key(IF_MATCH)
+        };
+        let repeatable = !conditional
+            && (method.is_idempotent()
+                || idempotency_key.is_some()
+                || self.default_headers.contains_key(IDEMPOTENCY_KEY_HEADER));
+        let attempts = match &payload {
+            Some(payload) if !payload.is_repeatable() => 1,
+            _ => self.max_retries,
+        };
+
+        for attempt in 0..attempts {
+            let can_retry = attempt + 1 < attempts;
+            exchange.attempts = attempt + 1;
+            if let Some(breaker) = &self.breaker {
+                breaker.check()?;
+            }
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.acquire().await;
+            }
+            let permit = match &self.queue {
+                Some(queue) => Some(queue.acquire(priority).await?),
+                None => None,
+            };
+            let token = self.auth.credentials().await?;
+            let mut builder = self
                 .http_client
-                .request(method.clone(), &url)
-                .header("Authorization", format!("Bearer {}", self.api_key))
-                .header("Content-Type", "application/json")
-                .header("User-Agent", "blackroad-rust/1.0.0");
+                .request(method.clone(), url)
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Content-Type", content_type)
+                .header("Accept", format.accept())
+                .header("User-Agent", &self.user_agent)
+                .headers(headers.clone());
+
+            if let Some(body) = payload.as_mut().and_then(Payload::next_body) {
+                builder = builder.body(body);
+            }
+            if let Some(etag) = if_none_match {
+                builder = builder.header(IF_NONE_MATCH, etag);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if !self.streaming && payload.as_ref().is_none_or(Payload::is_repeatable) {
+                builder = builder.timeout(self.timeout);
+            }
+            if self.dry_run && method != reqwest::Method::GET {
+                builder = builder.header(DRY_RUN_HEADER, "true");
+            }
+            if let Some(key) = &idempotency_key {
+                builder = builder.header(IDEMPOTENCY_KEY_HEADER, key);
+            }
+
+            let mut request = builder.build()?;
+            for interceptor in self.interceptors.iter() {
+                interceptor.on_request(&mut request).await?;
+            }
+            let request_url = request.url().clone();
+            #[cfg(feature = "tracing")]
+            if let Some(wire_log) = &self.wire_log {
+                wire_log.request(&request);
+            }
 
-            if let Some(body) = body {
-                request = request.json(body);
+            let sent_at = Instant::now();
+            let result = match self.hedge_delay {
+                Some(delay) if method == reqwest::Method::GET => {
+                    self.send_hedged(request, delay).await
+                }
+                _ => self.execute(request).await,
+            };
+            if let Ok(response) = &result {
+                #[cfg(feature = "tracing")]
+                if let Some(wire_log) = &self.wire_log {
+                    wire_log.response(&method, &request_url, response.status(), response.headers());
+                }
+                let info = ResponseInfo {
+                    method: &method,
+                    url: &request_url,
+                    status: response.status(),
+                    headers: response.headers(),
+                    elapsed: sent_at.elapsed(),
+                };
+                for interceptor in self.interceptors.iter() {
+                    interceptor.on_response(&info);
+                }
             }
 
-            match request.send().await {
+            if let Some(breaker) = &self.breaker {
+                match &result {
+                    Ok(response) => breaker.record(!response.status().is_server_error()),
+                    Err(error) if error.is_transient() => breaker.record(false),
+                    Err(_) => {}
+                }
+            }
+
+            match result {
                 Ok(response) => {
-                    return self.handle_response(response).await;
+                    if let Some(status) = rate_limit_from_headers(response.headers()) {
+                        self.rate_limit.send_replace(Some(status));
+                    }
+                    if method != reqwest::Method::GET {
+                        self.record_consistency_token(response.headers());
+                    }
+                    let status = response.status();
+                    #[cfg(feature = "tracing")]
+                    if status == StatusCode::TOO_MANY_REQUESTS {
+                        tracing::warn!(
+                            endpoint = self.endpoint(url),
+                            retry_after_ms = retry_after(response.headers())
+                                .map(|delay| delay.as_millis() as u64),
+                            "rate limited by the BlackRoad API"
+                        );
+                    }
+                    if status == StatusCode::UNAUTHORIZED && !reauthenticated && can_retry {
+                        reauthenticated = true;
+                        drop(permit);
+                        self.auth.invalidate(&token).await;
+                        last_error = Some(self.response_error(response).await);
+                        continue;
+                    }
+                    let retryable = status == StatusCode::TOO_MANY_REQUESTS
+                        || (status.is_server_error() && repeatable);
+                    if !retryable || !can_retry {
+                        return Ok(response);
+                    }
+                    let delay = retry_after(response.headers()).unwrap_or_else(|| backoff(attempt));
+                    if self.past_retry_budget(exchange, delay) {
+                        return Ok(response);
+                    }
+                    drop(permit);
+                    let error = self.response_error(response).await;
+                    self.notify_retry(exchange, delay, &error);
+                    crate::rt::sleep(delay).await;
+                    last_error = Some(error);
                 }
-                Err(e) => {
-                    last_error = Some(Error::Connection(format!("Request failed: {}", e)));
-                    if attempt < self.max_retries - 1 {
-                        tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                Err(error) => {
+                    drop(permit);
+                    if !error.is_transient() {
+                        return Err(error);
+                    }
+                    if can_retry {
+                        let delay = backoff(attempt);
+                        if self.past_retry_budget(exchange, delay) {
+                            return Err(error);
+                        }
+                        self.notify_retry(exchange, delay, &error);
+                        crate::rt::sleep(delay).await;
                     }
+                    last_error = Some(error);
                 }
             }
         }
@@ -160,27 +1213,257 @@ impl BlackRoadClient {
         Err(last_error.unwrap_or_else(|| Error::Connection("Max retries exceeded".to_string())))
     }
 
+    /// Returns true if a retry after `delay` would end past the retry budget,
+    /// including when the delay is too large to add at all.
+    fn past_retry_budget(&self, exchange: &Exchange<'_>, delay: Duration) -> bool {
+        exchange
+            .started
+            .elapsed()
+            .checked_add(delay)
+            .is_none_or(|end| end > self.max_retry_elapsed)
+    }
+
+    /// Reports an upcoming retry to the observer and metrics sink and, with
+    /// the `tracing` feature, as a `warn` event.
+    fn notify_retry(&self, exchange: &Exchange<'_>, delay: Duration, cause: &Error) {
+        let attempt = exchange.attempts;
+        let endpoint = exchange.endpoint;
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            attempt,
+            delay_ms = delay.as_millis() as u64,
+            cause = %cause,
+            endpoint,
+            "retrying BlackRoad request"
+        );
+        if let Some(observer) = &self.retry_observer {
+            observer.on_retry(attempt, delay, cause, endpoint);
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.on_retry(&exchange.metrics(), delay, cause);
+        }
+    }
+
+    /// The path of `url` relative to the base URL, without the query string.
+    fn endpoint<'a>(&self, url: &'a str) -> &'a str {
+        let path = url.strip_prefix(self.base_url.as_str()).unwrap_or(url);
+        path.split('?').next().unwrap_or(path)
+    }
+
+    /// Serves a GET from `cache` when fresh, revalidating stale entries with
+    /// `If-None-Match` and storing cacheable responses.
+    async fn cached_get<T: DeserializeOwned>(
+        &self,
+        cache: &dyn ResponseCache,
+        url: &str,
+    ) -> Result<T, Error> {
+        let key = cache_key(&self.auth.credentials().await?, &self.cache_url(url));
+        let cached = cache.load(&key).await;
+
+        if let Some(entry) = &cached {
+            if entry.is_fresh() {
+                return self.decode_json(&entry.body);
+            }
+        }
+
+        let etag = cached.as_ref().and_then(|e| e.etag.as_deref());
+        let response = self
+            .send::<()>(reqwest::Method::GET, url, None, etag, WireFormat::Json)
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut entry) = cached {
+                entry.refresh(response.headers());
+                cache.store(&key, &entry).await;
+                return self.decode_json(&entry.body);
+            }
+        }
+        if !response.status().is_success() {
+            return self.handle_response(response).await;
+        }
+
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        self.log_response_body(body.as_bytes());
+        let value = self.decode_json(&body)?;
+        if let Some(entry) = CachedResponse::from_headers(&headers, body) {
+            cache.store(&key, &entry).await;
+        }
+        Ok(value)
+    }
+
+    /// Returns `url` extended with the headers this client adds to every
+    /// request, such as the organization and project set by
+    /// [`with_org`](Self::with_org) and [`with_project`](Self::with_project),
+    /// so that clients scoped differently never share cached responses.
+    fn cache_url(&self, url: &str) -> String {
+        let mut headers: Vec<String> = self
+            .default_headers
+            .iter()
+            .filter(|(name, _)| name.as_str() != IDEMPOTENCY_KEY_HEADER && *name != IF_MATCH)
+            .map(|(name, value)| format!("{}: {}", name, value.to_str().unwrap_or_default()))
+            .collect();
+        headers.sort_unstable();
+        headers.insert(0, url.to_string());
+        headers.join("\n")
+    }
+
+    /// Sends `request`, firing an identical hedge request if no response has
+    /// arrived after `delay`. The first successful response wins and the other
+    /// in-flight request is dropped.
+    async fn send_hedged(&self, request: Request, delay: Duration) -> Result<Response, Error> {
+        let hedge = match request.try_clone() {
+            Some(hedge) => hedge,
+            None => return self.execute(request).await,
+        };
+
+        let primary = self.execute(request);
+        tokio::pin!(primary);
+        tokio::select! {
+            result = &mut primary => return result,
+            _ = crate::rt::sleep(delay) => {}
+        }
+
+        let secondary = self.execute(hedge);
+        tokio::pin!(secondary);
+        tokio::select! {
+            result = &mut primary => match result {
+                Ok(response) => Ok(response),
+                Err(_) => secondary.await,
+            },
+            result = &mut secondary => match result {
+                Ok(response) => Ok(response),
+                Err(_) => primary.await,
+            },
+        }
+    }
+
+    /// Sends one request through the transport. reqwest has no per-request
+    /// timeout on wasm32, and streamed responses have none on purpose, so
+    /// for those the wait for the response headers is bounded here.
+    async fn execute(&self, request: Request) -> Result<Response, Error> {
+        use futures::future::{select, Either};
+
+        if cfg!(not(target_arch = "wasm32")) && !self.streaming {
+            return self.transport.execute(request).await;
+        }
+        let send = self.transport.execute(request);
+        let timer = Box::pin(crate::rt::sleep(self.timeout));
+        match select(send, timer).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(Error::Connection(format!(
+                "Request timed out after {:?}",
+                self.timeout
+            ))),
+        }
+    }
+
     async fn handle_response<T: DeserializeOwned>(&self, response: Response) -> Result<T, Error> {
-        let status = response.status();
+        if response.status().is_success() {
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let body = response.bytes().await?;
+            self.log_response_body(&body);
+            return WireFormat::decode(content_type.as_deref(), &body, self.json_codec.as_deref());
+        }
+        Err(self.response_error(response).await)
+    }
 
-        if status.is_success() {
-            return response.json::<T>().await.map_err(Error::from);
+    /// Passes a response body to the wire log, if enabled.
+    fn log_response_body(&self, _body: &[u8]) {
+        #[cfg(feature = "tracing")]
+        if let Some(wire_log) = &self.wire_log {
+            wire_log.response_body(_body);
         }
+    }
 
+    /// Decodes a JSON body stored by the cache or snapshot store.
+    fn decode_json<T: DeserializeOwned>(&self, body: &str) -> Result<T, Error> {
+        WireFormat::decode(None, body.as_bytes(), self.json_codec.as_deref())
+    }
+
+    /// Converts an unsuccessful response into an error.
+    async fn response_error(&self, response: Response) -> Error {
+        let status = response.status();
+        let retry_after = retry_after(response.headers());
         let error_body = response.text().await.unwrap_or_default();
+        self.log_response_body(error_body.as_bytes());
+        Error::from_status(status, error_body, retry_after)
+    }
 
-        match status {
-            StatusCode::UNAUTHORIZED => Err(Error::Authentication("Invalid API key".to_string())),
-            StatusCode::NOT_FOUND => Err(Error::NotFound(error_body)),
-            StatusCode::UNPROCESSABLE_ENTITY => Err(Error::Validation(error_body)),
-            StatusCode::TOO_MANY_REQUESTS => {
-                Err(Error::RateLimit { retry_after: 1 })
-            }
-            _ => Err(Error::Api {
-                status: status.as_u16(),
-                message: error_body,
-            }),
+    /// Opens a `text/event-stream` response for `endpoint`, resuming after
+    /// `last_event_id` if given. The stream has no total timeout, since it
+    /// stays open for as long as it is read.
+    pub(crate) async fn open_event_stream(
+        &self,
+        endpoint: &str,
+        last_event_id: Option<&str>,
+    ) -> Result<Response, Error> {
+        let mut client = self.streaming();
+        if let Some(id) = last_event_id {
+            client = client.with_header(HeaderName::from_static("last-event-id"), id)?;
         }
+        client.get_raw(endpoint, "text/event-stream").await
+    }
+
+    /// Makes a GET request for a non-JSON body, returning the response so the
+    /// caller can read or stream it.
+    pub(crate) async fn get_raw(&self, endpoint: &str, accept: &str) -> Result<Response, Error> {
+        let client = self.with_header(ACCEPT, accept)?;
+        let url = self.url(endpoint, None);
+        let response = client
+            .send::<()>(reqwest::Method::GET, &url, None, None, WireFormat::Json)
+            .await?;
+        if !response.status().is_success() {
+            return Err(self.response_error(response).await);
+        }
+        Ok(response)
+    }
+
+    /// Like [`get_raw`](Self::get_raw), for a body read as a stream: the
+    /// request has no total timeout, and each chunk must arrive within the
+    /// client's timeout of the last.
+    pub(crate) async fn get_stream(
+        &self,
+        endpoint: &str,
+        accept: &str,
+    ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
+        let response = self.streaming().get_raw(endpoint, accept).await?;
+        Ok(idle_timeout(
+            Box::pin(response.bytes_stream().map_err(Error::from)),
+            self.timeout,
+        ))
+    }
+
+    /// Returns a copy of the client whose requests have no total timeout.
+    pub(crate) fn streaming(&self) -> Self {
+        let mut client = self.clone();
+        client.streaming = true;
+        client
+    }
+
+    /// Makes a PUT request with a raw body, such as a file upload.
+    pub(crate) async fn put_raw<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        payload: Payload,
+        content_type: &str,
+    ) -> Result<T, Error> {
+        let url = self.url(endpoint, None);
+        let response = self
+            .send_payload(
+                reqwest::Method::PUT,
+                &url,
+                Some(payload),
+                content_type,
+                None,
+                self.wire_format,
+            )
+            .await?;
+        self.handle_response(response).await
     }
 
     /// Makes a GET request.
@@ -213,6 +1496,16 @@ impl BlackRoadClient {
             .await
     }
 
+    /// Makes a PATCH request.
+    pub(crate) async fn patch<T: DeserializeOwned, B: Serialize>(
+        &self,
+        endpoint: &str,
+        body: &B,
+    ) -> Result<T, Error> {
+        self.request(reqwest::Method::PATCH, endpoint, Some(body), None)
+            .await
+    }
+
     /// Makes a DELETE request.
     pub(crate) async fn delete<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, Error> {
         self.request::<T, ()>(reqwest::Method::DELETE, endpoint, None, None)
@@ -224,6 +1517,51 @@ impl BlackRoadClient {
         self.get("/health", None).await
     }
 
+    /// Polls the health endpoint every `interval` and yields a [`HealthChange`]
+    /// whenever the overall status or any service status changes.
+    ///
+    /// The first observation is always yielded. Failed polls are yielded as
+    /// errors without ending the stream.
+    pub fn health_stream(
+        &self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<HealthChange, Error>> {
+        stream::unfold(
+            (self.clone(), None, false),
+            move |(client, mut previous, mut wait)| async move {
+                loop {
+                    if wait {
+                        crate::rt::sleep(interval).await;
+                    }
+                    wait = true;
+
+                    let current = match client.health().await {
+                        Ok(current) => current,
+                        Err(e) => return Some((Err(e), (client, previous, wait))),
+                    };
+                    if let Some(change) = diff_health(previous.as_ref(), &current) {
+                        return Some((Ok(change), (client, Some(current), wait)));
+                    }
+                    previous = Some(current);
+                }
+            },
+        )
+    }
+
+    /// Returns the rate-limit budget reported by the most recent response, or
+    /// `None` if no response has carried `X-RateLimit-*` headers yet.
+    pub fn rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.rate_limit.borrow().clone()
+    }
+
+    /// Subscribes to rate-limit budget updates.
+    ///
+    /// The receiver is notified after each response that carries
+    /// `X-RateLimit-*` headers, so callers can slow down before hitting 429s.
+    pub fn rate_limit_updates(&self) -> watch::Receiver<Option<RateLimitStatus>> {
+        self.rate_limit.subscribe()
+    }
+
     /// Gets the API version.
     pub async fn version(&self) -> Result<String, Error> {
         #[derive(serde::Deserialize)]
@@ -234,3 +1572,151 @@ impl BlackRoadClient {
         Ok(response.version)
     }
 }
+
+/// Percent-encodes a resource ID for use as a single URL path segment.
+///
+/// IDs that can never address a resource (empty, `.`/`..`, or containing
+/// control characters) are rejected with a validation error instead of
+/// silently producing a different URL.
+pub(crate) fn path_segment(id: &str) -> Result<String, Error> {
+    if id.trim().is_empty() {
+        return Err(Error::Validation(ValidationErrorBody::new(
+            "resource ID must not be empty",
+        )));
+    }
+    if id == "." || id == ".." {
+        return Err(Error::Validation(ValidationErrorBody::new(format!(
+            "invalid resource ID: {:?}",
+            id
+        ))));
+    }
+    if id.chars().any(char::is_control) {
+        return Err(Error::Validation(ValidationErrorBody::new(format!(
+            "resource ID must not contain control characters: {:?}",
+            id
+        ))));
+    }
+    Ok(urlencoding::encode(id).into_owned())
+}
+
+fn region_url(region_urls: &HashMap<String, String>, region: &str) -> String {
+    region_urls
+        .get(region)
+        .cloned()
+        .unwrap_or_else(|| REGION_URL_TEMPLATE.replace("{region}", region))
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Parses a `Retry-After` header given either as seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Ends `stream` with an error if an item takes longer than `limit` to
+/// arrive.
+fn idle_timeout<S, T>(stream: S, limit: Duration) -> impl Stream<Item = Result<T, Error>>
+where
+    S: Stream<Item = Result<T, Error>> + Unpin,
+{
+    use futures::future::{select, Either};
+
+    stream::unfold(Some(stream), move |stream| async move {
+        let mut stream = stream?;
+        let timer = Box::pin(crate::rt::sleep(limit));
+        match select(stream.next(), timer).await {
+            Either::Left((Some(item), _)) => Some((item, Some(stream))),
+            Either::Left((None, _)) => None,
+            Either::Right(_) => {
+                let error = Error::Connection(format!("No data received for {:?}", limit));
+                Some((Err(error), None))
+            }
+        }
+    })
+}
+
+/// Exponential backoff for the given zero-based attempt, with the upper half
+/// of each step randomized so clients retrying together spread out.
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    let step = Duration::from_secs(1 << attempt.min(16));
+    let jitter = RandomState::new().build_hasher().finish() % 1000;
+    step / 2 + step / 2 * jitter as u32 / 1000
+}
+
+/// Makes a fresh idempotency key from 128 random bits.
+fn idempotency_key() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("the OS or browser provides randomness");
+    hex::encode(bytes)
+}
+
+/// Reads `X-RateLimit-Limit`, `X-RateLimit-Remaining`, and `X-RateLimit-Reset`.
+///
+/// The reset header may be either seconds until reset or a Unix timestamp.
+fn rate_limit_from_headers(headers: &HeaderMap) -> Option<RateLimitStatus> {
+    let number =
+        |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.trim().parse().ok() };
+
+    let limit = number("x-ratelimit-limit")?;
+    let remaining = number("x-ratelimit-remaining")?;
+    let now = Utc::now();
+    let reset_at = number("x-ratelimit-reset").and_then(|reset| {
+        if reset > RATE_LIMIT_EPOCH_THRESHOLD {
+            Utc.timestamp_opt(reset as i64, 0).single()
+        } else {
+            Some(now + chrono::Duration::seconds(reset as i64))
+        }
+    });
+
+    Some(RateLimitStatus {
+        limit,
+        remaining,
+        reset_at,
+        observed_at: now,
+    })
+}
+
+fn diff_health(previous: Option<&HealthStatus>, current: &HealthStatus) -> Option<HealthChange> {
+    let empty = HashMap::new();
+    let before = previous.and_then(|p| p.services.as_ref()).unwrap_or(&empty);
+    let after = current.services.as_ref().unwrap_or(&empty);
+
+    let mut service_transitions: Vec<ServiceTransition> = after
+        .iter()
+        .filter(|(service, status)| before.get(*service) != Some(status))
+        .map(|(service, status)| ServiceTransition {
+            service: service.clone(),
+            from: before.get(service).cloned(),
+            to: Some(status.clone()),
+        })
+        .collect();
+    service_transitions.extend(
+        before
+            .iter()
+            .filter(|(service, _)| !after.contains_key(*service))
+            .map(|(service, status)| ServiceTransition {
+                service: service.clone(),
+                from: Some(status.clone()),
+                to: None,
+            }),
+    );
+
+    let status_changed = !matches!(previous, Some(p) if p.status == current.status);
+    if !status_changed && service_transitions.is_empty() {
+        return None;
+    }
+    Some(HealthChange {
+        previous: previous.cloned(),
+        current: current.clone(),
+        service_transitions,
+    })
+}
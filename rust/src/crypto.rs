@@ -0,0 +1,286 @@
+use crate::errors::Error;
+use crate::types::{LogMemoryOptions, MemoryEntry};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+const ENVELOPE_PREFIX: &str = "enc:v1:";
+const METADATA_FIELD: &str = "_enc";
+const NONCE_LEN: usize = 12;
+/// Context for deriving a division's digest key from its encryption key, so
+/// the same key material is never used directly for both.
+const DIGEST_KEY_CONTEXT: &[u8] = b"blackroad memory content digest v1";
+
+/// Per-division AES-256-GCM keys for client-side memory encryption.
+///
+/// Use with [`MemoryAPI::encrypted`](crate::MemoryAPI::encrypted).
+#[derive(Clone, Default)]
+pub struct MemoryKeyring {
+    keys: HashMap<String, Arc<DivisionKey>>,
+}
+
+struct DivisionKey {
+    cipher: Aes256Gcm,
+    digest_key: [u8; 32],
+}
+
+impl MemoryKeyring {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the 256-bit key for `division`.
+    pub fn with_key(mut self, division: impl Into<String>, key: [u8; 32]) -> Self {
+        let digest_key = hmac(&key, DIGEST_KEY_CONTEXT);
+        self.keys.insert(
+            division.into(),
+            Arc::new(DivisionKey {
+                cipher: Aes256Gcm::new(&key.into()),
+                digest_key,
+            }),
+        );
+        self
+    }
+
+    fn key(&self, division: &str) -> Result<&DivisionKey, Error> {
+        self.keys
+            .get(division)
+            .map(Arc::as_ref)
+            .ok_or_else(|| Error::Encoding(format!("no memory key for division {:?}", division)))
+    }
+
+    /// Returns the hex HMAC-SHA256 of `input` under `division`'s digest key.
+    fn digest(&self, division: &str, input: &[u8]) -> Result<String, Error> {
+        Ok(hex::encode(hmac(&self.key(division)?.digest_key, input)))
+    }
+
+    fn seal(&self, division: &str, plaintext: &[u8]) -> Result<String, Error> {
+        let cipher = &self.key(division)?.cipher;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: division.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::Encoding("memory encryption failed".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{}{}:{}",
+            ENVELOPE_PREFIX,
+            urlencoding::encode(division),
+            hex::encode(sealed)
+        ))
+    }
+
+    /// Decrypts an envelope. Returns `None` for plain text and for envelopes
+    /// sealed with a division key this keyring does not hold.
+    fn open(&self, value: &str) -> Result<Option<Vec<u8>>, Error> {
+        let Some(envelope) = value.strip_prefix(ENVELOPE_PREFIX) else {
+            return Ok(None);
+        };
+        let malformed = || Error::Encoding("malformed encrypted memory field".to_string());
+        let (division, sealed) = envelope.split_once(':').ok_or_else(malformed)?;
+        let division = urlencoding::decode(division).map_err(|_| malformed())?;
+        let Some(key) = self.keys.get(division.as_ref()) else {
+            return Ok(None);
+        };
+
+        let sealed = hex::decode(sealed).map_err(|_| malformed())?;
+        if sealed.len() < NONCE_LEN {
+            return Err(malformed());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        key.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: division.as_bytes(),
+                },
+            )
+            .map(Some)
+            .map_err(|_| Error::Encoding("memory decryption failed".to_string()))
+    }
+}
+
+fn hmac(key: &[u8], input: &[u8]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(input);
+    mac.finalize().into_bytes().into()
+}
+
+impl fmt::Debug for MemoryKeyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryKeyring")
+            .field("divisions", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Encrypts new entries for one division and decrypts any division the
+/// keyring holds.
+#[derive(Debug, Clone)]
+pub(crate) struct MemoryEncryption {
+    pub(crate) keyring: MemoryKeyring,
+    pub(crate) division: String,
+}
+
+impl MemoryEncryption {
+    /// Returns the keyed content digest of `opts`, computed before sealing.
+    pub(crate) fn content_digest(&self, opts: &LogMemoryOptions) -> Result<String, Error> {
+        self.keyring.digest(&self.division, &opts.digest_input())
+    }
+
+    /// Replaces `details` and `metadata` with encrypted envelopes.
+    pub(crate) fn seal(&self, opts: &mut LogMemoryOptions) -> Result<(), Error> {
+        if let Some(details) = &opts.details {
+            opts.details = Some(self.keyring.seal(&self.division, details.as_bytes())?);
+        }
+        if let Some(metadata) = &opts.metadata {
+            let sealed = self
+                .keyring
+                .seal(&self.division, &serde_json::to_vec(metadata)?)?;
+            opts.metadata = Some(HashMap::from([(
+                METADATA_FIELD.to_string(),
+                serde_json::Value::String(sealed),
+            )]));
+        }
+        Ok(())
+    }
+
+    /// Restores `details` and `metadata` sealed with a key in the keyring.
+    pub(crate) fn open(&self, entry: &mut MemoryEntry) -> Result<(), Error> {
+        if let Some(details) = &entry.details {
+            if let Some(plain) = self.keyring.open(details)? {
+                entry.details = Some(
+                    String::from_utf8(plain)
+                        .map_err(|e| Error::Encoding(format!("decrypted details: {}", e)))?,
+                );
+            }
+        }
+        let sealed = entry
+            .metadata
+            .as_ref()
+            .filter(|m| m.len() == 1)
+            .and_then(|m| m.get(METADATA_FIELD))
+            .and_then(|v| v.as_str());
+        if let Some(sealed) = sealed {
+            if let Some(plain) = self.keyring.open(sealed)? {
+                entry.metadata = Some(serde_json::from_slice(&plain)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn encryption(division: &str) -> MemoryEncryption {
+        MemoryEncryption {
+            keyring: MemoryKeyring::new()
+                .with_key("ops", [1; 32])
+                .with_key("research", [2; 32]),
+            division: division.to_string(),
+        }
+    }
+
+    fn sealed_entry(opts: LogMemoryOptions) -> MemoryEntry {
+        MemoryEntry {
+            hash: "h".to_string(),
+            timestamp: Utc::now(),
+            action: opts.action,
+            entity: opts.entity,
+            details: opts.details,
+            agent: opts.agent,
+            tags: opts.tags,
+            prev_hash: None,
+            metadata: opts.metadata,
+        }
+    }
+
+    fn opts() -> LogMemoryOptions {
+        LogMemoryOptions {
+            action: "deploy".to_string(),
+            entity: "svc".to_string(),
+            details: Some("rolled out v2".to_string()),
+            metadata: Some(HashMap::from([(
+                "region".to_string(),
+                serde_json::json!("eu"),
+            )])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_trips_details_and_metadata() {
+        let encryption = encryption("ops");
+        let mut sealed = opts();
+        encryption.seal(&mut sealed).unwrap();
+        let details = sealed.details.clone().unwrap();
+        assert!(details.starts_with("enc:v1:ops:"));
+        assert!(!details.contains("rolled out"));
+        assert_eq!(sealed.metadata.as_ref().unwrap().len(), 1);
+
+        let mut entry = sealed_entry(sealed);
+        encryption.open(&mut entry).unwrap();
+        assert_eq!(entry.details, opts().details);
+        assert_eq!(entry.metadata, opts().metadata);
+    }
+
+    #[test]
+    fn opens_other_divisions_in_the_keyring() {
+        let mut sealed = opts();
+        encryption("research").seal(&mut sealed).unwrap();
+        let mut entry = sealed_entry(sealed);
+        encryption("ops").open(&mut entry).unwrap();
+        assert_eq!(entry.details, opts().details);
+    }
+
+    #[test]
+    fn leaves_entries_sealed_with_unknown_keys() {
+        let mut sealed = opts();
+        encryption("ops").seal(&mut sealed).unwrap();
+        let details = sealed.details.clone();
+        let mut entry = sealed_entry(sealed);
+        let other = MemoryEncryption {
+            keyring: MemoryKeyring::new().with_key("research", [2; 32]),
+            division: "research".to_string(),
+        };
+        other.open(&mut entry).unwrap();
+        assert_eq!(entry.details, details);
+    }
+
+    #[test]
+    fn rejects_tampered_ciphertext() {
+        let mut sealed = opts();
+        encryption("ops").seal(&mut sealed).unwrap();
+        let mut details = sealed.details.clone().unwrap();
+        let last = if details.ends_with('0') { "1" } else { "0" };
+        details.replace_range(details.len() - 1.., last);
+        sealed.details = Some(details);
+        let mut entry = sealed_entry(sealed);
+        assert!(encryption("ops").open(&mut entry).is_err());
+    }
+
+    #[test]
+    fn content_digest_is_keyed() {
+        let plain = opts().content_digest();
+        let ops = encryption("ops").content_digest(&opts()).unwrap();
+        let research = encryption("research").content_digest(&opts()).unwrap();
+        assert_ne!(ops, plain);
+        assert_ne!(ops, research);
+        assert_eq!(ops, encryption("ops").content_digest(&opts()).unwrap());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::client::{path_segment, BlackRoadClient};
+use crate::errors::Error;
+use crate::types::{Agent, CreateDivisionOptions, Division, DivisionStats, UpdateDivisionOptions};
+use serde::Deserialize;
+
+/// API for divisions, their members, and division-level rollups.
+#[derive(Debug, Clone)]
+pub struct DivisionAPI {
+    client: BlackRoadClient,
+}
+
+#[derive(Deserialize)]
+struct DivisionsResponse {
+    divisions: Vec<Division>,
+}
+
+#[derive(Deserialize)]
+struct MembersResponse {
+    agents: Vec<Agent>,
+}
+
+#[derive(Deserialize)]
+struct DeleteResponse {
+    #[allow(dead_code)]
+    deleted: bool,
+}
+
+impl DivisionAPI {
+    pub(crate) fn new(client: BlackRoadClient) -> Self {
+        Self { client }
+    }
+
+    /// Lists all divisions.
+    pub async fn list(&self) -> Result<Vec<Division>, Error> {
+        let response: DivisionsResponse = self.client.get("/divisions", None).await?;
+        Ok(response.divisions)
+    }
+
+    /// Gets a division by name.
+    pub async fn get(&self, division: &str) -> Result<Division, Error> {
+        self.client
+            .get(&format!("/divisions/{}", path_segment(division)?), None)
+            .await
+    }
+
+    /// Creates a division.
+    pub async fn create(&self, opts: CreateDivisionOptions) -> Result<Division, Error> {
+        opts.validate()?;
+        self.client.post("/divisions", &opts).await
+    }
+
+    /// Changes a division's name, description, lead, or metadata. Only the
+    /// fields set in `opts` are sent.
+    pub async fn update(
+        &self,
+        division: &str,
+        opts: UpdateDivisionOptions,
+    ) -> Result<Division, Error> {
+        opts.validate()?;
+        self.client
+            .patch(&format!("/divisions/{}", path_segment(division)?), &opts)
+            .await
+    }
+
+    /// Deletes a division. The API rejects this while agents still belong
+    /// to it.
+    pub async fn delete(&self, division: &str) -> Result<(), Error> {
+        let _: DeleteResponse = self
+            .client
+            .delete(&format!("/divisions/{}", path_segment(division)?))
+            .await?;
+        Ok(())
+    }
+
+    /// Lists the agents that belong to a division.
+    pub async fn members(&self, division: &str) -> Result<Vec<Agent>, Error> {
+        let response: MembersResponse = self
+            .client
+            .get(
+                &format!("/divisions/{}/agents", path_segment(division)?),
+                None,
+            )
+            .await?;
+        Ok(response.agents)
+    }
+
+    /// Gets a rollup of agent, task, and memory activity for a division.
+    pub async fn stats(&self, division: &str) -> Result<DivisionStats, Error> {
+        self.client
+            .get(
+                &format!("/divisions/{}/stats", path_segment(division)?),
+                None,
+            )
+            .await
+    }
+}
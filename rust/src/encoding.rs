@@ -0,0 +1,172 @@
+use crate::errors::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+
+const JSON: &str = "application/json";
+#[cfg(feature = "msgpack")]
+const MSGPACK: &str = "application/msgpack";
+#[cfg(feature = "cbor")]
+const CBOR: &str = "application/cbor";
+
+/// Hook for customising how JSON bodies are written and read.
+///
+/// Request bodies reach [`encode`](Self::encode) as a [`serde_json::Value`];
+/// response bodies are parsed by [`decode`](Self::decode) before being
+/// converted to the SDK's types. Install one with
+/// [`ClientConfig::json_codec`](crate::ClientConfig::json_codec).
+///
+/// Key order and number precision follow serde_json's `preserve_order` and
+/// `arbitrary_precision` features, which this crate forwards under the same
+/// names. Non-finite floats are already `null` by the time a body reaches
+/// `encode`, so reject them when building the options instead.
+pub trait JsonCodec: Send + Sync + fmt::Debug {
+    /// Writes a request body.
+    fn encode(&self, body: &serde_json::Value) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(body)?)
+    }
+
+    /// Parses a response body.
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value, Error> {
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+/// Writes compact JSON with object keys sorted, matching the form the API
+/// hashes memory entries in.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanonicalJson;
+
+impl JsonCodec for CanonicalJson {
+    fn encode(&self, body: &serde_json::Value) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(&sorted(body))?)
+    }
+}
+
+/// Parses a JSON response body, reporting mismatches as [`Error::Decode`].
+pub(crate) fn decode_json<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value =
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|e| Error::decode(e, body))?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+fn sorted(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), sorted(v)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(sorted).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+/// Body encoding used for requests and requested for responses.
+///
+/// Responses are decoded according to their `Content-Type`, so a server that
+/// answers in JSON regardless is handled transparently. A request rejected with
+/// `415 Unsupported Media Type` is retried once as JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WireFormat {
+    #[default]
+    Json,
+    /// MessagePack (`application/msgpack`). Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// CBOR (`application/cbor`). Requires the `cbor` feature.
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl WireFormat {
+    /// Returns the media type sent in `Content-Type`.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            WireFormat::Json => JSON,
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack => MSGPACK,
+            #[cfg(feature = "cbor")]
+            WireFormat::Cbor => CBOR,
+        }
+    }
+
+    /// Returns the `Accept` header, preferring this format over JSON.
+    pub(crate) fn accept(&self) -> String {
+        match self {
+            WireFormat::Json => JSON.to_string(),
+            #[allow(unreachable_patterns)]
+            other => format!("{}, {};q=0.5", other.content_type(), JSON),
+        }
+    }
+
+    pub(crate) fn encode<B: Serialize>(
+        &self,
+        body: &B,
+        json: Option<&dyn JsonCodec>,
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            WireFormat::Json => match json {
+                Some(codec) => codec.encode(&serde_json::to_value(body)?),
+                None => Ok(serde_json::to_vec(body)?),
+            },
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack => {
+                rmp_serde::to_vec_named(body).map_err(|e| Error::Encoding(e.to_string()))
+            }
+            #[cfg(feature = "cbor")]
+            WireFormat::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(body, &mut buf)
+                    .map_err(|e| Error::Encoding(e.to_string()))?;
+                Ok(buf)
+            }
+        }
+    }
+
+    /// Decodes a response body according to its `Content-Type`, defaulting to
+    /// JSON.
+    pub(crate) fn decode<T: DeserializeOwned>(
+        content_type: Option<&str>,
+        body: &[u8],
+        json: Option<&dyn JsonCodec>,
+    ) -> Result<T, Error> {
+        let media_type = content_type
+            .and_then(|c| c.split(';').next())
+            .map(str::trim)
+            .unwrap_or(JSON);
+        match media_type {
+            #[cfg(feature = "msgpack")]
+            MSGPACK | "application/x-msgpack" => {
+                rmp_serde::from_slice(body).map_err(|e| Error::Encoding(e.to_string()))
+            }
+            #[cfg(feature = "cbor")]
+            CBOR => ciborium::from_reader(body).map_err(|e| Error::Encoding(e.to_string())),
+            _ => match json {
+                Some(codec) => serde_path_to_error::deserialize(codec.decode(body)?)
+                    .map_err(|e| Error::decode(e, body)),
+                None => decode_json(body),
+            },
+        }
+    }
+}
+
+/// Compresses a request body for `Content-Encoding: gzip`.
+pub(crate) fn gzip(body: &[u8]) -> Result<Vec<u8>, Error> {
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Default::default());
+    encoder.write_all(body)?;
+    Ok(encoder.finish()?)
+}
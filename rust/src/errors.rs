@@ -1,5 +1,13 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
 use thiserror::Error;
 
+/// Characters of a response body kept in [`Error::Decode`].
+const DECODE_SNIPPET_CHARS: usize = 512;
+
 /// Errors that can occur when using the BlackRoad SDK.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -11,13 +19,17 @@ pub enum Error {
     #[error("not found: {0}")]
     NotFound(String),
 
+    /// The resource was changed by another writer (409 or 412).
+    #[error("conflict: {0}")]
+    Conflict(String),
+
     /// Rate limit exceeded.
     #[error("rate limit exceeded, retry after {retry_after} seconds")]
     RateLimit { retry_after: u64 },
 
-    /// Validation error.
+    /// Validation error, from a 422 response or a request rejected locally.
     #[error("validation error: {0}")]
-    Validation(String),
+    Validation(ValidationErrorBody),
 
     /// Network or connection error.
     #[error("connection error: {0}")]
@@ -27,11 +39,185 @@ pub enum Error {
     #[error("request error: {0}")]
     Request(#[from] reqwest::Error),
 
+    /// Local file I/O error.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
     /// JSON serialization/deserialization error.
     #[error("serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    /// A response body did not match the type the SDK expected.
+    #[error("failed to decode response at `{path}`: {source} (body: {raw_body_snippet})")]
+    Decode {
+        /// Path to the offending field, e.g. `tasks[3].priority`.
+        path: String,
+        /// The start of the response body, truncated to 512 characters.
+        raw_body_snippet: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The client's request queue was full and the request was shed.
+    #[error("overloaded: {0}")]
+    Overloaded(String),
+
+    /// The client's circuit breaker is open after repeated failures, so the
+    /// request was not sent.
+    #[error("circuit open after repeated failures, retry at {retry_at}")]
+    CircuitOpen { retry_at: DateTime<Utc> },
+
+    /// A wait such as `wait_for_completion` ran out of time.
+    #[error("timed out: {0}")]
+    Timeout(String),
+
+    /// Non-JSON body encoding or decoding error.
+    #[error("encoding error: {0}")]
+    Encoding(String),
+
     /// Generic API error.
     #[error("API error ({status}): {message}")]
     Api { status: u16, message: String },
 }
+
+impl Error {
+    /// Returns true if the error is likely temporary and the same request may
+    /// succeed later: connection failures, rate limiting, a full request
+    /// queue, an open circuit breaker, and server errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Connection(_)
+            | Error::Request(_)
+            | Error::RateLimit { .. }
+            | Error::Overloaded(_)
+            | Error::CircuitOpen { .. } => true,
+            Error::Api { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Maps an unsuccessful status and its body to an error.
+    pub(crate) fn from_status(
+        status: StatusCode,
+        body: String,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => Error::Authentication("Invalid API key".to_string()),
+            StatusCode::NOT_FOUND => Error::NotFound(body),
+            StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED => Error::Conflict(body),
+            StatusCode::UNPROCESSABLE_ENTITY => Error::Validation(ValidationErrorBody::parse(body)),
+            StatusCode::TOO_MANY_REQUESTS => Error::RateLimit {
+                retry_after: retry_after.map_or(1, |d| d.as_secs().max(1)),
+            },
+            _ => Error::Api {
+                status: status.as_u16(),
+                message: body,
+            },
+        }
+    }
+
+    /// Describes a response body that failed to deserialize.
+    pub(crate) fn decode(
+        error: serde_path_to_error::Error<serde_json::Error>,
+        body: &[u8],
+    ) -> Self {
+        let body = String::from_utf8_lossy(body);
+        let raw_body_snippet = match body.char_indices().nth(DECODE_SNIPPET_CHARS) {
+            Some((end, _)) => format!("{}…", &body[..end]),
+            None => body.into_owned(),
+        };
+        Error::Decode {
+            path: error.path().to_string(),
+            raw_body_snippet,
+            source: error.into_inner(),
+        }
+    }
+
+    /// Makes an equivalent error, for reporting one failure against several
+    /// items. Wrapped library errors are carried over by message.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Error::Authentication(m) => Error::Authentication(m.clone()),
+            Error::NotFound(m) => Error::NotFound(m.clone()),
+            Error::Conflict(m) => Error::Conflict(m.clone()),
+            Error::RateLimit { retry_after } => Error::RateLimit {
+                retry_after: *retry_after,
+            },
+            Error::Validation(body) => Error::Validation(body.clone()),
+            Error::Connection(m) => Error::Connection(m.clone()),
+            Error::Request(e) => Error::Connection(e.to_string()),
+            Error::Io(e) => Error::Io(std::io::Error::new(e.kind(), e.to_string())),
+            Error::Serialization(e) => Error::Serialization(serde::de::Error::custom(e)),
+            Error::Decode {
+                path,
+                raw_body_snippet,
+                source,
+            } => Error::Decode {
+                path: path.clone(),
+                raw_body_snippet: raw_body_snippet.clone(),
+                source: serde::de::Error::custom(source),
+            },
+            Error::Overloaded(m) => Error::Overloaded(m.clone()),
+            Error::CircuitOpen { retry_at } => Error::CircuitOpen {
+                retry_at: *retry_at,
+            },
+            Error::Timeout(m) => Error::Timeout(m.clone()),
+            Error::Encoding(m) => Error::Encoding(m.clone()),
+            Error::Api { status, message } => Error::Api {
+                status: *status,
+                message: message.clone(),
+            },
+        }
+    }
+}
+
+/// The body of a 422 response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationErrorBody {
+    pub message: String,
+    /// Per-field problems; empty when the error is not tied to a field.
+    #[serde(default)]
+    pub errors: Vec<FieldError>,
+}
+
+/// A validation problem with a single request field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Path to the field, e.g. `metadata.owner`.
+    pub field: String,
+    pub message: String,
+    /// Machine-readable reason, e.g. `required` or `too_long`.
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+impl ValidationErrorBody {
+    /// Creates a body with a message and no field errors.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Parses a 422 response body, keeping unrecognised bodies as the message.
+    pub(crate) fn parse(body: String) -> Self {
+        serde_json::from_str(&body).unwrap_or_else(|_| Self::new(body))
+    }
+
+    /// Returns the errors for a given field.
+    pub fn field<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a FieldError> + 'a {
+        self.errors.iter().filter(move |e| e.field == name)
+    }
+}
+
+impl fmt::Display for ValidationErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        for error in &self.errors {
+            write!(f, "; {}: {}", error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use crate::client::{backoff, BlackRoadClient};
+use crate::errors::Error;
+use crate::polling::{long_poll_wait, poll_stream, push_or_poll, Batch};
+use crate::types::{Event, EventOptions};
+use futures::{stream, Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Pause before polling again when the API answers a long poll early.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[cfg(not(target_arch = "wasm32"))]
+type Frames = stream::BoxStream<'static, Result<String, Error>>;
+#[cfg(target_arch = "wasm32")]
+type Frames = stream::LocalBoxStream<'static, Result<String, Error>>;
+
+/// API for realtime events pushed over a WebSocket.
+#[derive(Debug, Clone)]
+pub struct EventAPI {
+    client: BlackRoadClient,
+}
+
+enum Connection {
+    Closed { attempt: u32 },
+    Open(Frames),
+}
+
+#[derive(Deserialize)]
+struct PolledEvents {
+    events: Vec<Event>,
+    cursor: String,
+}
+
+impl EventAPI {
+    pub(crate) fn new(client: BlackRoadClient) -> Self {
+        Self { client }
+    }
+
+    /// Returns a stream of events as they happen.
+    ///
+    /// The socket is opened when the stream is first polled. If it drops, the
+    /// stream yields the error and reconnects with backoff; events sent while
+    /// disconnected are not replayed. Errors that a retry cannot fix, such as
+    /// rejected credentials, end the stream.
+    ///
+    /// If the socket cannot be opened, events are long polled instead; see
+    /// [`StreamTransport`](crate::StreamTransport). Long polling follows a
+    /// cursor, so no events are missed between polls.
+    pub fn subscribe(
+        &self,
+        opts: Option<EventOptions>,
+    ) -> impl Stream<Item = Result<Event, Error>> {
+        let params = event_params(opts, &self.client);
+        let client = self.client.clone();
+        push_or_poll(
+            self.client.stream_transport(),
+            Box::pin(self.socket_events(params.clone())),
+            move || Box::pin(poll_events(client, params)),
+        )
+    }
+
+    /// Receives events over the WebSocket, reconnecting when it drops.
+    fn socket_events(
+        &self,
+        params: HashMap<String, String>,
+    ) -> impl Stream<Item = Result<Event, Error>> {
+        let client = self.client.clone();
+
+        stream::unfold(Some(Connection::Closed { attempt: 0 }), move |state| {
+            let client = client.clone();
+            let params = params.clone();
+            async move {
+                let mut state = state?;
+                loop {
+                    match state {
+                        Connection::Closed { attempt } => {
+                            if attempt > 0 {
+                                crate::rt::sleep(backoff(attempt - 1)).await;
+                            }
+                            match connect(&client, &params).await {
+                                Ok(frames) => state = Connection::Open(frames),
+                                Err(e) if e.is_transient() => {
+                                    let next = Connection::Closed {
+                                        attempt: attempt + 1,
+                                    };
+                                    return Some((Err(e), Some(next)));
+                                }
+                                Err(e) => return Some((Err(e), None)),
+                            }
+                        }
+                        Connection::Open(mut frames) => match frames.next().await {
+                            Some(Ok(text)) => {
+                                let event = serde_json::from_str(&text).map_err(Error::from);
+                                return Some((event, Some(Connection::Open(frames))));
+                            }
+                            Some(Err(e)) => {
+                                return Some((Err(e), Some(Connection::Closed { attempt: 1 })))
+                            }
+                            None => {
+                                let e = Error::Connection("event stream closed".to_string());
+                                return Some((Err(e), Some(Connection::Closed { attempt: 1 })));
+                            }
+                        },
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Long polls `/events`, picking up after the last batch each time.
+fn poll_events(
+    client: BlackRoadClient,
+    params: HashMap<String, String>,
+) -> impl Stream<Item = Result<Event, Error>> {
+    let wait = long_poll_wait(client.timeout());
+    poll_stream(None, POLL_INTERVAL, move |cursor: Option<String>| {
+        let client = client.clone();
+        let mut params = params.clone();
+        async move {
+            params.insert("wait".to_string(), wait.as_secs().to_string());
+            if let Some(cursor) = cursor {
+                params.insert("cursor".to_string(), cursor);
+            }
+            let response: PolledEvents = client.get("/events/poll", Some(&params)).await?;
+            Ok(Batch {
+                items: response.events,
+                cursor: Some(response.cursor),
+                done: false,
+            })
+        }
+    })
+}
+
+fn event_params(opts: Option<EventOptions>, client: &BlackRoadClient) -> HashMap<String, String> {
+    let opts = opts.unwrap_or_default();
+    let mut params = HashMap::new();
+    if let Some(d) = opts.division.or_else(|| client.scope().division.clone()) {
+        params.insert("division".to_string(), d);
+    }
+    if !opts.kinds.is_empty() {
+        params.insert("types".to_string(), opts.kinds.join(","));
+    }
+    params
+}
+
+/// The `/events` URL with its scheme switched from HTTP(S) to WS(S).
+fn socket_url(client: &BlackRoadClient, params: &HashMap<String, String>) -> String {
+    let url = client.url("/events", Some(params));
+    match url.split_once("://") {
+        Some(("https", rest)) => format!("wss://{}", rest),
+        Some(("http", rest)) => format!("ws://{}", rest),
+        _ => url,
+    }
+}
+
+fn socket_error(e: impl std::fmt::Display) -> Error {
+    Error::Connection(format!("WebSocket failed: {}", e))
+}
+
+/// Opens the socket and returns its text frames.
+#[cfg(not(target_arch = "wasm32"))]
+async fn connect(
+    client: &BlackRoadClient,
+    params: &HashMap<String, String>,
+) -> Result<Frames, Error> {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::http::HeaderValue;
+    use tokio_tungstenite::tungstenite::{self, Message};
+    use tokio_tungstenite::Connector;
+
+    let mut request = socket_url(client, params)
+        .into_client_request()
+        .map_err(socket_error)?;
+    let token = client.credentials().await?;
+    let headers = request.headers_mut();
+    headers.insert(
+        "Authorization",
+        HeaderValue::from_str(&format!("Bearer {}", token)).map_err(socket_error)?,
+    );
+    headers.insert(
+        "User-Agent",
+        HeaderValue::from_str(client.user_agent()).map_err(socket_error)?,
+    );
+
+    let connector = client.websocket_tls().map(Connector::NativeTls);
+    let connecting =
+        tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector);
+    let (socket, _) = match connecting.await {
+        Ok(connected) => connected,
+        Err(tungstenite::Error::Http(response)) => {
+            return Err(match response.status().as_u16() {
+                401 | 403 => Error::Authentication("event stream rejected credentials".to_string()),
+                429 => Error::RateLimit {
+                    retry_after: response
+                        .headers()
+                        .get("Retry-After")
+                        .and_then(|v| v.to_str().ok()?.parse().ok())
+                        .unwrap_or(60),
+                },
+                status => Error::Api {
+                    status,
+                    message: "event stream handshake failed".to_string(),
+                },
+            })
+        }
+        Err(e) => return Err(socket_error(e)),
+    };
+
+    Ok(socket
+        .filter_map(|message| async move {
+            match message {
+                Ok(Message::Text(text)) => Some(Ok(text)),
+                Ok(_) => None,
+                Err(e) => Some(Err(socket_error(e))),
+            }
+        })
+        .boxed())
+}
+
+/// Opens the socket and returns its text frames. Browsers cannot set headers
+/// on a WebSocket, so the credential goes in the query string.
+#[cfg(target_arch = "wasm32")]
+async fn connect(
+    client: &BlackRoadClient,
+    params: &HashMap<String, String>,
+) -> Result<Frames, Error> {
+    use gloo_net::websocket::{futures::WebSocket, Message};
+
+    let mut params = params.clone();
+    params.insert("access_token".to_string(), client.credentials().await?);
+    let socket = WebSocket::open(&socket_url(client, &params)).map_err(socket_error)?;
+
+    Ok(socket
+        .filter_map(|message| async move {
+            match message {
+                Ok(Message::Text(text)) => Some(Ok(text)),
+                Ok(Message::Bytes(_)) => None,
+                Err(e) => Some(Err(socket_error(e))),
+            }
+        })
+        .boxed_local())
+}
@@ -22,16 +22,78 @@
 //! }
 //! ```
 
+mod agents;
+mod attestation;
+mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod breaker;
+mod cache;
 mod client;
+#[cfg(feature = "encryption")]
+mod crypto;
+mod divisions;
+mod encoding;
 mod errors;
-mod types;
-mod agents;
-mod tasks;
+mod events;
 mod memory;
+mod metrics;
+mod middleware;
+mod organizations;
+mod outbox;
+mod pinning;
+mod polling;
+mod queue;
+mod ratelimit;
+mod retry;
+mod rt;
+mod selection;
+mod sse;
+mod tasks;
+mod tls;
+mod transport;
+mod types;
+mod validation;
+pub mod webhooks;
+#[cfg(feature = "tracing")]
+mod wirelog;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod worker;
 
-pub use client::{BlackRoadClient, ClientConfig};
-pub use errors::Error;
-pub use types::*;
 pub use agents::AgentAPI;
-pub use tasks::TaskAPI;
+#[cfg(not(target_arch = "wasm32"))]
+pub use agents::{AgentSession, HeartbeatObserver, HeartbeatPolicy};
+pub use async_trait::async_trait;
+pub use attestation::Attestation;
+pub use auth::{AuthProvider, ClientCredentials, OidcTokenExchange, StaticApiKey, SubjectToken};
+pub use breaker::CircuitBreakerConfig;
+pub use client::{BlackRoadClient, ClientConfig, ReadOptions, RequestOptions, Scope};
+#[cfg(feature = "encryption")]
+pub use crypto::MemoryKeyring;
+pub use divisions::DivisionAPI;
+pub use encoding::{CanonicalJson, JsonCodec, WireFormat};
+pub use errors::{Error, FieldError, ValidationErrorBody};
+pub use events::EventAPI;
 pub use memory::MemoryAPI;
+pub use metrics::{MetricsSink, RequestMetrics};
+pub use middleware::{Interceptor, ResponseInfo};
+pub use organizations::OrganizationAPI;
+pub use outbox::{FlushReport, LogOutcome, OfflineMemoryLogger, Outbox, OutboxOp};
+#[cfg(not(target_arch = "wasm32"))]
+pub use pinning::FilePinSink;
+pub use pinning::{ChainHeadKey, PinSink, PresignedUrlSink};
+pub use polling::StreamTransport;
+pub use queue::{RequestPriority, RequestQueueConfig, ShedPolicy};
+pub use ratelimit::RateLimiterConfig;
+pub use reqwest::{Method, Request, StatusCode, Url};
+pub use retry::RetryObserver;
+pub use selection::{LeastLoaded, SelectionStrategy};
+pub use tasks::TaskAPI;
+pub use tls::ClientIdentity;
+pub use transport::Transport;
+#[cfg(not(target_arch = "wasm32"))]
+pub use transport::{MockTransport, RecordedRequest};
+pub use types::*;
+pub use validation::{MAX_METADATA_BYTES, TASK_PRIORITIES};
+#[cfg(feature = "tracing")]
+pub use wirelog::WireLogConfig;
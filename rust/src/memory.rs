@@ -1,13 +1,47 @@
-use crate::client::BlackRoadClient;
-use crate::errors::Error;
-use crate::types::{LogMemoryOptions, MemoryEntry, MemoryQueryOptions, Stats, VerifyChainResult};
+use crate::client::{path_segment, BlackRoadClient, Payload};
+#[cfg(feature = "encryption")]
+use crate::crypto::{MemoryEncryption, MemoryKeyring};
+use crate::errors::{Error, ValidationErrorBody};
+use crate::pinning::{ChainHeadKey, PinSink};
+use crate::polling::DEFAULT_PAGE_SIZE;
+use crate::types::{
+    Attachment, BroadcastStatus, ChainHead, ChainIssue, ExportFormat, FacetCount, Faceted,
+    ImportReport, LogMemoryOptions, MemoryEntry, MemoryPage, MemoryQueryOptions, MemoryStats,
+    MemoryStatsOptions, PinVerification, ScoredMemoryEntry, StateDelta, StatsInterval, Timeline,
+    TimelineOptions, VerifyChainResult,
+};
+use bytes::Bytes;
+use chrono::SecondsFormat;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
+
+const DEFAULT_TIMELINE_MAX_ENTRIES: usize = 1000;
+/// Entries sent per request by `log_batch` and `import`.
+const LOG_BATCH_SIZE: usize = 100;
+const CSV_HEADER: &str = "hash,timestamp,action,entity,details,agent,tags,prev_hash,metadata\n";
 
 /// API for memory operations.
 #[derive(Debug, Clone)]
 pub struct MemoryAPI {
     client: BlackRoadClient,
+    #[cfg(feature = "encryption")]
+    encryption: Option<MemoryEncryption>,
+}
+
+#[derive(Deserialize)]
+struct FacetedEntriesResponse {
+    entries: Vec<MemoryEntry>,
+    #[serde(default)]
+    facets: HashMap<String, Vec<FacetCount>>,
+}
+
+#[derive(Deserialize)]
+struct SemanticSearchResponse {
+    results: Vec<ScoredMemoryEntry>,
 }
 
 #[derive(Deserialize)]
@@ -15,6 +49,11 @@ struct EntriesResponse {
     entries: Vec<MemoryEntry>,
 }
 
+#[derive(Deserialize)]
+struct StateVersionResponse {
+    version: i64,
+}
+
 #[derive(Deserialize)]
 struct BroadcastResponse {
     broadcast_id: String,
@@ -22,55 +61,396 @@ struct BroadcastResponse {
 
 impl MemoryAPI {
     pub(crate) fn new(client: BlackRoadClient) -> Self {
-        Self { client }
+        Self {
+            client,
+            #[cfg(feature = "encryption")]
+            encryption: None,
+        }
+    }
+
+    /// Returns a copy of the API that encrypts `details` and `metadata` of new
+    /// entries with `division`'s key, and decrypts entries read back for any
+    /// division in `keyring`.
+    ///
+    /// Actions, entities, and tags stay in plain text so entries can still be
+    /// queried. Entries sealed with a key the keyring lacks are returned as
+    /// stored. Requires the `encryption` feature.
+    #[cfg(feature = "encryption")]
+    pub fn encrypted(&self, keyring: MemoryKeyring, division: &str) -> MemoryAPI {
+        Self {
+            client: self.client.clone(),
+            encryption: Some(MemoryEncryption {
+                keyring,
+                division: division.to_string(),
+            }),
+        }
     }
 
     /// Logs a new memory entry.
+    ///
+    /// If `opts.dedupe_window_secs` is set, the entry's content digest is sent
+    /// along and the API returns the existing entry for a duplicate.
     pub async fn log(&self, opts: LogMemoryOptions) -> Result<MemoryEntry, Error> {
-        self.client.post("/memory", &opts).await
+        let body = self.log_body(opts)?;
+        let mut entry: MemoryEntry = self.client.post("/memory", &body).await?;
+        self.open(std::slice::from_mut(&mut entry))?;
+        Ok(entry)
     }
 
-    /// Queries memory entries.
-    pub async fn query(&self, opts: Option<MemoryQueryOptions>) -> Result<Vec<MemoryEntry>, Error> {
-        let mut params = HashMap::new();
-        params.insert("limit".to_string(), "100".to_string());
+    /// Logs `entries` in order, sending up to 100 per request.
+    ///
+    /// Every entry is validated before anything is sent. Chunks are sent one
+    /// at a time so the entries are chained in the order given; if a chunk
+    /// fails, the entries before it stay logged and the rest are not sent.
+    /// Use [`import`](Self::import) to learn how far a large load got.
+    pub async fn log_batch(
+        &self,
+        entries: Vec<LogMemoryOptions>,
+    ) -> Result<Vec<MemoryEntry>, Error> {
+        let bodies = entries
+            .into_iter()
+            .enumerate()
+            .map(|(index, opts)| self.log_body(opts).map_err(|e| in_batch(e, index)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut logged = Vec::with_capacity(bodies.len());
+        for chunk in bodies.chunks(LOG_BATCH_SIZE) {
+            logged.extend(self.post_batch(chunk).await?);
+        }
+        Ok(logged)
+    }
 
-        if let Some(opts) = opts {
-            if let Some(s) = opts.search {
-                params.insert("q".to_string(), s);
-            }
-            if let Some(a) = opts.action {
-                params.insert("action".to_string(), a);
-            }
-            if let Some(e) = opts.entity {
-                params.insert("entity".to_string(), e);
+    /// Logs the JSON Lines in `reader`, one [`LogMemoryOptions`] object per
+    /// line, in order and up to 100 per request. Blank lines are skipped.
+    ///
+    /// Stops at the first malformed line or failed request. Entries before
+    /// the failing chunk stay logged, so a failed import can be resumed by
+    /// skipping the first [`ImportReport::logged`] entries.
+    pub async fn import<R>(&self, reader: R) -> ImportReport
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let lines = stream::unfold(reader.lines(), |mut lines| async move {
+            lines
+                .next_line()
+                .await
+                .transpose()
+                .map(|line| (line, lines))
+        });
+        self.import_lines(lines).await
+    }
+
+    pub(crate) async fn import_lines(
+        &self,
+        lines: impl Stream<Item = std::io::Result<String>>,
+    ) -> ImportReport {
+        futures::pin_mut!(lines);
+        let mut report = ImportReport::default();
+        let mut line_number = 0;
+        let mut chunk = Vec::with_capacity(LOG_BATCH_SIZE);
+        loop {
+            let line = match lines.next().await {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => {
+                    report.error = Some(e.into());
+                    return report;
+                }
+                None => break,
+            };
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
             }
-            if let Some(tags) = opts.tags {
-                params.insert("tags".to_string(), tags.join(","));
+            let body = serde_json::from_str(&line)
+                .map_err(Error::from)
+                .and_then(|opts| self.log_body(opts))
+                .map_err(|e| on_line(e, line_number));
+            match body {
+                Ok(body) => chunk.push(body),
+                Err(e) => {
+                    report.error = Some(e);
+                    return report;
+                }
             }
-            if let Some(since) = opts.since {
-                params.insert("since".to_string(), since.to_rfc3339());
+            if chunk.len() == LOG_BATCH_SIZE {
+                if let Err(e) = self.import_chunk(&mut chunk, &mut report).await {
+                    report.error = Some(e);
+                    return report;
+                }
             }
-            if let Some(until) = opts.until {
-                params.insert("until".to_string(), until.to_rfc3339());
+        }
+        if !chunk.is_empty() {
+            if let Err(e) = self.import_chunk(&mut chunk, &mut report).await {
+                report.error = Some(e);
             }
-            if let Some(l) = opts.limit {
-                params.insert("limit".to_string(), l.to_string());
+        }
+        report
+    }
+
+    async fn import_chunk(
+        &self,
+        chunk: &mut Vec<serde_json::Value>,
+        report: &mut ImportReport,
+    ) -> Result<(), Error> {
+        let entries = self.post_batch(chunk).await?;
+        chunk.clear();
+        report.logged += entries.len() as u64;
+        if let Some(last) = entries.last() {
+            report.last_hash = Some(last.hash.clone());
+        }
+        Ok(())
+    }
+
+    async fn post_batch(&self, bodies: &[serde_json::Value]) -> Result<Vec<MemoryEntry>, Error> {
+        let body = serde_json::json!({ "entries": bodies });
+        let mut response: EntriesResponse = self.client.post("/memory/batch", &body).await?;
+        self.open(&mut response.entries)?;
+        Ok(response.entries)
+    }
+
+    /// Validates `opts`, applies the client's scope, and seals it, returning
+    /// the request body for one entry.
+    fn log_body(&self, mut opts: LogMemoryOptions) -> Result<serde_json::Value, Error> {
+        opts.validate()?;
+        let scope = self.client.scope();
+        if opts.agent.is_none() {
+            opts.agent = scope.agent_id.clone();
+        }
+        if !scope.default_tags.is_empty() {
+            let tags = opts.tags.get_or_insert_with(Vec::new);
+            for tag in &scope.default_tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
             }
-            if let Some(o) = opts.offset {
-                params.insert("offset".to_string(), o.to_string());
+        }
+        let digest = match opts.dedupe_window_secs {
+            Some(_) => Some(self.content_digest(&opts)?),
+            None => None,
+        };
+        self.seal(&mut opts)?;
+
+        let mut body = serde_json::to_value(&opts)?;
+        if let Some(digest) = digest {
+            body["content_digest"] = serde_json::Value::String(digest);
+        }
+        Ok(body)
+    }
+
+    /// Queries memory entries.
+    pub async fn query(&self, opts: Option<MemoryQueryOptions>) -> Result<Vec<MemoryEntry>, Error> {
+        Ok(self.query_page(opts).await?.entries)
+    }
+
+    /// Queries memory entries, returning the cursor for the next page.
+    ///
+    /// Pass `next_cursor` back as [`MemoryQueryOptions::cursor`] to continue a
+    /// scan. Unlike `offset`, cursors stay stable while new entries arrive.
+    pub async fn query_page(&self, opts: Option<MemoryQueryOptions>) -> Result<MemoryPage, Error> {
+        let params = query_params(opts);
+        let mut page: MemoryPage = self.client.get("/memory", Some(&params)).await?;
+        self.open(&mut page.entries)?;
+        Ok(page)
+    }
+
+    /// Returns a stream of every entry matching `opts`, following cursors
+    /// from page to page. Pages hold `opts.limit` entries (default 100).
+    pub fn list_all(
+        &self,
+        opts: Option<MemoryQueryOptions>,
+    ) -> impl Stream<Item = Result<MemoryEntry, Error>> {
+        let mut opts = opts.unwrap_or_default();
+        opts.limit = Some(opts.limit.unwrap_or(DEFAULT_PAGE_SIZE));
+        stream::try_unfold((Some(opts), self.clone()), |(opts, memory)| async move {
+            let opts = match opts {
+                Some(opts) => opts,
+                None => return Ok::<_, Error>(None),
+            };
+            let page = memory.query_page(Some(opts.clone())).await?;
+            let next = page.next_cursor.map(|cursor| MemoryQueryOptions {
+                cursor: Some(cursor),
+                offset: None,
+                ..opts
+            });
+            Ok(Some((page.entries, (next, memory))))
+        })
+        .map_ok(|entries| stream::iter(entries.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// Writes every entry matching `opts` to `writer`, following cursors like
+    /// [`list_all`](Self::list_all). Returns the number of entries written.
+    ///
+    /// Output is buffered and flushed at the end; the writer is not shut
+    /// down. On error, entries written so far remain in `writer`.
+    pub async fn export<W>(
+        &self,
+        opts: Option<MemoryQueryOptions>,
+        format: ExportFormat,
+        writer: W,
+    ) -> Result<u64, Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(export_header(format).as_bytes()).await?;
+        let entries = self.list_all(opts);
+        futures::pin_mut!(entries);
+        let mut written = 0;
+        while let Some(entry) = entries.try_next().await? {
+            writer.write_all(&export_record(&entry, format)?).await?;
+            written += 1;
+        }
+        writer.flush().await?;
+        Ok(written)
+    }
+
+    /// Stores `data` as attachment `name` on an entry, replacing any
+    /// attachment of that name. Use this for artifacts such as logs or
+    /// screenshots that are too large for `details`.
+    ///
+    /// Attachments are not part of the hash chain, and are stored as given
+    /// even by a `MemoryAPI` with encryption enabled.
+    pub async fn attach(
+        &self,
+        entry_hash: &str,
+        name: &str,
+        data: impl Into<Bytes>,
+        content_type: &str,
+    ) -> Result<Attachment, Error> {
+        self.client
+            .put_raw(
+                &attachment_path(entry_hash, name)?,
+                Payload::Bytes(data.into()),
+                content_type,
+            )
+            .await
+    }
+
+    /// Like [`attach`](Self::attach), but streams the content, e.g. from a
+    /// file, instead of holding it in memory. A streamed upload is not
+    /// retried.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn attach_stream<S>(
+        &self,
+        entry_hash: &str,
+        name: &str,
+        data: S,
+        content_type: &str,
+    ) -> Result<Attachment, Error>
+    where
+        S: futures::TryStream + Send + Sync + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        Bytes: From<S::Ok>,
+    {
+        self.client
+            .put_raw(
+                &attachment_path(entry_hash, name)?,
+                Payload::Stream(Some(reqwest::Body::wrap_stream(data))),
+                content_type,
+            )
+            .await
+    }
+
+    /// Downloads attachment `name` of an entry.
+    pub async fn download_attachment(&self, entry_hash: &str, name: &str) -> Result<Bytes, Error> {
+        let chunks: Vec<Bytes> = self
+            .download_attachment_stream(entry_hash, name)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(chunks.concat().into())
+    }
+
+    /// Like [`download_attachment`](Self::download_attachment), but yields
+    /// the content in chunks as it arrives.
+    pub async fn download_attachment_stream(
+        &self,
+        entry_hash: &str,
+        name: &str,
+    ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
+        self.client
+            .get_stream(&attachment_path(entry_hash, name)?, "*/*")
+            .await
+    }
+
+    /// Searches memory by meaning rather than keywords, using
+    /// `opts.semantic` as the query. Results come most relevant first, with
+    /// their scores; `opts.min_score` drops weaker matches.
+    ///
+    /// The other filters in `opts` (action, tags, time range, and so on)
+    /// narrow the candidates before ranking.
+    pub async fn search_semantic(
+        &self,
+        opts: MemoryQueryOptions,
+    ) -> Result<Vec<ScoredMemoryEntry>, Error> {
+        if opts
+            .semantic
+            .as_deref()
+            .filter(|q| !q.trim().is_empty())
+            .is_none()
+        {
+            return Err(Error::Validation(ValidationErrorBody::new(
+                "semantic search requires a non-empty `semantic` query",
+            )));
+        }
+        let params = query_params(Some(opts));
+        let mut response: SemanticSearchResponse =
+            self.client.get("/memory/search", Some(&params)).await?;
+        for result in &mut response.results {
+            self.open(std::slice::from_mut(&mut result.entry))?;
+        }
+        Ok(response.results)
+    }
+
+    /// Queries memory like [`query`](Self::query), also counting matches per
+    /// value of each field in `facets` (e.g. `"action"`, `"agent"`, `"tag"`).
+    pub async fn query_with_facets(
+        &self,
+        opts: Option<MemoryQueryOptions>,
+        facets: &[&str],
+    ) -> Result<Faceted<MemoryEntry>, Error> {
+        let mut params = query_params(opts);
+        params.insert("facets".to_string(), facets.join(","));
+        let mut response: FacetedEntriesResponse =
+            self.client.get("/memory", Some(&params)).await?;
+        self.open(&mut response.entries)?;
+        Ok(Faceted {
+            items: response.entries,
+            facets: response.facets,
+        })
+    }
+
+    /// Builds a timeline of the entries matching `opts.filters`, grouped by
+    /// agent or entity and bucketed by time.
+    pub async fn timeline(&self, opts: TimelineOptions) -> Result<Timeline, Error> {
+        let max_entries = opts.max_entries.unwrap_or(DEFAULT_TIMELINE_MAX_ENTRIES);
+        let mut filters = opts.filters;
+        let mut entries = Vec::new();
+        loop {
+            let page = self.query_page(Some(filters.clone())).await?;
+            entries.extend(page.entries);
+            match page.next_cursor {
+                Some(cursor) if entries.len() < max_entries => filters.cursor = Some(cursor),
+                _ => break,
             }
         }
+        entries.truncate(max_entries);
 
-        let response: EntriesResponse = self.client.get("/memory", Some(&params)).await?;
-        Ok(response.entries)
+        Ok(Timeline::from_entries(
+            entries,
+            opts.group_by,
+            opts.interval.unwrap_or(StatsInterval::Hour),
+        ))
     }
 
     /// Gets a specific memory entry by hash.
     pub async fn get(&self, entry_hash: &str) -> Result<MemoryEntry, Error> {
-        self.client
-            .get(&format!("/memory/{}", entry_hash), None)
-            .await
+        let mut entry: MemoryEntry = self
+            .client
+            .get(&format!("/memory/{}", path_segment(entry_hash)?), None)
+            .await?;
+        self.open(std::slice::from_mut(&mut entry))?;
+        Ok(entry)
     }
 
     /// Gets recent memory entries.
@@ -88,7 +468,10 @@ impl MemoryAPI {
         agent_id: &str,
     ) -> Result<HashMap<String, serde_json::Value>, Error> {
         self.client
-            .get(&format!("/memory/agents/{}/state", agent_id), None)
+            .get(
+                &format!("/memory/agents/{}/state", path_segment(agent_id)?),
+                None,
+            )
             .await
     }
 
@@ -100,11 +483,52 @@ impl MemoryAPI {
     ) -> Result<(), Error> {
         let _: serde_json::Value = self
             .client
-            .post(&format!("/memory/agents/{}/state", agent_id), &state)
+            .post(
+                &format!("/memory/agents/{}/state", path_segment(agent_id)?),
+                &state,
+            )
             .await?;
         Ok(())
     }
 
+    /// Gets the keys of an agent's state that changed after `since_version`.
+    ///
+    /// Pass `0` to fetch the full state along with its current version.
+    pub async fn state_changes(
+        &self,
+        agent_id: &str,
+        since_version: i64,
+    ) -> Result<StateDelta, Error> {
+        let mut params = HashMap::new();
+        params.insert("since".to_string(), since_version.to_string());
+        self.client
+            .get(
+                &format!("/memory/agents/{}/state/changes", path_segment(agent_id)?),
+                Some(&params),
+            )
+            .await
+    }
+
+    /// Uploads only the changed keys of an agent's state and returns the new
+    /// state version.
+    ///
+    /// `delta.version` must be the version the changes were computed against;
+    /// the API rejects the update if the state has moved on since then.
+    pub async fn push_state_changes(
+        &self,
+        agent_id: &str,
+        delta: &StateDelta,
+    ) -> Result<i64, Error> {
+        let response: StateVersionResponse = self
+            .client
+            .patch(
+                &format!("/memory/agents/{}/state", path_segment(agent_id)?),
+                delta,
+            )
+            .await?;
+        Ok(response.version)
+    }
+
     /// Broadcasts a message.
     pub async fn broadcast(&self, msg_type: &str, payload: &str) -> Result<String, Error> {
         let body = serde_json::json!({
@@ -115,6 +539,29 @@ impl MemoryAPI {
         Ok(response.broadcast_id)
     }
 
+    /// Gets which agents have received and acknowledged a broadcast.
+    pub async fn broadcast_status(&self, broadcast_id: &str) -> Result<BroadcastStatus, Error> {
+        self.client
+            .get(
+                &format!("/memory/broadcast/{}", path_segment(broadcast_id)?),
+                None,
+            )
+            .await
+    }
+
+    /// Acknowledges a broadcast on behalf of a receiving agent.
+    pub async fn ack(&self, broadcast_id: &str, agent_id: &str) -> Result<(), Error> {
+        let body = serde_json::json!({ "agent_id": agent_id });
+        let _: serde_json::Value = self
+            .client
+            .post(
+                &format!("/memory/broadcast/{}/ack", path_segment(broadcast_id)?),
+                &body,
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Creates a TIL (Today I Learned) entry.
     pub async fn til(&self, category: &str, learning: &str) -> Result<MemoryEntry, Error> {
         self.log(LogMemoryOptions {
@@ -122,14 +569,38 @@ impl MemoryAPI {
             entity: category.to_string(),
             details: Some(learning.to_string()),
             tags: Some(vec!["til".to_string(), category.to_string()]),
+            agent: None,
             metadata: None,
+            dedupe_window_secs: None,
         })
         .await
     }
 
-    /// Gets memory statistics.
-    pub async fn stats(&self) -> Result<Stats, Error> {
-        self.client.get("/memory/stats", None).await
+    /// Gets memory statistics, optionally bucketed by time and grouped by a dimension.
+    pub async fn stats(&self, opts: Option<MemoryStatsOptions>) -> Result<MemoryStats, Error> {
+        let mut params = HashMap::new();
+
+        if let Some(opts) = opts {
+            if let Some(since) = opts.since {
+                params.insert("since".to_string(), since.to_rfc3339());
+            }
+            if let Some(until) = opts.until {
+                params.insert("until".to_string(), until.to_rfc3339());
+            }
+            if let Some(interval) = opts.interval {
+                params.insert("interval".to_string(), interval.as_str().to_string());
+            }
+            if let Some(group_by) = opts.group_by {
+                params.insert("group_by".to_string(), group_by.as_str().to_string());
+            }
+        }
+
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        self.client.get("/memory/stats", params_opt).await
     }
 
     /// Verifies hash chain integrity.
@@ -145,4 +616,295 @@ impl MemoryAPI {
 
         self.client.get("/memory/verify", params.as_ref()).await
     }
+
+    /// Gets the signed head of the hash chain.
+    pub async fn head(&self) -> Result<ChainHead, Error> {
+        self.client.get("/memory/head", None).await
+    }
+
+    /// Returns a stream that writes the chain head to `sink` every `interval`,
+    /// yielding each pin written. Ticks where the head has not moved are
+    /// skipped. The stream ends after yielding the first error.
+    ///
+    /// Pins let chain integrity be proven with
+    /// [`verify_against_pin`](Self::verify_against_pin) even if the platform's
+    /// own history is later rewritten.
+    pub fn pin_head(
+        &self,
+        sink: Arc<dyn PinSink>,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<ChainHead, Error>> {
+        stream::unfold(
+            (Some((self.clone(), sink)), None::<String>, false),
+            move |(state, mut last_hash, mut wait)| async move {
+                let (memory, sink) = state?;
+                loop {
+                    if wait {
+                        crate::rt::sleep(interval).await;
+                    }
+                    wait = true;
+
+                    let result = match memory.head().await {
+                        Ok(head) if last_hash.as_deref() == Some(head.hash.as_str()) => continue,
+                        Ok(head) => sink.write(&head).await.map(|_| head),
+                        Err(e) => Err(e),
+                    };
+                    return Some(match result {
+                        Ok(head) => {
+                            last_hash = Some(head.hash.clone());
+                            (Ok(head), (Some((memory, sink)), last_hash, wait))
+                        }
+                        Err(e) => (Err(e), (None, last_hash, wait)),
+                    });
+                }
+            },
+        )
+    }
+
+    /// Checks that the chain still holds a pinned head at its pinned
+    /// position, without trusting the API's own verification.
+    ///
+    /// The pin and the current head must both be signed by `key`. Entries are
+    /// then fetched from the current head back to the pinned position,
+    /// one request per entry, and each one's hash and link to its
+    /// predecessor is recomputed locally with [`MemoryEntry::compute_hash`].
+    pub async fn verify_against_pin(
+        &self,
+        pin: &ChainHead,
+        key: &ChainHeadKey,
+    ) -> Result<PinVerification, Error> {
+        if !key.verify(pin) {
+            return Ok(PinVerification::BadSignature);
+        }
+        let current = self.head().await?;
+        if !key.verify(&current) {
+            return Ok(PinVerification::BadSignature);
+        }
+        if current.count < pin.count {
+            return Ok(PinVerification::Shrunk {
+                pinned: pin.count,
+                current: current.count,
+            });
+        }
+
+        let diverged = |checked, issue| {
+            PinVerification::Diverged(VerifyChainResult {
+                valid: false,
+                checked,
+                issues: vec![issue],
+            })
+        };
+        let steps = current.count - pin.count;
+        let mut hash = current.hash;
+        let mut child: Option<String> = None;
+        let mut checked = 0;
+        loop {
+            // Fetched as stored, since hashes cover sealed details.
+            let entry: MemoryEntry = match self
+                .client
+                .get(&format!("/memory/{}", path_segment(&hash)?), None)
+                .await
+            {
+                Ok(entry) => entry,
+                Err(Error::NotFound(_)) if checked == steps => {
+                    return Ok(PinVerification::Missing);
+                }
+                Err(Error::NotFound(_)) => {
+                    let issue = ChainIssue::Gap {
+                        hash: child.unwrap_or_default(),
+                        missing_prev_hash: hash,
+                    };
+                    return Ok(diverged(checked, issue));
+                }
+                Err(e) => return Err(e),
+            };
+            let computed = entry.compute_hash();
+            if computed != hash || entry.hash != hash {
+                return Ok(diverged(
+                    checked,
+                    ChainIssue::HashMismatch { hash, computed },
+                ));
+            }
+            checked += 1;
+            if checked > steps {
+                break;
+            }
+            match entry.prev_hash {
+                Some(prev) => child = Some(std::mem::replace(&mut hash, prev)),
+                None => return Ok(PinVerification::Missing),
+            }
+        }
+        if hash != pin.hash {
+            return Ok(PinVerification::Missing);
+        }
+        Ok(PinVerification::Intact)
+    }
+
+    #[cfg(feature = "encryption")]
+    fn content_digest(&self, opts: &LogMemoryOptions) -> Result<String, Error> {
+        match &self.encryption {
+            Some(encryption) => encryption.content_digest(opts),
+            None => Ok(opts.content_digest()),
+        }
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn content_digest(&self, opts: &LogMemoryOptions) -> Result<String, Error> {
+        Ok(opts.content_digest())
+    }
+
+    #[cfg(feature = "encryption")]
+    fn seal(&self, opts: &mut LogMemoryOptions) -> Result<(), Error> {
+        match &self.encryption {
+            Some(encryption) => encryption.seal(opts),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn seal(&self, _opts: &mut LogMemoryOptions) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    fn open(&self, entries: &mut [MemoryEntry]) -> Result<(), Error> {
+        if let Some(encryption) = &self.encryption {
+            for entry in entries {
+                encryption.open(entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn open(&self, _entries: &mut [MemoryEntry]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+fn attachment_path(entry_hash: &str, name: &str) -> Result<String, Error> {
+    Ok(format!(
+        "/memory/{}/attachments/{}",
+        path_segment(entry_hash)?,
+        path_segment(name)?
+    ))
+}
+
+/// Points the field paths of a validation error at entry `index` of a batch.
+fn in_batch(error: Error, index: usize) -> Error {
+    match error {
+        Error::Validation(mut body) => {
+            for field in &mut body.errors {
+                field.field = format!("entries[{}].{}", index, field.field);
+            }
+            Error::Validation(body)
+        }
+        other => other,
+    }
+}
+
+/// Adds the line number to an error from parsing or validating an import line.
+fn on_line(error: Error, line: usize) -> Error {
+    match error {
+        Error::Validation(mut body) => {
+            body.message = format!("{} on line {}", body.message, line);
+            Error::Validation(body)
+        }
+        Error::Serialization(e) => Error::Validation(ValidationErrorBody::new(format!(
+            "malformed entry on line {}: {}",
+            line, e
+        ))),
+        other => other,
+    }
+}
+
+/// The header written before the first entry, if `format` has one.
+pub(crate) fn export_header(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Jsonl => "",
+        ExportFormat::Csv => CSV_HEADER,
+    }
+}
+
+/// Encodes one entry as a line of `format`, including the trailing newline.
+pub(crate) fn export_record(entry: &MemoryEntry, format: ExportFormat) -> Result<Vec<u8>, Error> {
+    match format {
+        ExportFormat::Jsonl => {
+            let mut line = serde_json::to_vec(entry)?;
+            line.push(b'\n');
+            Ok(line)
+        }
+        ExportFormat::Csv => {
+            let metadata = match &entry.metadata {
+                Some(metadata) => serde_json::to_string(metadata)?,
+                None => String::new(),
+            };
+            let fields = [
+                entry.hash.as_str(),
+                &entry.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                &entry.action,
+                &entry.entity,
+                entry.details.as_deref().unwrap_or(""),
+                entry.agent.as_deref().unwrap_or(""),
+                &entry.tags.as_deref().unwrap_or_default().join(";"),
+                entry.prev_hash.as_deref().unwrap_or(""),
+                &metadata,
+            ];
+            let mut line = fields.map(csv_field).join(",");
+            line.push('\n');
+            Ok(line.into_bytes())
+        }
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn query_params(opts: Option<MemoryQueryOptions>) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    params.insert("limit".to_string(), "100".to_string());
+
+    if let Some(opts) = opts {
+        if let Some(s) = opts.search {
+            params.insert("q".to_string(), s);
+        }
+        if let Some(a) = opts.action {
+            params.insert("action".to_string(), a);
+        }
+        if let Some(e) = opts.entity {
+            params.insert("entity".to_string(), e);
+        }
+        if let Some(tags) = opts.tags {
+            params.insert("tags".to_string(), tags.join(","));
+        }
+        if let Some(since) = opts.since {
+            params.insert("since".to_string(), since.to_rfc3339());
+        }
+        if let Some(until) = opts.until {
+            params.insert("until".to_string(), until.to_rfc3339());
+        }
+        if let Some(l) = opts.limit {
+            params.insert("limit".to_string(), l.to_string());
+        }
+        if let Some(o) = opts.offset {
+            params.insert("offset".to_string(), o.to_string());
+        }
+        if let Some(c) = opts.cursor {
+            params.insert("cursor".to_string(), c);
+        }
+        if let Some(semantic) = opts.semantic {
+            params.insert("semantic".to_string(), semantic);
+        }
+        if let Some(score) = opts.min_score {
+            params.insert("min_score".to_string(), score.to_string());
+        }
+    }
+
+    params
 }
@@ -0,0 +1,38 @@
+use crate::errors::Error;
+use reqwest::{Method, StatusCode};
+use std::fmt;
+use std::time::Duration;
+
+/// Hook that receives request counts, outcomes, and latencies.
+///
+/// Install one with [`ClientConfig::metrics`](crate::ClientConfig::metrics)
+/// to feed a metrics pipeline such as StatsD, OpenTelemetry, or Prometheus.
+/// Each call to the SDK reports `on_request` once, `on_retry` before each
+/// retry, and then either `on_response` or `on_error`. Called on the
+/// request's task, so keep it cheap.
+pub trait MetricsSink: Send + Sync + fmt::Debug {
+    /// Called before the first attempt of a request.
+    fn on_request(&self, _method: &Method, _endpoint: &str) {}
+
+    /// Called with the final response, whatever its status.
+    fn on_response(&self, _request: &RequestMetrics<'_>, _status: StatusCode) {}
+
+    /// Called after a failed attempt, before sleeping for `delay`.
+    fn on_retry(&self, _request: &RequestMetrics<'_>, _delay: Duration, _cause: &Error) {}
+
+    /// Called when a request fails without a response, e.g. on a connection
+    /// failure or an open circuit.
+    fn on_error(&self, _request: &RequestMetrics<'_>, _error: &Error) {}
+}
+
+/// A request as seen by a [`MetricsSink`].
+#[derive(Debug)]
+pub struct RequestMetrics<'a> {
+    pub method: &'a Method,
+    /// The request path relative to the base URL, without the query string.
+    pub endpoint: &'a str,
+    /// Attempts made so far, starting at 1.
+    pub attempts: u32,
+    /// Time since the request started, including retries and waits.
+    pub elapsed: Duration,
+}
@@ -0,0 +1,38 @@
+use crate::errors::Error;
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, Request, StatusCode, Url};
+use std::fmt;
+use std::time::Duration;
+
+/// Hook that can change outgoing requests and observe the responses.
+///
+/// Install interceptors with
+/// [`ClientConfig::interceptors`](crate::ClientConfig::interceptors); they run
+/// in order for every attempt, including retries and hedged requests, after
+/// the SDK has set its own headers. That makes them suitable for tenant
+/// headers, timing, or replacing the `Authorization` header with a custom
+/// scheme.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Interceptor: Send + Sync + fmt::Debug {
+    /// Called before a request is sent. Returning an error fails the request
+    /// without sending it.
+    async fn on_request(&self, _request: &mut Request) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when a response arrives, before its body is read.
+    fn on_response(&self, _response: &ResponseInfo<'_>) {}
+}
+
+/// A response as seen by [`Interceptor::on_response`].
+#[derive(Debug)]
+pub struct ResponseInfo<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub status: StatusCode,
+    pub headers: &'a HeaderMap,
+    /// Time from sending the request to receiving the response headers.
+    pub elapsed: Duration,
+}
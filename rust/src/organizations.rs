@@ -0,0 +1,65 @@
+use crate::client::{path_segment, BlackRoadClient};
+use crate::errors::Error;
+use crate::types::{Organization, Project};
+use serde::Deserialize;
+
+/// API for organizations and their projects.
+#[derive(Debug, Clone)]
+pub struct OrganizationAPI {
+    client: BlackRoadClient,
+}
+
+#[derive(Deserialize)]
+struct OrganizationsResponse {
+    organizations: Vec<Organization>,
+}
+
+#[derive(Deserialize)]
+struct ProjectsResponse {
+    projects: Vec<Project>,
+}
+
+impl OrganizationAPI {
+    pub(crate) fn new(client: BlackRoadClient) -> Self {
+        Self { client }
+    }
+
+    /// Lists the organizations the credentials can access.
+    pub async fn list(&self) -> Result<Vec<Organization>, Error> {
+        let response: OrganizationsResponse = self.client.get("/organizations", None).await?;
+        Ok(response.organizations)
+    }
+
+    /// Gets an organization by ID.
+    pub async fn get(&self, org_id: &str) -> Result<Organization, Error> {
+        self.client
+            .get(&format!("/organizations/{}", path_segment(org_id)?), None)
+            .await
+    }
+
+    /// Lists the projects of an organization.
+    pub async fn list_projects(&self, org_id: &str) -> Result<Vec<Project>, Error> {
+        let response: ProjectsResponse = self
+            .client
+            .get(
+                &format!("/organizations/{}/projects", path_segment(org_id)?),
+                None,
+            )
+            .await?;
+        Ok(response.projects)
+    }
+
+    /// Gets a project of an organization by ID.
+    pub async fn get_project(&self, org_id: &str, project_id: &str) -> Result<Project, Error> {
+        self.client
+            .get(
+                &format!(
+                    "/organizations/{}/projects/{}",
+                    path_segment(org_id)?,
+                    path_segment(project_id)?
+                ),
+                None,
+            )
+            .await
+    }
+}
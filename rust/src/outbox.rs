@@ -0,0 +1,268 @@
+use crate::client::BlackRoadClient;
+use crate::errors::Error;
+use crate::types::{DispatchTaskOptions, LogMemoryOptions, MemoryEntry};
+use chrono::Utc;
+use reqwest::header::HeaderName;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// A write operation that can be queued in an [`Outbox`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum OutboxOp {
+    /// Logs a memory entry.
+    LogMemory(LogMemoryOptions),
+    /// Dispatches a task.
+    DispatchTask(DispatchTaskOptions),
+    /// Replaces an agent's synced state.
+    SyncState {
+        agent_id: String,
+        state: HashMap<String, serde_json::Value>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OutboxRecord {
+    key: String,
+    op: OutboxOp,
+}
+
+/// Result of flushing an [`Outbox`].
+#[derive(Debug, Default)]
+pub struct FlushReport {
+    /// Keys of the operations delivered during this flush, in order.
+    pub delivered: Vec<String>,
+    /// Operations the API rejected permanently. They are removed from the outbox.
+    pub rejected: Vec<(String, Error)>,
+    /// Number of operations still queued, e.g. because the API became unreachable.
+    pub remaining: usize,
+}
+
+/// A disk-backed queue of write operations that survives process restarts.
+///
+/// Operations are delivered in the order they were enqueued. Each carries a
+/// dedupe key, sent as the `Idempotency-Key` header, so an operation that was
+/// in flight when the process died is not applied twice. Enqueuing a key that
+/// is already pending is a no-op.
+#[derive(Debug)]
+pub struct Outbox {
+    client: BlackRoadClient,
+    path: PathBuf,
+    records: Mutex<Vec<OutboxRecord>>,
+}
+
+impl Outbox {
+    /// Opens the outbox stored at `path`, creating it on first write.
+    pub async fn open(client: BlackRoadClient, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let records = match crate::rt::fs::read_to_string(&path).await {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<OutboxRecord>, _>>()?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            client,
+            path,
+            records: Mutex::new(records),
+        })
+    }
+
+    /// Queues an operation. Returns `false` if `key` is already pending.
+    ///
+    /// Operations that fail local validation are rejected instead of queued.
+    pub async fn enqueue(&self, key: impl Into<String>, op: OutboxOp) -> Result<bool, Error> {
+        match &op {
+            OutboxOp::LogMemory(opts) => opts.validate()?,
+            OutboxOp::DispatchTask(opts) => opts.validate()?,
+            OutboxOp::SyncState { .. } => {}
+        }
+        let key = key.into();
+        let mut records = self.records.lock().await;
+        if records.iter().any(|r| r.key == key) {
+            return Ok(false);
+        }
+        records.push(OutboxRecord { key, op });
+        self.persist(&records).await?;
+        Ok(true)
+    }
+
+    /// Returns the number of queued operations.
+    pub async fn len(&self) -> usize {
+        self.records.lock().await.len()
+    }
+
+    /// Returns true if no operations are queued.
+    pub async fn is_empty(&self) -> bool {
+        self.records.lock().await.is_empty()
+    }
+
+    /// Delivers queued operations in order.
+    ///
+    /// Stops at the first transient failure (see [`Error::is_transient`]),
+    /// leaving that operation and everything after it queued for the next
+    /// flush. Permanently rejected operations are dropped and reported.
+    pub async fn flush(&self) -> Result<FlushReport, Error> {
+        let mut records = self.records.lock().await;
+        let mut report = FlushReport::default();
+
+        while let Some(record) = records.first().cloned() {
+            match self.deliver(&record).await {
+                Ok(()) => report.delivered.push(record.key),
+                Err(e) if e.is_transient() => break,
+                Err(e) => report.rejected.push((record.key, e)),
+            }
+            records.remove(0);
+            self.persist(&records).await?;
+        }
+
+        report.remaining = records.len();
+        Ok(report)
+    }
+
+    async fn deliver(&self, record: &OutboxRecord) -> Result<(), Error> {
+        let client = self.client.with_header(IDEMPOTENCY_KEY, &record.key)?;
+
+        match record.op.clone() {
+            OutboxOp::LogMemory(opts) => client.memory().log(opts).await.map(drop),
+            OutboxOp::DispatchTask(opts) => client.tasks().dispatch(opts).await.map(drop),
+            OutboxOp::SyncState { agent_id, state } => {
+                client.memory().sync_state(&agent_id, state).await
+            }
+        }
+    }
+
+    /// Rewrites the outbox file atomically via a temporary file.
+    async fn persist(&self, records: &[OutboxRecord]) -> Result<(), Error> {
+        let mut contents = String::new();
+        for record in records {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                crate::rt::fs::create_dir_all(parent).await?;
+            }
+        }
+        let tmp = self.path.with_extension("tmp");
+        crate::rt::fs::write(&tmp, contents).await?;
+        crate::rt::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+/// Outcome of [`OfflineMemoryLogger::log`].
+#[derive(Debug, Clone)]
+pub enum LogOutcome {
+    /// The entry was logged right away.
+    Logged(Box<MemoryEntry>),
+    /// The API was unreachable, so the entry was queued on disk.
+    Queued,
+}
+
+/// Logs memory entries, queuing them in an [`Outbox`] while the API is
+/// unreachable.
+///
+/// Entries reach the API in the order they were logged: while anything is
+/// queued, `log` first tries to flush the queue, and queues the new entry
+/// behind it if that fails. Each entry keeps the idempotency key of its first
+/// attempt, so an entry whose request reached the API before the connection
+/// dropped is not logged twice.
+#[derive(Debug)]
+pub struct OfflineMemoryLogger {
+    client: BlackRoadClient,
+    outbox: Outbox,
+    /// Serializes `log` and `flush` so entries cannot overtake each other.
+    order: Mutex<()>,
+    sequence: AtomicU64,
+    /// Entries rejected while `log` flushed the queue, held until reported.
+    rejected: Mutex<Vec<(String, Error)>>,
+}
+
+impl OfflineMemoryLogger {
+    /// Opens the logger with its queue stored at `path`.
+    pub async fn open(client: BlackRoadClient, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        Ok(Self {
+            outbox: Outbox::open(client.clone(), path).await?,
+            client,
+            order: Mutex::new(()),
+            sequence: AtomicU64::new(0),
+            rejected: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Logs an entry, or queues it if the API cannot be reached.
+    ///
+    /// Entries that fail validation, or that the API rejects outright, are
+    /// returned as errors rather than queued. Queued entries the API rejects
+    /// while this flushes the queue are kept for [`flush`](Self::flush) or
+    /// [`take_rejected`](Self::take_rejected) to report.
+    pub async fn log(&self, opts: LogMemoryOptions) -> Result<LogOutcome, Error> {
+        opts.validate()?;
+        let _order = self.order.lock().await;
+        let key = self.next_key();
+
+        if self.outbox.is_empty().await || self.flush_queue().await?.remaining == 0 {
+            let client = self.client.with_header(IDEMPOTENCY_KEY, &key)?;
+            match client.memory().log(opts.clone()).await {
+                Ok(entry) => return Ok(LogOutcome::Logged(Box::new(entry))),
+                Err(e) if e.is_transient() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.outbox.enqueue(key, OutboxOp::LogMemory(opts)).await?;
+        Ok(LogOutcome::Queued)
+    }
+
+    /// Delivers queued entries in order. See [`Outbox::flush`].
+    ///
+    /// The report's `rejected` also lists entries rejected by earlier flushes
+    /// that `log` ran, oldest first.
+    pub async fn flush(&self) -> Result<FlushReport, Error> {
+        let _order = self.order.lock().await;
+        let mut report = self.outbox.flush().await?;
+        let mut rejected = self.take_rejected().await;
+        rejected.append(&mut report.rejected);
+        report.rejected = rejected;
+        Ok(report)
+    }
+
+    /// Returns and clears the queued entries the API rejected while `log`
+    /// flushed the queue, as `(key, error)` pairs.
+    pub async fn take_rejected(&self) -> Vec<(String, Error)> {
+        std::mem::take(&mut *self.rejected.lock().await)
+    }
+
+    /// Flushes the outbox, keeping its rejections until they are reported.
+    async fn flush_queue(&self) -> Result<FlushReport, Error> {
+        let mut report = self.outbox.flush().await?;
+        self.rejected
+            .lock()
+            .await
+            .extend(std::mem::take(&mut report.rejected));
+        Ok(report)
+    }
+
+    /// Returns the number of queued entries.
+    pub async fn pending(&self) -> usize {
+        self.outbox.len().await
+    }
+
+    fn next_key(&self) -> String {
+        format!(
+            "memory-{}-{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            self.sequence.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+}
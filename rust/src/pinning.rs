@@ -0,0 +1,142 @@
+use crate::errors::{Error, ValidationErrorBody};
+use crate::types::ChainHead;
+use async_trait::async_trait;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+/// The API's Ed25519 public key for chain head signatures.
+///
+/// Pass it to [`MemoryAPI::verify_against_pin`](crate::MemoryAPI::verify_against_pin)
+/// so pins are checked against a key held by the caller rather than one the
+/// API reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainHeadKey(VerifyingKey);
+
+impl ChainHeadKey {
+    /// Parses a 32-byte Ed25519 public key.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self, Error> {
+        VerifyingKey::from_bytes(bytes).map(Self).map_err(|_| {
+            Error::Validation(ValidationErrorBody::new(
+                "chain head key is not a valid Ed25519 public key",
+            ))
+        })
+    }
+
+    /// Returns true if `head` carries a valid signature by this key.
+    pub fn verify(&self, head: &ChainHead) -> bool {
+        let Some(signature) = head.signature.as_deref() else {
+            return false;
+        };
+        let Ok(bytes) = hex::decode(signature) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&bytes) else {
+            return false;
+        };
+        self.0
+            .verify(head.signed_message().as_bytes(), &signature)
+            .is_ok()
+    }
+}
+
+/// External storage for memory chain pins written by
+/// [`MemoryAPI::pin_head`](crate::MemoryAPI::pin_head).
+///
+/// Keep pins somewhere the platform cannot rewrite, such as a write-once
+/// bucket or another provider's object store.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait PinSink: Send + Sync + fmt::Debug {
+    /// Stores a pin.
+    async fn write(&self, head: &ChainHead) -> Result<(), Error>;
+}
+
+/// Appends each pin as a JSON line to a local file.
+///
+/// Not available on `wasm32`.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FilePinSink {
+    path: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FilePinSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl PinSink for FilePinSink {
+    async fn write(&self, head: &ChainHead) -> Result<(), Error> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut line = serde_json::to_vec(head)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+}
+
+/// Uploads each pin with an HTTP `PUT` to a pre-signed URL, such as an S3 or
+/// GCS signed URL, without the SDK holding storage credentials.
+///
+/// Each write replaces the object, so point it at a versioned or
+/// object-locked bucket to keep the pin history.
+#[derive(Clone)]
+pub struct PresignedUrlSink {
+    url: String,
+    http_client: reqwest::Client,
+}
+
+impl PresignedUrlSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl fmt::Debug for PresignedUrlSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The query string carries the signature.
+        let url = self.url.split('?').next().unwrap_or_default();
+        f.debug_struct("PresignedUrlSink")
+            .field("url", &url)
+            .finish()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl PinSink for PresignedUrlSink {
+    async fn write(&self, head: &ChainHead) -> Result<(), Error> {
+        let response = self
+            .http_client
+            .put(&self.url)
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(head)?)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::Api {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+}
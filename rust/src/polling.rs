@@ -0,0 +1,226 @@
+use crate::errors::Error;
+use crate::types::Page;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+/// Page size used by `list_all` when the options do not set a limit.
+pub(crate) const DEFAULT_PAGE_SIZE: i32 = 100;
+/// Longest server-side wait per long-poll request.
+const MAX_LONG_POLL_WAIT: Duration = Duration::from_secs(25);
+/// Headroom between a long-poll wait and the client's request timeout.
+const LONG_POLL_TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
+
+/// How streams such as [`TaskAPI::watch`](crate::TaskAPI::watch) and
+/// [`EventAPI::subscribe`](crate::EventAPI::subscribe) receive updates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamTransport {
+    /// Use server-sent events or a WebSocket, switching to long polling if
+    /// the connection cannot be opened, e.g. behind a proxy that blocks it.
+    #[default]
+    Auto,
+    /// Only use server-sent events or a WebSocket.
+    Push,
+    /// Only use long polling, which works through any HTTPS proxy.
+    LongPoll,
+}
+
+impl StreamTransport {
+    /// Returns true if `error`, from opening a push connection, is worth
+    /// retrying over long polling. Credential, rate-limit, and lookup
+    /// failures would fail there too.
+    fn falls_back_on(&self, error: &Error) -> bool {
+        *self == StreamTransport::Auto
+            && matches!(
+                error,
+                Error::Connection(_) | Error::Request(_) | Error::Api { .. }
+            )
+    }
+}
+
+/// Returns how long the API may hold a long-poll request open, leaving the
+/// request within `timeout`.
+pub(crate) fn long_poll_wait(timeout: Duration) -> Duration {
+    MAX_LONG_POLL_WAIT.min(
+        timeout
+            .saturating_sub(LONG_POLL_TIMEOUT_MARGIN)
+            .max(Duration::from_secs(1)),
+    )
+}
+
+/// Streams from `push`, or from the stream `poll` builds as `transport`
+/// directs. With [`StreamTransport::Auto`], a first item from `push` that is
+/// a connection failure is dropped and long polling takes over.
+pub(crate) fn push_or_poll<T, P, L, F>(
+    transport: StreamTransport,
+    push: P,
+    poll: F,
+) -> impl Stream<Item = Result<T, Error>>
+where
+    P: Stream<Item = Result<T, Error>> + Unpin,
+    L: Stream<Item = Result<T, Error>> + Unpin,
+    F: FnOnce() -> L,
+{
+    enum State<P, L, F> {
+        Push(P, Option<F>),
+        Poll(L),
+    }
+
+    let state = match transport {
+        StreamTransport::LongPoll => State::Poll(poll()),
+        _ => State::Push(push, Some(poll)),
+    };
+    stream::unfold(state, move |state| async move {
+        match state {
+            State::Push(mut push, poll) => match (push.next().await, poll) {
+                (Some(Err(e)), Some(build)) if transport.falls_back_on(&e) => {
+                    let mut poll = build();
+                    let item = poll.next().await?;
+                    Some((item, State::Poll(poll)))
+                }
+                (Some(item), _) => Some((item, State::Push(push, None))),
+                (None, _) => None,
+            },
+            State::Poll(mut poll) => {
+                let item = poll.next().await?;
+                Some((item, State::Poll(poll)))
+            }
+        }
+    })
+}
+
+/// One round of a cursor-based poll.
+pub(crate) struct Batch<T, C> {
+    pub items: Vec<T>,
+    pub cursor: C,
+    pub done: bool,
+}
+
+/// Builds a stream by repeatedly calling `fetch` with the latest cursor.
+///
+/// Empty batches are retried after `interval`; non-empty batches are polled
+/// again immediately. The stream ends after a batch marked `done`, or after
+/// yielding the first error.
+pub(crate) fn poll_stream<T, C, F, Fut>(
+    cursor: C,
+    interval: Duration,
+    fetch: F,
+) -> impl Stream<Item = Result<T, Error>>
+where
+    F: FnMut(C) -> Fut,
+    Fut: Future<Output = Result<Batch<T, C>, Error>>,
+{
+    stream::try_unfold(
+        (Some(cursor), fetch),
+        move |(cursor, mut fetch)| async move {
+            let mut cursor = match cursor {
+                Some(cursor) => cursor,
+                None => return Ok::<_, Error>(None),
+            };
+            loop {
+                let batch = fetch(cursor).await?;
+                if batch.done {
+                    return Ok(Some((batch.items, (None, fetch))));
+                }
+                if !batch.items.is_empty() {
+                    return Ok(Some((batch.items, (Some(batch.cursor), fetch))));
+                }
+                cursor = batch.cursor;
+                crate::rt::sleep(interval).await;
+            }
+        },
+    )
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+/// Where a page fetched by [`page_stream`] starts.
+pub(crate) enum PageStart {
+    Offset(i32),
+    Cursor(String),
+}
+
+impl PageStart {
+    pub(crate) fn offset(&self) -> Option<i32> {
+        match self {
+            PageStart::Offset(offset) => Some(*offset),
+            PageStart::Cursor(_) => None,
+        }
+    }
+
+    pub(crate) fn cursor(self) -> Option<String> {
+        match self {
+            PageStart::Offset(_) => None,
+            PageStart::Cursor(cursor) => Some(cursor),
+        }
+    }
+}
+
+/// The page after the one a [`page_stream`] is yielding from.
+enum NextPage<T, Fut> {
+    /// Being fetched; holds the offset it starts at, if any.
+    Fetching(Option<i32>, Pin<Box<Fut>>),
+    Fetched(Option<i32>, Result<Page<T>, Error>),
+    Done,
+}
+
+/// Builds a stream that walks pagination from `start`, calling `fetch` with
+/// the start of each page.
+///
+/// Pages follow the server's `next_cursor` when it returns one, falling back
+/// to `limit`/`offset` otherwise. The next page is fetched while the current
+/// one's items are yielded, so at most one request is in flight and items
+/// keep their order. Rows can shift between offset pages when data changes,
+/// so items whose `key` has already been yielded are skipped. The stream ends
+/// after a cursor page without a `next_cursor`, an offset page shorter than
+/// `page_size`, or after yielding the first error.
+pub(crate) fn page_stream<T, K, F, Fut>(
+    start: PageStart,
+    page_size: i32,
+    key: fn(&T) -> K,
+    mut fetch: F,
+) -> impl Stream<Item = Result<T, Error>>
+where
+    K: Eq + Hash,
+    F: FnMut(PageStart) -> Fut,
+    Fut: Future<Output = Result<Page<T>, Error>>,
+{
+    let mut next = NextPage::Fetching(start.offset(), Box::pin(fetch(start)));
+    let mut items = VecDeque::new();
+    let mut seen = HashSet::new();
+    stream::poll_fn(move |cx| loop {
+        if let NextPage::Fetching(offset, page) = &mut next {
+            if let Poll::Ready(result) = page.as_mut().poll(cx) {
+                next = NextPage::Fetched(*offset, result);
+            }
+        }
+        if let Some(item) = items.pop_front() {
+            return Poll::Ready(Some(Ok(item)));
+        }
+        match std::mem::replace(&mut next, NextPage::Done) {
+            fetching @ NextPage::Fetching(..) => {
+                next = fetching;
+                return Poll::Pending;
+            }
+            NextPage::Done => return Poll::Ready(None),
+            NextPage::Fetched(_, Err(e)) => return Poll::Ready(Some(Err(e))),
+            NextPage::Fetched(offset, Ok(page)) => {
+                let start = match (page.next_cursor, offset) {
+                    (Some(cursor), _) => Some(PageStart::Cursor(cursor)),
+                    (None, Some(offset)) if page.items.len() >= page_size as usize => {
+                        Some(PageStart::Offset(offset + page.items.len() as i32))
+                    }
+                    (None, _) => None,
+                };
+                if let Some(start) = start {
+                    next = NextPage::Fetching(start.offset(), Box::pin(fetch(start)));
+                }
+                items.extend(page.items.into_iter().filter(|item| seen.insert(key(item))));
+            }
+        }
+    })
+}
@@ -0,0 +1,354 @@
+use crate::errors::Error;
+use crate::types::RateLimitStatus;
+use chrono::Utc;
+use reqwest::Method;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, watch};
+
+/// Relative importance of an outbound request when the client is queueing.
+///
+/// Requests default to [`Critical`](Self::Critical) for heartbeats,
+/// [`High`](Self::High) for other writes, and [`Normal`](Self::Normal) for
+/// reads. Use [`BlackRoadClient::with_priority`](crate::BlackRoadClient::with_priority)
+/// to mark bulk work as [`Background`](Self::Background).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    Background,
+    Normal,
+    High,
+    Critical,
+}
+
+impl RequestPriority {
+    /// The default priority for a request to `url`.
+    pub(crate) fn classify(method: &Method, url: &str) -> Self {
+        let path = url.split('?').next().unwrap_or(url);
+        if path.ends_with("/heartbeat") {
+            RequestPriority::Critical
+        } else if method != Method::GET {
+            RequestPriority::High
+        } else {
+            RequestPriority::Normal
+        }
+    }
+}
+
+/// What to do with a request that arrives when the queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShedPolicy {
+    /// Fail the new request.
+    #[default]
+    RejectNew,
+    /// Fail the oldest queued request of the lowest priority, if it ranks
+    /// below the new one; otherwise fail the new request.
+    DropLowest,
+}
+
+/// Limits for the outbound request queue.
+#[derive(Debug, Clone)]
+pub struct RequestQueueConfig {
+    /// Requests allowed in flight at once; must be at least 1. Defaults to 8.
+    pub max_concurrent: usize,
+    /// Requests allowed to wait for a slot. Defaults to 256.
+    pub max_queued: usize,
+    /// Policy applied when `max_queued` is reached.
+    pub shed_policy: ShedPolicy,
+}
+
+impl Default for RequestQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 8,
+            max_queued: 256,
+            shed_policy: ShedPolicy::default(),
+        }
+    }
+}
+
+/// Admits requests up to a concurrency cap, holding the rest in priority
+/// order. While the API reports an exhausted rate limit, nothing is admitted
+/// until the reset time.
+#[derive(Debug)]
+pub(crate) struct RequestQueue {
+    config: RequestQueueConfig,
+    rate_limit: watch::Receiver<Option<RateLimitStatus>>,
+    state: Mutex<QueueState>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    active: usize,
+    next_seq: u64,
+    waiting: Vec<Waiter>,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    priority: RequestPriority,
+    seq: u64,
+    tx: oneshot::Sender<Result<Permit, Error>>,
+}
+
+/// A slot in the queue, released when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    queue: Arc<RequestQueue>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().active -= 1;
+        self.queue.pump();
+    }
+}
+
+impl QueueState {
+    /// Index of the next waiter to admit: highest priority, then oldest.
+    fn highest(&self) -> Option<usize> {
+        (0..self.waiting.len()).min_by_key(|&i| {
+            let w = &self.waiting[i];
+            (std::cmp::Reverse(w.priority), w.seq)
+        })
+    }
+
+    /// Index of the waiter to shed: lowest priority, then oldest.
+    fn lowest(&self) -> Option<usize> {
+        (0..self.waiting.len()).min_by_key(|&i| {
+            let w = &self.waiting[i];
+            (w.priority, w.seq)
+        })
+    }
+}
+
+impl RequestQueue {
+    pub(crate) fn new(
+        config: RequestQueueConfig,
+        rate_limit: watch::Receiver<Option<RateLimitStatus>>,
+    ) -> Self {
+        Self {
+            config,
+            rate_limit,
+            state: Mutex::new(QueueState::default()),
+        }
+    }
+
+    /// Waits for a slot for a request of the given priority.
+    ///
+    /// A request is admitted at once when a slot is free and nothing is
+    /// waiting, so `max_queued` only limits requests that would have to wait.
+    pub(crate) async fn acquire(
+        self: &Arc<Self>,
+        priority: RequestPriority,
+    ) -> Result<Permit, Error> {
+        let (tx, mut rx) = oneshot::channel();
+        let rate_limited = self.rate_limit_reset().is_some();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.waiting.retain(|w| !w.tx.is_closed());
+            if !rate_limited
+                && state.waiting.is_empty()
+                && state.active < self.config.max_concurrent
+            {
+                state.active += 1;
+                return Ok(Permit {
+                    queue: Arc::clone(self),
+                });
+            }
+            if state.waiting.len() >= self.config.max_queued {
+                let shed = match self.config.shed_policy {
+                    ShedPolicy::DropLowest => state
+                        .lowest()
+                        .filter(|&i| state.waiting[i].priority < priority),
+                    ShedPolicy::RejectNew => None,
+                };
+                match shed {
+                    Some(i) => {
+                        let _ = state.waiting.swap_remove(i).tx.send(Err(queue_full()));
+                    }
+                    None => return Err(queue_full()),
+                }
+            }
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiting.push(Waiter { priority, seq, tx });
+        }
+        self.pump();
+
+        loop {
+            match self.rate_limit_reset() {
+                Some(wait) => {
+                    tokio::select! {
+                        result = &mut rx => return received(result),
+                        _ = crate::rt::sleep(wait) => self.pump(),
+                    }
+                }
+                None => return received(rx.await),
+            }
+        }
+    }
+
+    /// Admits waiters while slots are free and the rate limit allows.
+    fn pump(self: &Arc<Self>) {
+        loop {
+            if self.rate_limit_reset().is_some() {
+                return;
+            }
+            let waiter = {
+                let mut state = self.state.lock().unwrap();
+                if state.active >= self.config.max_concurrent {
+                    return;
+                }
+                let Some(i) = state.highest() else {
+                    return;
+                };
+                state.active += 1;
+                state.waiting.swap_remove(i)
+            };
+            // If the caller has gone away the permit comes back and is
+            // dropped here, freeing the slot again.
+            let _ = waiter.tx.send(Ok(Permit {
+                queue: Arc::clone(self),
+            }));
+        }
+    }
+
+    /// Time until the rate limit resets, if it is currently exhausted.
+    fn rate_limit_reset(&self) -> Option<std::time::Duration> {
+        let status = self.rate_limit.borrow();
+        let status = status.as_ref().filter(|s| s.remaining == 0)?;
+        (status.reset_at? - Utc::now()).to_std().ok()
+    }
+}
+
+fn received(
+    result: Result<Result<Permit, Error>, oneshot::error::RecvError>,
+) -> Result<Permit, Error> {
+    result.unwrap_or_else(|_| Err(queue_full()))
+}
+
+fn queue_full() -> Error {
+    Error::Overloaded("request queue is full".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    fn queue(
+        max_concurrent: usize,
+        max_queued: usize,
+        shed_policy: ShedPolicy,
+    ) -> Arc<RequestQueue> {
+        let config = RequestQueueConfig {
+            max_concurrent,
+            max_queued,
+            shed_policy,
+        };
+        Arc::new(RequestQueue::new(config, watch::channel(None).1))
+    }
+
+    /// Queues a request of `priority` that reports its outcome on `tx` and
+    /// releases its slot right away.
+    async fn enqueue(
+        queue: &Arc<RequestQueue>,
+        priority: RequestPriority,
+        tx: &mpsc::UnboundedSender<(RequestPriority, bool)>,
+    ) {
+        let (queue, tx) = (Arc::clone(queue), tx.clone());
+        tokio::spawn(async move {
+            let admitted = queue.acquire(priority).await.is_ok();
+            tx.send((priority, admitted)).unwrap();
+        });
+        tokio::task::yield_now().await;
+    }
+
+    #[tokio::test]
+    async fn caps_requests_in_flight() {
+        let queue = queue(2, 8, ShedPolicy::RejectNew);
+        let first = queue.acquire(RequestPriority::Normal).await.unwrap();
+        let _second = queue.acquire(RequestPriority::Normal).await.unwrap();
+        let third = tokio::time::timeout(
+            Duration::from_millis(20),
+            queue.acquire(RequestPriority::Normal),
+        );
+        assert!(third.await.is_err());
+
+        drop(first);
+        let fourth = tokio::time::timeout(
+            Duration::from_millis(20),
+            queue.acquire(RequestPriority::Normal),
+        );
+        assert!(fourth.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn admits_by_priority_then_age() {
+        let queue = queue(1, 8, ShedPolicy::RejectNew);
+        let held = queue.acquire(RequestPriority::Normal).await.unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        for priority in [
+            RequestPriority::Background,
+            RequestPriority::Normal,
+            RequestPriority::Critical,
+            RequestPriority::Background,
+        ] {
+            enqueue(&queue, priority, &tx).await;
+        }
+
+        drop(held);
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            order.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(
+            order,
+            [
+                (RequestPriority::Critical, true),
+                (RequestPriority::Normal, true),
+                (RequestPriority::Background, true),
+                (RequestPriority::Background, true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn admits_without_queueing_while_slots_are_free() {
+        let queue = queue(2, 0, ShedPolicy::RejectNew);
+        let _first = queue.acquire(RequestPriority::Normal).await.unwrap();
+        let _second = queue.acquire(RequestPriority::Background).await.unwrap();
+        let rejected = queue.acquire(RequestPriority::Critical).await;
+        assert!(matches!(rejected, Err(Error::Overloaded(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_new_requests_when_full() {
+        let queue = queue(1, 1, ShedPolicy::RejectNew);
+        let _held = queue.acquire(RequestPriority::Normal).await.unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        enqueue(&queue, RequestPriority::Background, &tx).await;
+
+        let rejected = queue.acquire(RequestPriority::Critical).await;
+        assert!(matches!(rejected, Err(Error::Overloaded(_))));
+    }
+
+    #[tokio::test]
+    async fn drops_lower_priority_requests_when_full() {
+        let queue = queue(1, 1, ShedPolicy::DropLowest);
+        let held = queue.acquire(RequestPriority::Normal).await.unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        enqueue(&queue, RequestPriority::Background, &tx).await;
+        enqueue(&queue, RequestPriority::High, &tx).await;
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            (RequestPriority::Background, false)
+        );
+
+        let rejected = queue.acquire(RequestPriority::Normal).await;
+        assert!(matches!(rejected, Err(Error::Overloaded(_))));
+
+        drop(held);
+        assert_eq!(rx.recv().await.unwrap(), (RequestPriority::High, true));
+    }
+}
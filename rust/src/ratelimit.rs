@@ -0,0 +1,123 @@
+use crate::rt::Instant;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Client-side limit on the rate of outbound requests.
+#[derive(Debug, Clone)]
+pub struct RateLimiterConfig {
+    /// Sustained requests per second. Defaults to 10.
+    pub requests_per_second: f64,
+    /// Requests that may be sent at once after a quiet period. Defaults to 10.
+    pub burst: u32,
+}
+
+impl Default for RateLimiterConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 10.0,
+            burst: 10,
+        }
+    }
+}
+
+/// Token bucket shared by every clone of a client. Each request takes a
+/// token; tokens refill at `requests_per_second` up to `burst`.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Goes negative while requests are waiting, so waiters queue up behind
+    /// each other instead of racing for the next token.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: RateLimiterConfig) -> Self {
+        let burst = f64::from(config.burst.max(1));
+        Self {
+            rate: config.requests_per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent.
+    pub(crate) async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.updated).as_secs_f64() * self.rate;
+            bucket.tokens = (bucket.tokens + refill).min(self.burst) - 1.0;
+            bucket.updated = now;
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / self.rate))
+        };
+        if let Some(wait) = wait {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                wait_ms = wait.as_millis() as u64,
+                "waiting for the client-side rate limiter"
+            );
+            crate::rt::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_second: f64, burst: u32) -> RateLimiter {
+        RateLimiter::new(RateLimiterConfig {
+            requests_per_second,
+            burst,
+        })
+    }
+
+    #[tokio::test]
+    async fn sends_a_burst_then_paces_requests() {
+        let limiter = limiter(20.0, 2);
+        let started = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(started.elapsed() < Duration::from_millis(30));
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(95), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(400), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn concurrent_waiters_queue_behind_each_other() {
+        let limiter = limiter(20.0, 1);
+        limiter.acquire().await;
+
+        let started = Instant::now();
+        futures::future::join3(limiter.acquire(), limiter.acquire(), limiter.acquire()).await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(145), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn refills_no_more_than_the_burst() {
+        let limiter = limiter(100.0, 1);
+        limiter.acquire().await;
+        crate::rt::sleep(Duration::from_millis(100)).await;
+
+        let started = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(9));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::errors::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Hook notified before the client retries a failed request.
+///
+/// Install one with
+/// [`ClientConfig::retry_observer`](crate::ClientConfig::retry_observer) to
+/// count or log retries per endpoint. Called on the request's task, so keep
+/// it cheap.
+pub trait RetryObserver: Send + Sync + fmt::Debug {
+    /// Called after attempt number `attempt` (starting at 1) failed with
+    /// `cause`, before sleeping for `delay`. `endpoint` is the request path
+    /// relative to the base URL, without the query string.
+    fn on_retry(&self, attempt: u32, delay: Duration, cause: &Error, endpoint: &str);
+}
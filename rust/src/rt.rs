@@ -0,0 +1,61 @@
+//! Clocks and timers that work both natively and on `wasm32`, where Tokio's
+//! timer and `std::time::Instant` are unavailable.
+
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+/// Waits for `duration`.
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(duration).await;
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// File system access. There is no file system on `wasm32`, so there every
+/// call fails with [`std::io::ErrorKind::Unsupported`] and features backed by
+/// files degrade as they do on any I/O error.
+pub(crate) mod fs {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) use tokio::fs::{create_dir_all, read, read_to_string, remove_file, rename, write};
+
+    #[cfg(target_arch = "wasm32")]
+    mod unsupported {
+        use std::io;
+        use std::path::Path;
+
+        pub(crate) async fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+            std::fs::read(path)
+        }
+
+        pub(crate) async fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+            std::fs::read_to_string(path)
+        }
+
+        pub(crate) async fn write(
+            path: impl AsRef<Path>,
+            contents: impl AsRef<[u8]>,
+        ) -> io::Result<()> {
+            std::fs::write(path, contents)
+        }
+
+        pub(crate) async fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+            std::fs::rename(from, to)
+        }
+
+        pub(crate) async fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+            std::fs::create_dir_all(path)
+        }
+
+        pub(crate) async fn remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+            std::fs::remove_file(path)
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) use unsupported::*;
+}
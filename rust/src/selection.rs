@@ -0,0 +1,39 @@
+use crate::types::Agent;
+use std::fmt;
+
+/// Chooses an agent for manual assignment from a list of candidates.
+///
+/// Used by [`AgentAPI::pick`](crate::AgentAPI::pick), which passes the
+/// agents matching the caller's filters.
+pub trait SelectionStrategy: Send + Sync + fmt::Debug {
+    /// Returns the chosen agent, or `None` if no candidate is suitable.
+    fn select<'a>(&self, candidates: &'a [Agent]) -> Option<&'a Agent>;
+}
+
+/// Picks the agent with the lowest reported load.
+///
+/// Agents at or above `max_load` are skipped. Ties go to the agent seen most
+/// recently.
+#[derive(Debug, Clone, Copy)]
+pub struct LeastLoaded {
+    pub max_load: f64,
+}
+
+impl Default for LeastLoaded {
+    fn default() -> Self {
+        Self { max_load: 1.0 }
+    }
+}
+
+impl SelectionStrategy for LeastLoaded {
+    fn select<'a>(&self, candidates: &'a [Agent]) -> Option<&'a Agent> {
+        candidates
+            .iter()
+            .filter(|a| a.load < self.max_load)
+            .min_by(|a, b| {
+                a.load
+                    .total_cmp(&b.load)
+                    .then_with(|| b.last_seen.cmp(&a.last_seen))
+            })
+    }
+}
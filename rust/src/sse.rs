@@ -0,0 +1,208 @@
+use crate::client::BlackRoadClient;
+use crate::errors::Error;
+use bytes::Bytes;
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Wait before reconnecting when the server has not sent a `retry` field.
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[cfg(not(target_arch = "wasm32"))]
+type Body = stream::BoxStream<'static, reqwest::Result<Bytes>>;
+#[cfg(target_arch = "wasm32")]
+type Body = stream::LocalBoxStream<'static, reqwest::Result<Bytes>>;
+
+/// One server-sent event.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SseEvent {
+    /// The `event` field, or `"message"` when the server sent none.
+    pub event: String,
+    pub data: String,
+}
+
+/// Incremental parser for a `text/event-stream` body.
+#[derive(Debug, Default)]
+struct Parser {
+    buffer: Vec<u8>,
+    event: String,
+    data: String,
+    /// The `id` field seen so far, committed to `last_id` on dispatch.
+    id: Option<String>,
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Parser {
+    /// Consumes a chunk of the body and returns the events it completed.
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                events.extend(self.dispatch());
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => self.event = value.to_string(),
+                "data" => {
+                    self.data.push_str(value);
+                    self.data.push('\n');
+                }
+                "id" => self.id = Some(value.to_string()),
+                "retry" => {
+                    if let Ok(ms) = value.parse() {
+                        self.retry = Some(Duration::from_millis(ms));
+                    }
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    /// Drops a half-received event after the connection is lost.
+    fn discard_partial(&mut self) {
+        self.buffer.clear();
+        self.event.clear();
+        self.data.clear();
+        self.id = self.last_id.clone();
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        self.last_id = self.id.clone();
+        let event = std::mem::take(&mut self.event);
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+        Some(SseEvent {
+            event: if event.is_empty() {
+                "message".to_string()
+            } else {
+                event
+            },
+            data,
+        })
+    }
+}
+
+struct State {
+    client: BlackRoadClient,
+    endpoint: String,
+    parser: Parser,
+    body: Option<Body>,
+    pending: VecDeque<SseEvent>,
+}
+
+/// Subscribes to the event stream at `endpoint`.
+///
+/// When the connection drops, the stream waits for the server's `retry`
+/// interval and reconnects with `Last-Event-ID`, so no events are lost. It
+/// ends after yielding the first error from (re)connecting.
+pub(crate) fn event_stream(
+    client: BlackRoadClient,
+    endpoint: String,
+) -> impl Stream<Item = Result<SseEvent, Error>> {
+    let state = State {
+        client,
+        endpoint,
+        parser: Parser::default(),
+        body: None,
+        pending: VecDeque::new(),
+    };
+
+    stream::try_unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Ok(Some((event, state)));
+            }
+            match &mut state.body {
+                None => {
+                    let response = state
+                        .client
+                        .open_event_stream(&state.endpoint, state.parser.last_id.as_deref())
+                        .await?;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let body = response.bytes_stream().boxed();
+                    #[cfg(target_arch = "wasm32")]
+                    let body = response.bytes_stream().boxed_local();
+                    state.body = Some(body);
+                }
+                Some(body) => match body.next().await {
+                    Some(Ok(chunk)) => state.pending.extend(state.parser.feed(&chunk)),
+                    Some(Err(_)) | None => {
+                        state.body = None;
+                        state.parser.discard_partial();
+                        let delay = state.parser.retry.unwrap_or(DEFAULT_RECONNECT_DELAY);
+                        crate::rt::sleep(delay).await;
+                    }
+                },
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names_and_data(events: &[SseEvent]) -> Vec<(&str, &str)> {
+        events
+            .iter()
+            .map(|e| (e.event.as_str(), e.data.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_events_split_across_chunks() {
+        let mut parser = Parser::default();
+        assert!(parser.feed(b"event: status\ndata: {\"a\"").is_empty());
+        let events = parser.feed(b":1}\n\ndata: next\n\n");
+        assert_eq!(
+            names_and_data(&events),
+            [("status", "{\"a\":1}"), ("message", "next")]
+        );
+    }
+
+    #[test]
+    fn joins_data_lines_and_skips_comments() {
+        let mut parser = Parser::default();
+        let events = parser.feed(b": keepalive\r\ndata: one\r\ndata:two\r\n\r\n");
+        assert_eq!(names_and_data(&events), [("message", "one\ntwo")]);
+    }
+
+    #[test]
+    fn ignores_events_without_data() {
+        let mut parser = Parser::default();
+        assert!(parser.feed(b"event: ping\n\n").is_empty());
+        let events = parser.feed(b"data: x\n\n");
+        assert_eq!(names_and_data(&events), [("message", "x")]);
+    }
+
+    #[test]
+    fn tracks_last_event_id_and_retry() {
+        let mut parser = Parser::default();
+        parser.feed(b"id: 7\nretry: 2500\ndata: x\n\n");
+        assert_eq!(parser.last_id.as_deref(), Some("7"));
+        assert_eq!(parser.retry, Some(Duration::from_millis(2500)));
+
+        parser.feed(b"id: 8\ndata: half");
+        parser.discard_partial();
+        assert_eq!(parser.last_id.as_deref(), Some("7"));
+        let events = parser.feed(b"data: whole\n\n");
+        assert_eq!(names_and_data(&events), [("message", "whole")]);
+        assert_eq!(parser.last_id.as_deref(), Some("7"));
+    }
+}
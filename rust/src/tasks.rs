@@ -1,8 +1,33 @@
-use crate::client::BlackRoadClient;
+use crate::client::{path_segment, BlackRoadClient, Scope};
 use crate::errors::Error;
-use crate::types::{DispatchTaskOptions, Stats, Task, TaskListOptions};
-use serde::Deserialize;
+use crate::polling::{
+    long_poll_wait, page_stream, poll_stream, push_or_poll, Batch, PageStart, DEFAULT_PAGE_SIZE,
+};
+use crate::sse::{event_stream, SseEvent};
+use crate::types::{
+    BulkResult, ClaimOptions, DispatchTaskOptions, FacetCount, Faceted, Page, PeekOptions,
+    Schedule, ScheduleSpec, SlaBreachOptions, SlaKind, Snapshot, Task, TaskEvent, TaskEventKind,
+    TaskGraph, TaskListOptions, TaskLogLine, TaskOutputChunk, TaskPriority, TaskProgress,
+    TaskSlaBreach, TaskStats, TaskStatsOptions, TaskStatus, TaskStatusChange, TaskUpdate,
+    WaitOptions,
+};
+use chrono::{DateTime, Utc};
+use futures::{future, stream, FutureExt, Stream, StreamExt, TryStreamExt};
+use reqwest::header::IF_MATCH;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// Tasks sent per request by `dispatch_batch`.
+const DISPATCH_BATCH_SIZE: usize = 100;
+const BULK_CONCURRENCY: usize = 8;
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const LOG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Pause before polling a task's events again when the API answers early.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Pause before claiming again when the API answers a claim early.
+const CLAIM_IDLE_DELAY: Duration = Duration::from_secs(1);
 
 /// API for managing tasks.
 #[derive(Debug, Clone)]
@@ -15,6 +40,97 @@ struct TasksResponse {
     tasks: Vec<Task>,
 }
 
+#[derive(Deserialize)]
+struct TaskPageResponse {
+    tasks: Vec<Task>,
+    #[serde(default)]
+    total: Option<i64>,
+    #[serde(default)]
+    limit: Option<i32>,
+    #[serde(default)]
+    offset: Option<i32>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FacetedTasksResponse {
+    tasks: Vec<Task>,
+    #[serde(default)]
+    facets: HashMap<String, Vec<FacetCount>>,
+}
+
+#[derive(Deserialize)]
+struct SchedulesResponse {
+    schedules: Vec<Schedule>,
+}
+
+#[derive(Deserialize)]
+struct BatchDispatchResponse {
+    results: Vec<BatchDispatchItem>,
+}
+
+/// One task's outcome in a batch: the created task, or the status and body
+/// the API would have returned for it alone.
+#[derive(Deserialize)]
+struct BatchDispatchItem {
+    #[serde(default)]
+    task: Option<Task>,
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+impl BatchDispatchItem {
+    fn into_result(self) -> Result<Task, Error> {
+        if let Some(task) = self.task {
+            return Ok(task);
+        }
+        let status = self
+            .status
+            .and_then(|s| StatusCode::from_u16(s).ok())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = match self.error {
+            Some(serde_json::Value::String(message)) => message,
+            Some(error) => error.to_string(),
+            None => String::new(),
+        };
+        Err(Error::from_status(status, body, None))
+    }
+}
+
+#[derive(Deserialize)]
+struct ClaimResponse {
+    #[serde(default)]
+    task: Option<Task>,
+}
+
+#[derive(Deserialize)]
+struct EventsResponse {
+    events: Vec<TaskEvent>,
+}
+
+#[derive(Deserialize)]
+struct PeekResponse {
+    #[serde(default)]
+    task: Option<Task>,
+}
+
+#[derive(Deserialize)]
+struct OutputResponse {
+    chunks: Vec<TaskOutputChunk>,
+    #[serde(default)]
+    done: bool,
+}
+
+#[derive(Deserialize)]
+struct LogsResponse {
+    lines: Vec<TaskLogLine>,
+    #[serde(default)]
+    done: bool,
+}
+
 #[derive(Deserialize)]
 struct CancelResponse {
     #[allow(dead_code)]
@@ -28,101 +144,750 @@ impl TaskAPI {
 
     /// Dispatches a new task.
     pub async fn dispatch(&self, opts: DispatchTaskOptions) -> Result<Task, Error> {
+        let body = self.dispatch_body(opts)?;
+        self.client.post("/tasks", &body).await
+    }
+
+    /// Dispatches many tasks, sending them in chunks of up to 100 per request.
+    ///
+    /// Returns one result per task, in the order given. Tasks that fail local
+    /// validation are not sent, and a failed chunk is reported against each
+    /// of its tasks; neither stops the rest of the batch.
+    pub async fn dispatch_batch(
+        &self,
+        tasks: Vec<DispatchTaskOptions>,
+    ) -> Vec<Result<Task, Error>> {
+        let mut results: Vec<Option<Result<Task, Error>>> = Vec::with_capacity(tasks.len());
+        let mut pending = Vec::new();
+        for (index, opts) in tasks.into_iter().enumerate() {
+            match self.dispatch_body(opts) {
+                Ok(body) => {
+                    results.push(None);
+                    pending.push((index, body));
+                }
+                Err(e) => results.push(Some(Err(e))),
+            }
+        }
+
+        let mut pending = pending.into_iter();
+        let chunks = std::iter::from_fn(|| {
+            let chunk: Vec<_> = pending.by_ref().take(DISPATCH_BATCH_SIZE).collect();
+            (!chunk.is_empty()).then_some(chunk)
+        });
+        let outcomes: Vec<_> = stream::iter(chunks)
+            .map(|chunk| async move {
+                let (indices, bodies): (Vec<usize>, Vec<serde_json::Value>) =
+                    chunk.into_iter().unzip();
+                let body = serde_json::json!({ "tasks": bodies });
+                let response = self
+                    .client
+                    .post::<BatchDispatchResponse, _>("/tasks/batch", &body)
+                    .await;
+                (indices, response)
+            })
+            .buffered(BULK_CONCURRENCY)
+            .collect()
+            .await;
+
+        for (indices, response) in outcomes {
+            match response {
+                Ok(response) => {
+                    let mut items = response.results.into_iter();
+                    for index in indices {
+                        results[index] = Some(match items.next() {
+                            Some(item) => item.into_result(),
+                            None => Err(Error::Api {
+                                status: 200,
+                                message: "batch response is missing this task".to_string(),
+                            }),
+                        });
+                    }
+                }
+                Err(e) => {
+                    for index in indices {
+                        results[index] = Some(Err(e.duplicate()));
+                    }
+                }
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("every task has a result"))
+            .collect()
+    }
+
+    /// Schedules `opts` to be dispatched once at a given time or on a cron
+    /// recurrence, replacing an external cron job that calls
+    /// [`dispatch`](Self::dispatch).
+    pub async fn schedule(
+        &self,
+        opts: DispatchTaskOptions,
+        spec: ScheduleSpec,
+    ) -> Result<Schedule, Error> {
+        spec.validate()?;
+        let mut body = serde_json::to_value(&spec)?;
+        body["task"] = self.dispatch_body(opts)?;
+        self.client.post("/tasks/schedules", &body).await
+    }
+
+    /// Lists active schedules.
+    pub async fn schedules(&self) -> Result<Vec<Schedule>, Error> {
+        let response: SchedulesResponse = self.client.get("/tasks/schedules", None).await?;
+        Ok(response.schedules)
+    }
+
+    /// Cancels a schedule. Tasks it already dispatched are not affected.
+    pub async fn cancel_schedule(&self, schedule_id: &str) -> Result<(), Error> {
+        let _: CancelResponse = self
+            .client
+            .delete(&format!("/tasks/schedules/{}", path_segment(schedule_id)?))
+            .await?;
+        Ok(())
+    }
+
+    /// Validates `opts` and builds the request body for creating a task.
+    fn dispatch_body(&self, opts: DispatchTaskOptions) -> Result<serde_json::Value, Error> {
+        opts.validate()?;
         let mut body = serde_json::json!({
             "title": opts.title,
-            "priority": opts.priority.unwrap_or_else(|| "medium".to_string()),
+            "priority": opts.priority.unwrap_or(TaskPriority::Medium),
         });
 
         if let Some(desc) = opts.description {
             body["description"] = serde_json::Value::String(desc);
         }
-        if let Some(div) = opts.division {
+        if let Some(div) = opts
+            .division
+            .or_else(|| self.client.scope().division.clone())
+        {
             body["division"] = serde_json::Value::String(div);
         }
         if let Some(level) = opts.target_level {
             body["target_level"] = serde_json::Value::Number(level.into());
         }
+        if let Some(at) = opts.respond_by {
+            body["respond_by"] = serde_json::to_value(at)?;
+        }
+        if let Some(at) = opts.complete_by {
+            body["complete_by"] = serde_json::to_value(at)?;
+        }
+        if !opts.depends_on.is_empty() {
+            body["depends_on"] = serde_json::to_value(opts.depends_on)?;
+        }
         if let Some(meta) = opts.metadata {
             body["metadata"] = serde_json::to_value(meta)?;
         }
 
-        self.client.post("/tasks", &body).await
+        Ok(body)
+    }
+
+    /// Gets the dependency graph reachable from a task.
+    pub async fn graph(&self, root_id: &str) -> Result<TaskGraph, Error> {
+        self.client
+            .get(&format!("/tasks/{}/graph", path_segment(root_id)?), None)
+            .await
+    }
+
+    /// Lists the tasks that must complete before `task_id` can start.
+    pub async fn dependencies(&self, task_id: &str) -> Result<Vec<Task>, Error> {
+        let response: TasksResponse = self
+            .client
+            .get(
+                &format!("/tasks/{}/dependencies", path_segment(task_id)?),
+                None,
+            )
+            .await?;
+        Ok(response.tasks)
+    }
+
+    /// Lists the tasks waiting on `task_id` to complete.
+    pub async fn dependents(&self, task_id: &str) -> Result<Vec<Task>, Error> {
+        let response: TasksResponse = self
+            .client
+            .get(
+                &format!("/tasks/{}/dependents", path_segment(task_id)?),
+                None,
+            )
+            .await?;
+        Ok(response.tasks)
     }
 
     /// Gets a specific task by ID.
     pub async fn get(&self, task_id: &str) -> Result<Task, Error> {
-        self.client.get(&format!("/tasks/{}", task_id), None).await
+        self.client
+            .get(&format!("/tasks/{}", path_segment(task_id)?), None)
+            .await
+    }
+
+    /// Gets several tasks concurrently, at most eight requests at a time.
+    ///
+    /// Results are returned in the order of `task_ids`; one failure does not
+    /// stop the others.
+    pub async fn get_many<I, S>(&self, task_ids: I) -> Vec<BulkResult<Task>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        stream::iter(task_ids.into_iter().map(Into::into))
+            .map(|id: String| async move {
+                let result = self.get(&id).await;
+                BulkResult { id, result }
+            })
+            .buffered(BULK_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Lists tasks like [`list`](Self::list), falling back to the last-known
+    /// result if the API is unreachable.
+    pub async fn list_snapshot(
+        &self,
+        opts: Option<TaskListOptions>,
+    ) -> Result<Snapshot<Vec<Task>>, Error> {
+        let params = list_params(opts, self.client.scope());
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        let snapshot: Snapshot<TasksResponse> =
+            self.client.get_snapshot("/tasks", params_opt).await?;
+        Ok(snapshot.map(|r| r.tasks))
+    }
+
+    /// Gets a task like [`get`](Self::get), falling back to the last-known
+    /// result if the API is unreachable.
+    pub async fn get_snapshot(&self, task_id: &str) -> Result<Snapshot<Task>, Error> {
+        self.client
+            .get_snapshot(&format!("/tasks/{}", path_segment(task_id)?), None)
+            .await
     }
 
     /// Lists tasks with optional filters.
     pub async fn list(&self, opts: Option<TaskListOptions>) -> Result<Vec<Task>, Error> {
+        let params = list_params(opts, self.client.scope());
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        let response: TasksResponse = self.client.get("/tasks", params_opt).await?;
+        Ok(response.tasks)
+    }
+
+    /// Lists tasks like [`list`](Self::list), keeping the total count and paging
+    /// position the API reports alongside them.
+    pub async fn list_page(&self, opts: Option<TaskListOptions>) -> Result<Page<Task>, Error> {
+        let params = list_params(opts, self.client.scope());
+        let response: TaskPageResponse = self.client.get("/tasks", Some(&params)).await?;
+        Ok(Page {
+            items: response.tasks,
+            total: response.total,
+            limit: response.limit,
+            offset: response.offset,
+            next_cursor: response.next_cursor,
+        })
+    }
+
+    /// Returns a stream of every task matching `opts`, fetching pages of
+    /// `opts.limit` (default 100) starting at `opts.cursor` or `opts.offset`.
+    /// Pages follow the API's `next_cursor` when it returns one. Tasks that
+    /// move between pages while the stream runs are yielded only once.
+    pub fn list_all(
+        &self,
+        opts: Option<TaskListOptions>,
+    ) -> impl Stream<Item = Result<Task, Error>> {
+        let mut opts = opts.unwrap_or_default();
+        let page_size = opts.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+        let start = match opts.cursor.take() {
+            Some(cursor) => PageStart::Cursor(cursor),
+            None => PageStart::Offset(opts.offset.unwrap_or(0).max(0)),
+        };
+        let api = self.clone();
+        page_stream(
+            start,
+            page_size,
+            |t: &Task| t.id.clone(),
+            move |start| {
+                let api = api.clone();
+                let opts = TaskListOptions {
+                    limit: Some(page_size),
+                    offset: start.offset(),
+                    cursor: start.cursor(),
+                    ..opts.clone()
+                };
+                async move { api.list_page(Some(opts)).await }
+            },
+        )
+    }
+
+    /// Lists tasks like [`list`](Self::list), also counting matches per value
+    /// of each field in `facets` (e.g. `"status"`, `"division"`, `"priority"`).
+    pub async fn list_with_facets(
+        &self,
+        opts: Option<TaskListOptions>,
+        facets: &[&str],
+    ) -> Result<Faceted<Task>, Error> {
+        let mut params = list_params(opts, self.client.scope());
+        params.insert("facets".to_string(), facets.join(","));
+        let response: FacetedTasksResponse = self.client.get("/tasks", Some(&params)).await?;
+        Ok(Faceted {
+            items: response.tasks,
+            facets: response.facets,
+        })
+    }
+
+    /// Waits until a task matching `opts` is available and claims it for
+    /// `agent_id`, or returns `None` once `timeout` has passed.
+    ///
+    /// The API holds each request open until a task arrives (long polling), so
+    /// idle workers do not need to poll [`pending`](Self::pending). Claiming is
+    /// atomic: no two callers receive the same task.
+    pub async fn wait_for_task(
+        &self,
+        agent_id: &str,
+        opts: Option<ClaimOptions>,
+        timeout: Duration,
+    ) -> Result<Option<Task>, Error> {
+        let opts = opts.unwrap_or_default();
+        let max_wait = long_poll_wait(self.client.timeout());
+        let deadline = crate::rt::Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(crate::rt::Instant::now());
+            let wait = remaining.min(max_wait);
+            let started = crate::rt::Instant::now();
+            let task = self.claim(agent_id, &opts, wait).await?;
+            let remaining = deadline.saturating_duration_since(crate::rt::Instant::now());
+            if task.is_some() || remaining.is_zero() {
+                return Ok(task);
+            }
+            // An empty answer before the wait ran out means the API is not
+            // holding the request open, so pause instead of spinning.
+            if started.elapsed() < wait {
+                crate::rt::sleep(CLAIM_IDLE_DELAY.min(remaining)).await;
+            }
+        }
+    }
+
+    /// Claims the next task matching `opts` for `agent_id`, or returns `None`
+    /// right away if nothing is queued.
+    ///
+    /// Claiming is atomic, so competing workers can pull from the same queue
+    /// without processing a task twice. Set `opts.lease_secs` so that tasks
+    /// held by a crashed worker return to pending, and keep the lease alive
+    /// with [`renew_lease`](Self::renew_lease) while working.
+    pub async fn claim_next(
+        &self,
+        agent_id: &str,
+        opts: Option<ClaimOptions>,
+    ) -> Result<Option<Task>, Error> {
+        self.claim(agent_id, &opts.unwrap_or_default(), Duration::ZERO)
+            .await
+    }
+
+    /// Extends the lease on a claimed task by its original length, returning
+    /// the task with the new `lease_expires_at`.
+    ///
+    /// Fails with [`Error::Conflict`] if the lease already lapsed and the task
+    /// went back to pending or to another agent; stop working on it then.
+    pub async fn renew_lease(&self, task_id: &str) -> Result<Task, Error> {
+        let body = serde_json::json!({});
+        self.client
+            .post(
+                &format!("/tasks/{}/lease/renew", path_segment(task_id)?),
+                &body,
+            )
+            .await
+    }
+
+    async fn claim(
+        &self,
+        agent_id: &str,
+        opts: &ClaimOptions,
+        wait: Duration,
+    ) -> Result<Option<Task>, Error> {
+        let mut body = serde_json::to_value(opts)?;
+        body["agent_id"] = serde_json::Value::String(agent_id.to_string());
+        body["wait_secs"] = wait.as_secs().into();
+
+        let response: ClaimResponse = self.client.post("/tasks/claim", &body).await?;
+        Ok(response.task)
+    }
+
+    /// Polls a task until it is completed, failed, or cancelled and returns
+    /// it in that final state.
+    ///
+    /// Waits between polls start at `opts.poll_interval_ms` and grow by
+    /// `opts.backoff_factor` up to `opts.max_poll_interval_ms`. Fails with
+    /// [`Error::Timeout`] once `opts.timeout_secs` has passed. To react to
+    /// each transition as it happens, use [`watch`](Self::watch) instead.
+    pub async fn wait_for_completion(
+        &self,
+        task_id: &str,
+        opts: Option<WaitOptions>,
+    ) -> Result<Task, Error> {
+        let opts = opts.unwrap_or_default();
+        let mut interval = Duration::from_millis(opts.poll_interval_ms.unwrap_or(1000));
+        let max_interval =
+            Duration::from_millis(opts.max_poll_interval_ms.unwrap_or(30_000)).max(interval);
+        let factor = opts.backoff_factor.unwrap_or(1.5).max(1.0);
+        let deadline = opts
+            .timeout_secs
+            .map(|secs| crate::rt::Instant::now() + Duration::from_secs(secs));
+
+        loop {
+            let task = self.get(task_id).await?;
+            if task.status.is_terminal() {
+                return Ok(task);
+            }
+
+            let mut wait = interval;
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(crate::rt::Instant::now());
+                if remaining.is_zero() {
+                    return Err(Error::Timeout(format!(
+                        "task {} is still {}",
+                        task_id, task.status
+                    )));
+                }
+                wait = wait.min(remaining);
+            }
+            crate::rt::sleep(wait).await;
+            interval = interval.mul_f64(factor).min(max_interval);
+        }
+    }
+
+    /// Returns the task the scheduler would assign next, without claiming it.
+    ///
+    /// Returns `None` when nothing is queued. The task may still be assigned to
+    /// someone else before the caller claims it.
+    pub async fn peek(&self, opts: PeekOptions) -> Result<Option<Task>, Error> {
+        let mut params = HashMap::new();
+        if let Some(a) = opts.agent_id {
+            params.insert("agent".to_string(), a);
+        }
+        if let Some(d) = opts.division {
+            params.insert("division".to_string(), d);
+        }
+        if let Some(l) = opts.target_level {
+            params.insert("target_level".to_string(), l.to_string());
+        }
+
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        let response: PeekResponse = self.client.get("/tasks/next", params_opt).await?;
+        Ok(response.task)
+    }
+
+    /// Lists tasks that missed their `respond_by` or `complete_by` deadline.
+    pub async fn sla_breaches(&self, opts: Option<SlaBreachOptions>) -> Result<Vec<Task>, Error> {
         let mut params = HashMap::new();
 
         if let Some(opts) = opts {
-            if let Some(s) = opts.status {
-                params.insert("status".to_string(), s);
-            }
-            if let Some(p) = opts.priority {
-                params.insert("priority".to_string(), p);
+            if let Some(k) = opts.kind {
+                params.insert("kind".to_string(), k.as_str().to_string());
             }
             if let Some(d) = opts.division {
                 params.insert("division".to_string(), d);
             }
+            if let Some(p) = opts.priority {
+                params.insert("priority".to_string(), p.to_string());
+            }
+            if let Some(since) = opts.since {
+                params.insert("since".to_string(), since.to_rfc3339());
+            }
             if let Some(l) = opts.limit {
                 params.insert("limit".to_string(), l.to_string());
             }
-            if let Some(o) = opts.offset {
-                params.insert("offset".to_string(), o.to_string());
-            }
         }
 
-        let params_opt = if params.is_empty() { None } else { Some(&params) };
-        let response: TasksResponse = self.client.get("/tasks", params_opt).await?;
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        let response: TasksResponse = self.client.get("/tasks/sla-breaches", params_opt).await?;
         Ok(response.tasks)
     }
 
-    /// Completes a task.
+    /// Returns a copy of the API whose updates only apply if the task is still
+    /// at `version` (sent as `If-Match`). Reads are sent unconditionally.
+    ///
+    /// If another writer changed the task first, `complete`, `fail`, and
+    /// `assign` return [`Error::Conflict`] instead of overwriting it. These
+    /// updates are not retried after a 5xx response, since the first attempt
+    /// may have applied.
+    pub fn if_match(&self, version: &str) -> Result<TaskAPI, Error> {
+        Ok(Self {
+            client: self.client.with_header(IF_MATCH, version)?,
+        })
+    }
+
+    /// Completes a task with an optional text result. Use
+    /// [`complete_with`](Self::complete_with) for structured results.
     pub async fn complete(&self, task_id: &str, result: Option<&str>) -> Result<Task, Error> {
-        let mut body = serde_json::json!({ "status": "completed" });
+        let mut body = serde_json::json!({ "status": TaskStatus::Completed });
         if let Some(r) = result {
             body["result"] = serde_json::Value::String(r.to_string());
         }
-        self.client.put(&format!("/tasks/{}", task_id), &body).await
+        self.client
+            .put(&format!("/tasks/{}", path_segment(task_id)?), &body)
+            .await
+    }
+
+    /// Completes a task with a structured result, stored as JSON so the
+    /// reader can get it back with [`Task::result_as`].
+    pub async fn complete_with<T: Serialize>(
+        &self,
+        task_id: &str,
+        result: &T,
+    ) -> Result<Task, Error> {
+        let body = serde_json::json!({
+            "status": TaskStatus::Completed,
+            "result": serde_json::to_value(result)?,
+        });
+        self.client
+            .put(&format!("/tasks/{}", path_segment(task_id)?), &body)
+            .await
     }
 
     /// Fails a task.
     pub async fn fail(&self, task_id: &str, reason: Option<&str>) -> Result<Task, Error> {
-        let mut body = serde_json::json!({ "status": "failed" });
+        let mut body = serde_json::json!({ "status": TaskStatus::Failed });
         if let Some(r) = reason {
             body["result"] = serde_json::Value::String(r.to_string());
         }
-        self.client.put(&format!("/tasks/{}", task_id), &body).await
+        self.client
+            .put(&format!("/tasks/{}", path_segment(task_id)?), &body)
+            .await
     }
 
     /// Assigns a task to an agent.
     pub async fn assign(&self, task_id: &str, agent_id: &str) -> Result<Task, Error> {
         let body = serde_json::json!({
             "assigned_agent": agent_id,
-            "status": "assigned"
+            "status": TaskStatus::Assigned,
         });
-        self.client.put(&format!("/tasks/{}", task_id), &body).await
+        self.client
+            .put(&format!("/tasks/{}", path_segment(task_id)?), &body)
+            .await
     }
 
-    /// Cancels a task.
+    /// Cancels a task. Cancelled tasks can be brought back with
+    /// [`restore`](Self::restore) until the API purges them.
     pub async fn cancel(&self, task_id: &str) -> Result<(), Error> {
-        let _: CancelResponse = self.client.delete(&format!("/tasks/{}", task_id)).await?;
+        let _: CancelResponse = self
+            .client
+            .delete(&format!("/tasks/{}", path_segment(task_id)?))
+            .await?;
         Ok(())
     }
 
-    /// Gets task statistics.
-    pub async fn stats(&self) -> Result<Stats, Error> {
-        self.client.get("/tasks/stats", None).await
+    /// Restores a cancelled task.
+    pub async fn restore(&self, task_id: &str) -> Result<Task, Error> {
+        let body = serde_json::json!({});
+        self.client
+            .post(&format!("/tasks/{}/restore", path_segment(task_id)?), &body)
+            .await
+    }
+
+    /// Streams output chunks for a task as the assigned agent appends them.
+    ///
+    /// Chunks already written are yielded first. The stream ends once the task
+    /// reaches a terminal state and all output has been delivered.
+    pub fn stream_output(
+        &self,
+        task_id: &str,
+    ) -> impl Stream<Item = Result<TaskOutputChunk, Error>> {
+        let client = self.client.clone();
+        let task_id = task_id.to_string();
+
+        poll_stream(-1, OUTPUT_POLL_INTERVAL, move |after: i64| {
+            let client = client.clone();
+            let task_id = task_id.clone();
+            async move {
+                let endpoint = format!("/tasks/{}/output", path_segment(&task_id)?);
+                let mut params = HashMap::new();
+                params.insert("after".to_string(), after.to_string());
+
+                let response: OutputResponse = client.get(&endpoint, Some(&params)).await?;
+                let cursor = response.chunks.last().map_or(after, |c| c.seq);
+                Ok(Batch {
+                    items: response.chunks,
+                    cursor,
+                    done: response.done,
+                })
+            }
+        })
+    }
+
+    /// Appends a chunk of output to a running task.
+    pub async fn append_output(
+        &self,
+        task_id: &str,
+        chunk: &str,
+    ) -> Result<TaskOutputChunk, Error> {
+        let body = serde_json::json!({ "data": chunk });
+        self.client
+            .post(&format!("/tasks/{}/output", path_segment(task_id)?), &body)
+            .await
+    }
+
+    /// Gets a task's history of status changes, assignments, and progress
+    /// notes, oldest first.
+    pub async fn events(&self, task_id: &str) -> Result<Vec<TaskEvent>, Error> {
+        let response: EventsResponse = self
+            .client
+            .get(&format!("/tasks/{}/events", path_segment(task_id)?), None)
+            .await?;
+        Ok(response.events)
+    }
+
+    /// Gets the log lines a task has emitted so far.
+    pub async fn logs(&self, task_id: &str) -> Result<Vec<TaskLogLine>, Error> {
+        let response: LogsResponse = self
+            .client
+            .get(&format!("/tasks/{}/logs", path_segment(task_id)?), None)
+            .await?;
+        Ok(response.lines)
+    }
+
+    /// Streams log lines for a task as they are emitted.
+    ///
+    /// Lines already emitted are yielded first. The stream ends once the task
+    /// reaches a terminal state and all lines have been delivered.
+    pub fn logs_stream(&self, task_id: &str) -> impl Stream<Item = Result<TaskLogLine, Error>> {
+        let client = self.client.clone();
+        let task_id = task_id.to_string();
+
+        poll_stream(-1, LOG_POLL_INTERVAL, move |after: i64| {
+            let client = client.clone();
+            let task_id = task_id.clone();
+            async move {
+                let endpoint = format!("/tasks/{}/logs", path_segment(&task_id)?);
+                let mut params = HashMap::new();
+                params.insert("after".to_string(), after.to_string());
+
+                let response: LogsResponse = client.get(&endpoint, Some(&params)).await?;
+                let cursor = response.lines.last().map_or(after, |l| l.seq);
+                Ok(Batch {
+                    items: response.lines,
+                    cursor,
+                    done: response.done,
+                })
+            }
+        })
+    }
+
+    /// Streams status transitions and progress updates for a task as the API
+    /// pushes them over server-sent events.
+    ///
+    /// The task is fetched first to learn its `respond_by` and `complete_by`
+    /// deadlines. If one passes while the task is still pending or, for
+    /// `complete_by`, unfinished, a [`TaskUpdate::SlaBreach`] is yielded;
+    /// a deadline already missed when watching starts is reported at once.
+    ///
+    /// If server-sent events are blocked, the task's event history is long
+    /// polled instead; see [`StreamTransport`](crate::StreamTransport).
+    ///
+    /// The stream ends after the task reaches a terminal status, or after
+    /// yielding the first error. Dropped connections are resumed without
+    /// missing updates.
+    pub fn watch(&self, task_id: &str) -> impl Stream<Item = Result<TaskUpdate, Error>> {
+        let id = match path_segment(task_id) {
+            Ok(id) => id,
+            Err(e) => return stream::once(future::ready(Err(e))).left_stream(),
+        };
+        let tasks = self.clone();
+        let task_id = task_id.to_string();
+        stream::once(async move {
+            let task = tasks.get(&task_id).await?;
+            let pushed = event_stream(tasks.client.clone(), format!("/tasks/{}/watch", id))
+                .try_filter_map(|event| future::ready(parse_watch_event(&event)));
+            let updates = push_or_poll(
+                tasks.client.stream_transport(),
+                Box::pin(pushed),
+                move || Box::pin(tasks.poll_updates(task_id)),
+            );
+            Ok::<_, Error>(watch_updates(task, Box::pin(updates)))
+        })
+        .try_flatten()
+        .right_stream()
+    }
+
+    /// Long polls the events of a task logged after the call, as updates.
+    fn poll_updates(&self, task_id: String) -> impl Stream<Item = Result<TaskUpdate, Error>> {
+        let client = self.client.clone();
+        let wait = long_poll_wait(client.timeout());
+        let id = task_id.clone();
+        poll_stream(None, EVENT_POLL_INTERVAL, move |after: Option<i64>| {
+            let client = client.clone();
+            let task_id = id.clone();
+            async move {
+                let endpoint = format!("/tasks/{}/events", path_segment(&task_id)?);
+                let mut params = HashMap::new();
+                if let Some(after) = after {
+                    params.insert("after".to_string(), after.to_string());
+                    params.insert("wait".to_string(), wait.as_secs().to_string());
+                }
+                let response: EventsResponse = client.get(&endpoint, Some(&params)).await?;
+                let cursor = response
+                    .events
+                    .last()
+                    .map(|e| e.seq)
+                    .or(after)
+                    .unwrap_or(-1);
+                // The first call only finds where the history ends.
+                let items = match after {
+                    Some(_) => response.events,
+                    None => Vec::new(),
+                };
+                Ok(Batch {
+                    items,
+                    cursor: Some(cursor),
+                    done: false,
+                })
+            }
+        })
+        .try_filter_map(move |event| future::ready(Ok(task_update(&task_id, event))))
+    }
+
+    /// Gets task statistics, optionally filtered by division, priority, or time range.
+    pub async fn stats(&self, opts: Option<TaskStatsOptions>) -> Result<TaskStats, Error> {
+        let mut params = HashMap::new();
+
+        if let Some(opts) = opts {
+            if let Some(d) = opts.division {
+                params.insert("division".to_string(), d);
+            }
+            if let Some(p) = opts.priority {
+                params.insert("priority".to_string(), p.to_string());
+            }
+            if let Some(since) = opts.since {
+                params.insert("since".to_string(), since.to_rfc3339());
+            }
+            if let Some(until) = opts.until {
+                params.insert("until".to_string(), until.to_rfc3339());
+            }
+        }
+
+        let params_opt = if params.is_empty() {
+            None
+        } else {
+            Some(&params)
+        };
+        self.client.get("/tasks/stats", params_opt).await
     }
 
     /// Gets pending tasks.
     pub async fn pending(&self) -> Result<Vec<Task>, Error> {
         self.list(Some(TaskListOptions {
-            status: Some("pending".to_string()),
+            status: Some(TaskStatus::Pending),
             ..Default::default()
         }))
         .await
@@ -131,7 +896,7 @@ impl TaskAPI {
     /// Gets in-progress tasks.
     pub async fn in_progress(&self) -> Result<Vec<Task>, Error> {
         self.list(Some(TaskListOptions {
-            status: Some("in_progress".to_string()),
+            status: Some(TaskStatus::InProgress),
             ..Default::default()
         }))
         .await
@@ -149,9 +914,138 @@ impl TaskAPI {
     /// Gets urgent tasks.
     pub async fn urgent(&self) -> Result<Vec<Task>, Error> {
         self.list(Some(TaskListOptions {
-            priority: Some("urgent".to_string()),
+            priority: Some(TaskPriority::Urgent),
             ..Default::default()
         }))
         .await
     }
 }
+
+fn list_params(opts: Option<TaskListOptions>, scope: &Scope) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+
+    if let Some(opts) = opts {
+        if let Some(s) = opts.status {
+            params.insert("status".to_string(), s.to_string());
+        }
+        if let Some(p) = opts.priority {
+            params.insert("priority".to_string(), p.to_string());
+        }
+        if let Some(d) = opts.division {
+            params.insert("division".to_string(), d);
+        }
+        if let Some(d) = opts.include_deleted {
+            params.insert("include_deleted".to_string(), d.to_string());
+        }
+        if let Some(l) = opts.limit {
+            params.insert("limit".to_string(), l.to_string());
+        }
+        if let Some(o) = opts.offset {
+            params.insert("offset".to_string(), o.to_string());
+        }
+        if let Some(c) = opts.cursor {
+            params.insert("cursor".to_string(), c);
+        }
+    }
+
+    if let Some(d) = &scope.division {
+        params
+            .entry("division".to_string())
+            .or_insert_with(|| d.clone());
+    }
+
+    params
+}
+
+/// Passes on a task's updates, adding a breach for each SLA deadline that
+/// passes before the task meets it. Ends after a terminal status or the
+/// first error.
+fn watch_updates<S>(task: Task, updates: S) -> impl Stream<Item = Result<TaskUpdate, Error>>
+where
+    S: Stream<Item = Result<TaskUpdate, Error>> + Unpin,
+{
+    use futures::future::Either;
+
+    let mut deadlines: Vec<(DateTime<Utc>, SlaKind)> = [
+        task.respond_by.map(|at| (at, SlaKind::Response)),
+        task.complete_by.map(|at| (at, SlaKind::Completion)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    deadlines.sort_by_key(|(at, _)| *at);
+    let state = (Some(updates), task.id, task.status, deadlines);
+
+    stream::unfold(
+        state,
+        |(updates, task_id, mut status, mut deadlines)| async move {
+            let mut updates = updates?;
+            loop {
+                let next_deadline = deadlines
+                    .first()
+                    .map(|(at, _)| (*at - Utc::now()).to_std().unwrap_or(Duration::ZERO));
+                let timer = match next_deadline {
+                    Some(wait) => crate::rt::sleep(wait).left_future(),
+                    None => future::pending().right_future(),
+                };
+                let update = match future::select(updates.next(), Box::pin(timer)).await {
+                    Either::Left((None, _)) => return None,
+                    Either::Left((Some(update), _)) => update,
+                    Either::Right(_) => {
+                        let (deadline, kind) = deadlines.remove(0);
+                        let met = match kind {
+                            SlaKind::Response => status != TaskStatus::Pending,
+                            SlaKind::Completion => status.is_terminal(),
+                        };
+                        if met {
+                            continue;
+                        }
+                        Ok(TaskUpdate::SlaBreach(TaskSlaBreach {
+                            task_id: task_id.clone(),
+                            kind,
+                            deadline,
+                        }))
+                    }
+                };
+                let done = match &update {
+                    Ok(TaskUpdate::Status(change)) => {
+                        status = change.status.clone();
+                        status.is_terminal()
+                    }
+                    Ok(_) => false,
+                    Err(_) => true,
+                };
+                let updates = (!done).then_some(updates);
+                return Some((update, (updates, task_id, status, deadlines)));
+            }
+        },
+    )
+}
+
+fn parse_watch_event(event: &SseEvent) -> Result<Option<TaskUpdate>, Error> {
+    Ok(match event.event.as_str() {
+        "status" => Some(TaskUpdate::Status(serde_json::from_str(&event.data)?)),
+        "progress" => Some(TaskUpdate::Progress(serde_json::from_str(&event.data)?)),
+        _ => None,
+    })
+}
+
+/// Converts a task event to the update `watch` would have pushed for it.
+fn task_update(task_id: &str, event: TaskEvent) -> Option<TaskUpdate> {
+    match event.kind {
+        TaskEventKind::StatusChanged => Some(TaskUpdate::Status(TaskStatusChange {
+            task_id: task_id.to_string(),
+            status: event.status?,
+            previous_status: event.previous_status,
+            agent_id: event.agent_id,
+            changed_at: event.timestamp,
+        })),
+        TaskEventKind::Progress => Some(TaskUpdate::Progress(TaskProgress {
+            task_id: task_id.to_string(),
+            percent: event.percent,
+            message: event.message,
+            reported_at: event.timestamp,
+        })),
+        _ => None,
+    }
+}
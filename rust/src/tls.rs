@@ -0,0 +1,114 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::errors::{Error, ValidationErrorBody};
+use std::fmt;
+
+/// A client certificate presented for mutual TLS.
+#[derive(Clone)]
+pub enum ClientIdentity {
+    /// A PEM certificate chain and its PKCS#8 PEM private key.
+    Pem { cert: Vec<u8>, key: Vec<u8> },
+    /// A DER-encoded PKCS#12 archive and its password.
+    Pkcs12 { der: Vec<u8>, password: String },
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientIdentity::Pem { .. } => f.write_str("ClientIdentity::Pem(..)"),
+            ClientIdentity::Pkcs12 { .. } => f.write_str("ClientIdentity::Pkcs12(..)"),
+        }
+    }
+}
+
+/// TLS settings from [`ClientConfig`](crate::ClientConfig), shared by the
+/// HTTP client and the event stream socket.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct TlsSettings<'a> {
+    pub(crate) root_certificates: &'a [Vec<u8>],
+    pub(crate) identity: Option<&'a ClientIdentity>,
+    pub(crate) accept_invalid_certs: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TlsSettings<'_> {
+    fn is_default(&self) -> bool {
+        self.root_certificates.is_empty() && self.identity.is_none() && !self.accept_invalid_certs
+    }
+
+    /// Applies the settings to the reqwest client.
+    pub(crate) fn configure(
+        &self,
+        mut builder: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, Error> {
+        for pem in self.root_certificates {
+            for cert in
+                reqwest::Certificate::from_pem_bundle(pem).map_err(invalid("root certificate"))?
+            {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(identity) = self.identity {
+            let identity = match identity {
+                ClientIdentity::Pem { cert, key } => reqwest::Identity::from_pkcs8_pem(cert, key),
+                ClientIdentity::Pkcs12 { der, password } => {
+                    reqwest::Identity::from_pkcs12_der(der, password)
+                }
+            };
+            builder = builder.identity(identity.map_err(invalid("client identity"))?);
+        }
+        Ok(builder.danger_accept_invalid_certs(self.accept_invalid_certs))
+    }
+
+    /// Builds the connector for `wss://` sockets, or `None` to use the
+    /// system defaults.
+    pub(crate) fn websocket_connector(&self) -> Result<Option<native_tls::TlsConnector>, Error> {
+        if self.is_default() {
+            return Ok(None);
+        }
+        let mut builder = native_tls::TlsConnector::builder();
+        for pem in self.root_certificates {
+            for block in pem_blocks(pem) {
+                let cert = native_tls::Certificate::from_pem(block.as_bytes())
+                    .map_err(invalid("root certificate"))?;
+                builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(identity) = self.identity {
+            let identity = match identity {
+                ClientIdentity::Pem { cert, key } => native_tls::Identity::from_pkcs8(cert, key),
+                ClientIdentity::Pkcs12 { der, password } => {
+                    native_tls::Identity::from_pkcs12(der, password)
+                }
+            };
+            builder.identity(identity.map_err(invalid("client identity"))?);
+        }
+        builder.danger_accept_invalid_certs(self.accept_invalid_certs);
+        builder
+            .build()
+            .map(Some)
+            .map_err(invalid("TLS configuration"))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn invalid<E: fmt::Display>(what: &'static str) -> impl Fn(E) -> Error {
+    move |e| Error::Validation(ValidationErrorBody::new(format!("invalid {}: {}", what, e)))
+}
+
+/// Splits a PEM bundle into its `CERTIFICATE` blocks.
+#[cfg(not(target_arch = "wasm32"))]
+fn pem_blocks(pem: &[u8]) -> Vec<String> {
+    const END: &str = "-----END CERTIFICATE-----";
+    let text = String::from_utf8_lossy(pem);
+    let mut blocks = Vec::new();
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find("-----BEGIN CERTIFICATE-----") {
+        let Some(end) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + end + END.len();
+        blocks.push(rest[start..end].to_string());
+        rest = &rest[end..];
+    }
+    blocks
+}
@@ -0,0 +1,262 @@
+use crate::errors::Error;
+use async_trait::async_trait;
+use reqwest::{Client, Request, Response};
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE},
+    reqwest::{Method, StatusCode, Url},
+    serde::de::DeserializeOwned,
+    std::collections::VecDeque,
+    std::sync::{Arc, Mutex},
+};
+
+/// Sends HTTP requests on behalf of the client.
+///
+/// The default transport uses reqwest. Swap it through
+/// [`ClientConfig::transport`](crate::ClientConfig::transport), e.g. for a
+/// [`MockTransport`] in unit tests. Authentication, retries, interceptors, and
+/// error mapping all happen above the transport.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Transport: Send + Sync + fmt::Debug {
+    /// Sends `request` and returns the response, whatever its status.
+    ///
+    /// Return a transient error such as [`Error::Connection`] when the request
+    /// may not have reached the API; the client retries those.
+    async fn execute(&self, request: Request) -> Result<Response, Error>;
+}
+
+/// The default transport, backed by a reqwest client.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpTransport {
+    client: Client,
+}
+
+impl HttpTransport {
+    pub(crate) fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for HttpTransport {
+    async fn execute(&self, request: Request) -> Result<Response, Error> {
+        self.client
+            .execute(request)
+            .await
+            .map_err(|e| Error::Connection(format!("Request failed: {}", e)))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// A request captured by [`MockTransport`].
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RecordedRequest {
+    /// Parses the body as JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_slice(
+            self.body.as_deref().unwrap_or(b"null"),
+        )?)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum MockReply {
+    Status(StatusCode, HeaderMap, Vec<u8>),
+    Fail(String),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct MockRoute {
+    method: Method,
+    path: String,
+    reply: MockReply,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct MockState {
+    routes: VecDeque<MockRoute>,
+    requests: Vec<RecordedRequest>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// An in-memory [`Transport`] that serves canned responses and records every
+/// request, for testing code built on the SDK without an HTTP server.
+///
+/// Each canned response is used once, in the order added. Routes match on the
+/// method and the end of the URL path, so `"/agents"` matches
+/// `https://api.blackroad.io/v1/agents` and the query string is ignored.
+/// Requests without a matching response get a 404.
+///
+/// Clones share the same responses and recorded requests, so keep one to make
+/// assertions after handing another to the client.
+///
+/// Not available on `wasm32`.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a JSON response for `method` and `path`.
+    pub fn respond(
+        &self,
+        method: Method,
+        path: &str,
+        status: u16,
+        body: serde_json::Value,
+    ) -> &Self {
+        self.respond_raw(method, path, status, &[], body.to_string())
+    }
+
+    /// Queues a response with a raw body and extra headers, e.g. a
+    /// `text/event-stream` body, or `ETag` and `Retry-After` headers. The
+    /// content type is JSON unless `headers` sets one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header name or value is invalid.
+    pub fn respond_raw(
+        &self,
+        method: Method,
+        path: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: impl Into<Vec<u8>>,
+    ) -> &Self {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut header_map = HeaderMap::new();
+        header_map.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        for (name, value) in headers {
+            header_map.insert(
+                HeaderName::from_bytes(name.as_bytes()).expect("valid header name"),
+                HeaderValue::from_str(value).expect("valid header value"),
+            );
+        }
+        self.push(
+            method,
+            path,
+            MockReply::Status(status, header_map, body.into()),
+        )
+    }
+
+    /// Queues a connection failure for `method` and `path`.
+    pub fn fail(&self, method: Method, path: &str, message: &str) -> &Self {
+        self.push(method, path, MockReply::Fail(message.to_string()))
+    }
+
+    /// Returns every request sent so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Returns how many requests were sent for `method` and `path`.
+    pub fn calls(&self, method: Method, path: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|r| r.method == method && path_matches(r.url.path(), path))
+            .count()
+    }
+
+    /// Returns true if every queued response has been used.
+    pub fn is_exhausted(&self) -> bool {
+        self.state.lock().unwrap().routes.is_empty()
+    }
+
+    fn push(&self, method: Method, path: &str, reply: MockReply) -> &Self {
+        self.state.lock().unwrap().routes.push_back(MockRoute {
+            method,
+            path: path.to_string(),
+            reply,
+        });
+        self
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("MockTransport")
+            .field("queued", &state.routes.len())
+            .field("requests", &state.requests.len())
+            .finish()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for MockTransport {
+    async fn execute(&self, request: Request) -> Result<Response, Error> {
+        let recorded = RecordedRequest {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers: request.headers().clone(),
+            body: request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(<[u8]>::to_vec),
+        };
+
+        let reply = {
+            let mut state = self.state.lock().unwrap();
+            let position = state.routes.iter().position(|route| {
+                route.method == recorded.method && path_matches(recorded.url.path(), &route.path)
+            });
+            let reply = position
+                .and_then(|i| state.routes.remove(i))
+                .map(|route| route.reply);
+            state.requests.push(recorded.clone());
+            reply
+        };
+
+        let (status, headers, body) = match reply {
+            Some(MockReply::Status(status, headers, body)) => (status, headers, body),
+            Some(MockReply::Fail(message)) => return Err(Error::Connection(message)),
+            None => (
+                StatusCode::NOT_FOUND,
+                HeaderMap::from_iter([(CONTENT_TYPE, HeaderValue::from_static("text/plain"))]),
+                format!(
+                    "no mock response for {} {}",
+                    recorded.method,
+                    recorded.url.path()
+                )
+                .into_bytes(),
+            ),
+        };
+
+        let mut response = http::Response::new(body);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        Ok(Response::from(response))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path_matches(request_path: &str, route_path: &str) -> bool {
+    let route_path = route_path.split('?').next().unwrap_or(route_path);
+    match request_path.strip_suffix(route_path) {
+        Some(prefix) => prefix.is_empty() || route_path.starts_with('/') || prefix.ends_with('/'),
+        None => false,
+    }
+}
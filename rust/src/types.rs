@@ -1,6 +1,114 @@
-use chrono::{DateTime, Utc};
+use crate::attestation::Attestation;
+use crate::errors::Error;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Defines a string-backed enum that keeps values unknown to this version of
+/// the SDK in an `Unknown` variant instead of failing to deserialize.
+macro_rules! open_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            /// A value not known to this version of the SDK.
+            Unknown(String),
+        }
+
+        impl $name {
+            /// Returns the API representation.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    other => $name::Unknown(other.to_string()),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                Ok($name::from(value.as_str()))
+            }
+        }
+    };
+}
+
+open_enum! {
+    /// Lifecycle state of an agent.
+    pub enum AgentStatus {
+        Active => "active",
+        Standby => "standby",
+        Maintenance => "maintenance",
+        /// Not assigned new work until resumed; see
+        /// [`AgentAPI::pause`](crate::AgentAPI::pause).
+        Paused => "paused",
+        Dead => "dead",
+    }
+}
+
+open_enum! {
+    /// Lifecycle state of a task.
+    pub enum TaskStatus {
+        Pending => "pending",
+        Assigned => "assigned",
+        InProgress => "in_progress",
+        Completed => "completed",
+        Failed => "failed",
+        Cancelled => "cancelled",
+    }
+}
+
+impl TaskStatus {
+    /// Returns true for statuses a task never leaves: completed, failed, or
+    /// cancelled.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled
+        )
+    }
+}
+
+open_enum! {
+    /// Priority of a task.
+    pub enum TaskPriority {
+        Urgent => "urgent",
+        High => "high",
+        Medium => "medium",
+        Low => "low",
+    }
+}
 
 /// Represents a BlackRoad agent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,18 +120,107 @@ pub struct Agent {
     #[serde(default)]
     pub division: Option<String>,
     pub level: i32,
-    pub status: String,
+    pub status: AgentStatus,
     #[serde(default)]
     pub load: f64,
+    /// Machine fingerprint from the agent's [`Attestation`], if it attested.
     #[serde(default)]
     pub hash: Option<String>,
+    /// Software version the agent last reported.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Version the agent has been asked to upgrade to, if a rollout is pending.
+    #[serde(default)]
+    pub target_version: Option<String>,
     pub created_at: DateTime<Utc>,
     #[serde(default)]
     pub last_seen: Option<DateTime<Utc>>,
+    /// Set when the agent has been deleted but can still be restored.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// What the agent can do (e.g. `"gpu"`), used to route work to it.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
     #[serde(default)]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Outcome of one item in a bulk operation.
+#[derive(Debug)]
+pub struct BulkResult<T> {
+    pub id: String,
+    pub result: Result<T, Error>,
+}
+
+/// One page of a listing, with the position and totals the API reported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Matches across all pages, if the API counted them.
+    #[serde(default)]
+    pub total: Option<i64>,
+    #[serde(default)]
+    pub limit: Option<i32>,
+    #[serde(default)]
+    pub offset: Option<i32>,
+    /// Pass back as the `cursor` option to fetch the next page; `None` on
+    /// the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Search hits returned together with per-field facet counts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Faceted<T> {
+    pub items: Vec<T>,
+    /// Counts per requested field, e.g. `facets["status"]`, highest first.
+    #[serde(default)]
+    pub facets: HashMap<String, Vec<FacetCount>>,
+}
+
+/// Number of matches sharing one value of a faceted field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+/// Compact liveness snapshot of a single agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentPresence {
+    pub id: String,
+    pub online: bool,
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// A difference between two fleet presence snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresenceChange {
+    /// An agent appeared in the fleet.
+    Joined(AgentPresence),
+    /// A known agent came online.
+    Online(AgentPresence),
+    /// A known agent went offline.
+    Offline(AgentPresence),
+    /// An agent with this ID is no longer part of the fleet.
+    Left(String),
+}
+
+/// Runtime configuration pushed to an agent through the platform.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Server-assigned revision, incremented on every change. Ignored on writes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<u32>,
+    #[serde(default)]
+    pub feature_flags: HashMap<String, bool>,
+    #[serde(default)]
+    pub settings: HashMap<String, serde_json::Value>,
+}
+
 /// Represents a BlackRoad task.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
@@ -31,24 +228,229 @@ pub struct Task {
     pub title: String,
     #[serde(default)]
     pub description: Option<String>,
-    pub status: String,
-    pub priority: String,
+    pub status: TaskStatus,
+    pub priority: TaskPriority,
     #[serde(default)]
     pub division: Option<String>,
     #[serde(default)]
     pub target_level: Option<i32>,
     #[serde(default)]
     pub assigned_agent: Option<String>,
+    /// When the current claim lapses and the task returns to pending, if it
+    /// was claimed with a lease.
+    #[serde(default)]
+    pub lease_expires_at: Option<DateTime<Utc>>,
+    /// What the task produced: any JSON value, or a string for results set
+    /// with [`TaskAPI::complete`](crate::TaskAPI::complete). Read it as a
+    /// type with [`result_as`](Self::result_as).
     #[serde(default)]
-    pub result: Option<String>,
+    pub result: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     #[serde(default)]
     pub completed_at: Option<DateTime<Utc>>,
+    /// Deadline for an agent to pick up the task.
+    #[serde(default)]
+    pub respond_by: Option<DateTime<Utc>>,
+    /// Deadline for the task to complete.
+    #[serde(default)]
+    pub complete_by: Option<DateTime<Utc>>,
+    /// IDs of tasks that must complete before this one can start.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Opaque version that changes on every update, for use with
+    /// [`TaskAPI::if_match`](crate::TaskAPI::if_match).
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Set when the task has been cancelled but can still be restored.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
     #[serde(default)]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+impl Task {
+    /// Deserializes the task's result into `T`, or returns `None` if the task
+    /// has no result.
+    ///
+    /// Results stored as a JSON-encoded string, as older callers did with
+    /// [`TaskAPI::complete`](crate::TaskAPI::complete), are decoded as well.
+    pub fn result_as<T: DeserializeOwned>(&self) -> Result<Option<T>, Error> {
+        let Some(value) = &self.result else {
+            return Ok(None);
+        };
+        match serde_json::from_value(value.clone()) {
+            Ok(result) => Ok(Some(result)),
+            Err(e) => match value {
+                serde_json::Value::String(s) => {
+                    serde_json::from_str(s).map(Some).map_err(|_| e.into())
+                }
+                _ => Err(e.into()),
+            },
+        }
+    }
+}
+
+/// A dependency edge: `to` cannot start until `from` has completed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskEdge {
+    pub from: String,
+    pub to: String,
+}
+
+/// The dependency graph reachable from a task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskGraph {
+    pub root: String,
+    pub nodes: Vec<Task>,
+    pub edges: Vec<TaskEdge>,
+}
+
+impl TaskGraph {
+    /// Returns the IDs along a dependency cycle, if the graph has one.
+    ///
+    /// The first ID is repeated at the end, e.g. `["a", "b", "a"]`.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in &self.edges {
+            successors
+                .entry(edge.from.as_str())
+                .or_default()
+                .push(edge.to.as_str());
+        }
+
+        // 0 = unvisited, 1 = on the current path, 2 = finished
+        let mut state: HashMap<&str, u8> = HashMap::new();
+        let mut starts: Vec<&str> = self.nodes.iter().map(|t| t.id.as_str()).collect();
+        starts.extend(self.edges.iter().map(|e| e.from.as_str()));
+
+        for start in starts {
+            if state.contains_key(start) {
+                continue;
+            }
+            let mut path = vec![start];
+            let mut pending = vec![successors.get(start).cloned().unwrap_or_default()];
+            state.insert(start, 1);
+
+            while let Some(next) = pending.last_mut() {
+                match next.pop() {
+                    Some(node) => match state.get(node) {
+                        Some(1) => {
+                            let at = path.iter().position(|n| *n == node).unwrap_or(0);
+                            let mut cycle: Vec<String> =
+                                path[at..].iter().map(|n| n.to_string()).collect();
+                            cycle.push(node.to_string());
+                            return Some(cycle);
+                        }
+                        Some(_) => {}
+                        None => {
+                            state.insert(node, 1);
+                            path.push(node);
+                            pending.push(successors.get(node).cloned().unwrap_or_default());
+                        }
+                    },
+                    None => {
+                        pending.pop();
+                        if let Some(done) = path.pop() {
+                            state.insert(done, 2);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Renders the graph in Graphviz DOT format, labelling each task with its
+    /// title and status.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph tasks {\n");
+        for task in &self.nodes {
+            dot.push_str(&format!(
+                "  \"{}\" [label=\"{}\\n{}\"];\n",
+                dot_escape(&task.id),
+                dot_escape(&task.title),
+                dot_escape(task.status.as_str())
+            ));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  \"{}\" -> \"{}\";\n",
+                dot_escape(&edge.from),
+                dot_escape(&edge.to)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A chunk of incremental output appended to a running task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskOutputChunk {
+    /// Sequence number of the chunk within the task's output.
+    pub seq: i64,
+    pub data: String,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// A structured log line emitted while a task executes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskLogLine {
+    /// Sequence number of the line within the task's log.
+    pub seq: i64,
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub message: String,
+    #[serde(default)]
+    pub fields: Option<HashMap<String, serde_json::Value>>,
+}
+
+open_enum! {
+    /// What happened in a [`TaskEvent`].
+    pub enum TaskEventKind {
+        Created => "created",
+        StatusChanged => "status_changed",
+        Assigned => "assigned",
+        Progress => "progress",
+        Note => "note",
+        LeaseExpired => "lease_expired",
+    }
+}
+
+/// One entry in a task's history, as returned by
+/// [`TaskAPI::events`](crate::TaskAPI::events).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEvent {
+    /// Sequence number of the event within the task's history.
+    pub seq: i64,
+    #[serde(rename = "type")]
+    pub kind: TaskEventKind,
+    pub timestamp: DateTime<Utc>,
+    /// Agent or API key that caused the event, if known.
+    #[serde(default)]
+    pub actor: Option<String>,
+    /// The new status, for `status_changed` events.
+    #[serde(default)]
+    pub status: Option<TaskStatus>,
+    #[serde(default)]
+    pub previous_status: Option<TaskStatus>,
+    /// The agent the task was assigned to, for `assigned` events.
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    /// Fraction done, from 0.0 to 1.0, for `progress` events.
+    #[serde(default)]
+    pub percent: Option<f64>,
+    /// The progress message or note text.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
 /// Represents an entry in the BlackRoad memory system.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryEntry {
@@ -68,6 +470,32 @@ pub struct MemoryEntry {
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+impl MemoryEntry {
+    /// Recomputes the entry's hash: the hex SHA-256 of its `prev_hash`,
+    /// `timestamp` (RFC 3339 in UTC with milliseconds), `action`, `entity`,
+    /// `details`, and `agent`, each followed by a NUL byte, with absent fields
+    /// left empty. Tags and metadata are not covered.
+    ///
+    /// Encrypted entries hash their sealed `details`, so recompute from the
+    /// entry as stored rather than as decrypted.
+    pub fn compute_hash(&self) -> String {
+        let timestamp = self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true);
+        let mut hasher = Sha256::new();
+        for field in [
+            self.prev_hash.as_deref().unwrap_or_default(),
+            &timestamp,
+            &self.action,
+            &self.entity,
+            self.details.as_deref().unwrap_or_default(),
+            self.agent.as_deref().unwrap_or_default(),
+        ] {
+            hasher.update(field.as_bytes());
+            hasher.update([0]);
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
 /// Statistics from the API.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stats {
@@ -86,6 +514,241 @@ pub struct Stats {
     pub completed: Option<i64>,
 }
 
+/// Agent statistics from the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStats {
+    pub total: i64,
+    #[serde(default)]
+    pub by_status: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub by_type: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub by_level: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub active: Option<i64>,
+    /// Mean reported load across matching agents.
+    #[serde(default)]
+    pub avg_load: Option<f64>,
+    /// Agent counts bucketed by time since last heartbeat, keyed by bucket
+    /// label (e.g. `"<1m"`, `"1m-5m"`, `">1h"`).
+    #[serde(default)]
+    pub heartbeat_freshness: Option<HashMap<String, i64>>,
+}
+
+/// Autoscaling signal comparing queued work with available agent capacity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacitySignal {
+    pub division: Option<String>,
+    /// Number of pending tasks.
+    pub queue_depth: i64,
+    pub active_agents: i64,
+    pub avg_load: f64,
+    /// Unused capacity, in agents: `active_agents * (1 - avg_load)`.
+    pub available_capacity: f64,
+    /// Suggested change in agent count. Positive to scale out, negative to scale in.
+    pub recommended_delta: i64,
+}
+
+/// Combined activity rollup for a single division.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DivisionStats {
+    pub division: String,
+    pub agents_total: i64,
+    pub agents_online: i64,
+    #[serde(default)]
+    pub avg_load: Option<f64>,
+    /// Number of pending tasks.
+    pub queue_depth: i64,
+    #[serde(default)]
+    pub in_progress: Option<i64>,
+    /// Fraction of finished tasks that failed, from 0.0 to 1.0.
+    #[serde(default)]
+    pub failure_rate: Option<f64>,
+    /// Memory entries logged by the division's agents in the last 24 hours.
+    #[serde(default)]
+    pub memory_entries_24h: Option<i64>,
+    #[serde(default)]
+    pub last_memory_at: Option<DateTime<Utc>>,
+}
+
+/// A named group of agents, the value used by `division` filters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Division {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Agent responsible for the division, usually a level 2 commander.
+    #[serde(default)]
+    pub lead_agent_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// A tenant that owns projects, agents, and tasks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub slug: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// A project within an [`Organization`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub organization_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// Task statistics from the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStats {
+    pub total: i64,
+    #[serde(default)]
+    pub by_status: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub by_priority: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub by_division: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub pending: Option<i64>,
+    #[serde(default)]
+    pub completed: Option<i64>,
+    #[serde(default)]
+    pub failed: Option<i64>,
+    /// Mean time from creation to completion, in seconds.
+    #[serde(default)]
+    pub avg_completion_secs: Option<f64>,
+    /// Fraction of finished tasks that failed, between 0.0 and 1.0.
+    #[serde(default)]
+    pub failure_rate: Option<f64>,
+}
+
+/// Memory statistics from the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryStats {
+    pub total: i64,
+    /// Time-bucketed counts, present when an interval was requested.
+    #[serde(default)]
+    pub buckets: Vec<MemoryStatsBucket>,
+}
+
+/// Entry counts for a single time bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryStatsBucket {
+    pub start: DateTime<Utc>,
+    pub count: i64,
+    /// Counts per group key, present when a group-by was requested.
+    #[serde(default)]
+    pub groups: Option<HashMap<String, i64>>,
+}
+
+/// Width of the time buckets used in statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsInterval {
+    Hour,
+    Day,
+    Week,
+}
+
+impl StatsInterval {
+    /// Returns the API representation of the interval.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatsInterval::Hour => "hour",
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+        }
+    }
+
+    /// Returns the start of the bucket containing `at`. Weeks start on Monday.
+    pub fn bucket_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        const HOUR: i64 = 3600;
+        const DAY: i64 = 24 * HOUR;
+        // 1970-01-01 was a Thursday; shift so buckets begin on Monday.
+        const MONDAY_OFFSET: i64 = 3 * DAY;
+
+        let secs = at.timestamp();
+        let start = match self {
+            StatsInterval::Hour => secs - secs.rem_euclid(HOUR),
+            StatsInterval::Day => secs - secs.rem_euclid(DAY),
+            StatsInterval::Week => secs - (secs + MONDAY_OFFSET).rem_euclid(7 * DAY),
+        };
+        Utc.timestamp_opt(start, 0).single().unwrap_or(at)
+    }
+}
+
+/// Dimension to group memory statistics by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryGroupBy {
+    Action,
+    Agent,
+    Tag,
+}
+
+impl MemoryGroupBy {
+    /// Returns the API representation of the dimension.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryGroupBy::Action => "action",
+            MemoryGroupBy::Agent => "agent",
+            MemoryGroupBy::Tag => "tag",
+        }
+    }
+}
+
+/// A change in API health between two observations.
+#[derive(Debug, Clone)]
+pub struct HealthChange {
+    /// The previous observation, or `None` for the first item of a stream.
+    pub previous: Option<HealthStatus>,
+    pub current: HealthStatus,
+    /// Per-service status changes from the `services` map.
+    pub service_transitions: Vec<ServiceTransition>,
+}
+
+/// A status change of a single service reported by the health endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceTransition {
+    pub service: String,
+    /// Previous status, or `None` if the service was not reported before.
+    pub from: Option<String>,
+    /// New status, or `None` if the service is no longer reported.
+    pub to: Option<String>,
+}
+
+/// A read result that may have been served from the last-known response.
+#[derive(Debug, Clone)]
+pub struct Snapshot<T> {
+    pub value: T,
+    /// True if the API was unreachable and `value` is a previously fetched copy.
+    pub stale: bool,
+    /// When `value` was fetched from the API.
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl<T> Snapshot<T> {
+    /// Maps the value, keeping the staleness information.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Snapshot<U> {
+        Snapshot {
+            value: f(self.value),
+            stale: self.stale,
+            fetched_at: self.fetched_at,
+        }
+    }
+}
+
 /// API health status.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthStatus {
@@ -107,48 +770,251 @@ pub struct RegisterAgentOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub level: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Identity proof; its fingerprint becomes the agent's `hash`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+    /// What the agent can do (e.g. `"gpu"`, `"browser"`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// Options for creating a division.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreateDivisionOptions {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lead_agent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// Fields to change on an existing division. Fields left as `None` are kept.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdateDivisionOptions {
+    /// Renames the division; agents and tasks in it move with it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lead_agent_id: Option<String>,
+    /// Replaces the division's metadata as a whole.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// Fields to change on an existing agent. Fields left as `None` are kept.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdateAgentOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub division: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+    /// Replaces the agent's metadata as a whole.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Options for sending an agent heartbeat.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HeartbeatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+}
+
 /// Options for listing agents.
 #[derive(Debug, Clone, Default)]
 pub struct AgentListOptions {
     pub agent_type: Option<String>,
     pub division: Option<String>,
     pub level: Option<i32>,
-    pub status: Option<String>,
+    pub status: Option<AgentStatus>,
+    /// Only agents that declare this capability.
+    pub capability: Option<String>,
+    /// Only agents last seen before this time (or never seen).
+    pub seen_before: Option<DateTime<Utc>>,
+    /// Include deleted agents that can still be restored.
+    pub include_deleted: Option<bool>,
     pub limit: Option<i32>,
     pub offset: Option<i32>,
+    /// Opaque cursor from a previous [`Page::next_cursor`].
+    pub cursor: Option<String>,
+}
+
+/// Options for filtering agent statistics.
+#[derive(Debug, Clone, Default)]
+pub struct AgentStatsOptions {
+    pub agent_type: Option<String>,
+    pub division: Option<String>,
+    pub level: Option<i32>,
 }
 
 /// Options for dispatching a task.
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DispatchTaskOptions {
     pub title: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub priority: Option<String>,
+    pub priority: Option<TaskPriority>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub division: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target_level: Option<i32>,
+    /// SLA deadline for an agent to pick up the task.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub respond_by: Option<DateTime<Utc>>,
+    /// SLA deadline for the task to complete.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub complete_by: Option<DateTime<Utc>>,
+    /// IDs of tasks that must complete before this one can start.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// When a scheduled task is dispatched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScheduleSpec {
+    /// Dispatch once, at `run_at`.
+    Once { run_at: DateTime<Utc> },
+    /// Dispatch on a five-field cron expression, e.g. `"0 3 * * *"` for
+    /// 03:00 daily, evaluated in an IANA `timezone` (UTC if unset).
+    Cron {
+        cron: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+    },
+}
+
+/// A task template dispatched by the API at scheduled times.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub task: DispatchTaskOptions,
+    #[serde(flatten)]
+    pub spec: ScheduleSpec,
+    /// When the task will next be dispatched; `None` once a one-off schedule
+    /// has run.
+    #[serde(default)]
+    pub next_run_at: Option<DateTime<Utc>>,
+    /// The task dispatched by the most recent run.
+    #[serde(default)]
+    pub last_task_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Options for listing tasks.
 #[derive(Debug, Clone, Default)]
 pub struct TaskListOptions {
-    pub status: Option<String>,
-    pub priority: Option<String>,
+    pub status: Option<TaskStatus>,
+    pub priority: Option<TaskPriority>,
     pub division: Option<String>,
+    /// Include cancelled tasks that can still be restored.
+    pub include_deleted: Option<bool>,
     pub limit: Option<i32>,
     pub offset: Option<i32>,
+    /// Opaque cursor from a previous [`Page::next_cursor`].
+    pub cursor: Option<String>,
 }
 
-/// Options for logging a memory entry.
+/// Selects whose queue [`TaskAPI::peek`](crate::TaskAPI::peek) looks at.
+#[derive(Debug, Clone, Default)]
+pub struct PeekOptions {
+    /// Peek on behalf of a specific agent, honouring its division and level.
+    pub agent_id: Option<String>,
+    pub division: Option<String>,
+    pub target_level: Option<i32>,
+}
+
+/// Options for [`TaskAPI::wait_for_completion`](crate::TaskAPI::wait_for_completion).
+#[derive(Debug, Clone, Default)]
+pub struct WaitOptions {
+    /// Wait before the second poll, in milliseconds. Defaults to 1000.
+    pub poll_interval_ms: Option<u64>,
+    /// Longest wait between polls, in milliseconds. Defaults to 30000.
+    pub max_poll_interval_ms: Option<u64>,
+    /// Factor applied to the wait after each poll. Defaults to 1.5; use 1.0
+    /// for a fixed interval.
+    pub backoff_factor: Option<f64>,
+    /// Give up with [`Error::Timeout`] after this many seconds. Defaults to
+    /// waiting indefinitely.
+    pub timeout_secs: Option<u64>,
+}
+
+/// Restricts which tasks a worker will claim.
 #[derive(Debug, Clone, Default, Serialize)]
+pub struct ClaimOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub division: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_level: Option<i32>,
+    /// Only claim tasks with one of these priorities.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priorities: Option<Vec<TaskPriority>>,
+    /// Seconds the claim holds before the task goes back to pending, unless
+    /// it is finished or the lease is renewed with
+    /// [`TaskAPI::renew_lease`](crate::TaskAPI::renew_lease). Claims without
+    /// a lease hold until the task is finished.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lease_secs: Option<u64>,
+}
+
+/// Options for listing SLA breaches.
+#[derive(Debug, Clone, Default)]
+pub struct SlaBreachOptions {
+    /// Only report breaches of this deadline.
+    pub kind: Option<SlaKind>,
+    pub division: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<i32>,
+}
+
+/// Which SLA deadline a task breached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaKind {
+    /// The task was not picked up by `respond_by`.
+    Response,
+    /// The task was not completed by `complete_by`.
+    Completion,
+}
+
+impl SlaKind {
+    /// Returns the API representation of the SLA kind.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlaKind::Response => "response",
+            SlaKind::Completion => "completion",
+        }
+    }
+}
+
+/// Options for filtering task statistics.
+#[derive(Debug, Clone, Default)]
+pub struct TaskStatsOptions {
+    pub division: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Options for logging a memory entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LogMemoryOptions {
     pub action: String,
     pub entity: String,
@@ -156,8 +1022,41 @@ pub struct LogMemoryOptions {
     pub details: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    /// Agent the entry is attributed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
+    /// If set, the API skips this entry when an entry with the same
+    /// [`content_digest`](Self::content_digest) was logged within this many
+    /// seconds, and returns the existing entry instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedupe_window_secs: Option<u64>,
+}
+
+impl LogMemoryOptions {
+    /// Returns the hex SHA-256 digest of the entry's action, entity, and details,
+    /// used to recognise duplicate entries.
+    ///
+    /// A `MemoryAPI` with encryption enabled sends an HMAC keyed by the
+    /// division's key instead, so the digest reveals nothing about the
+    /// encrypted details.
+    pub fn content_digest(&self) -> String {
+        hex::encode(Sha256::digest(self.digest_input()))
+    }
+
+    /// The bytes [`content_digest`](Self::content_digest) covers.
+    pub(crate) fn digest_input(&self) -> Vec<u8> {
+        let mut input = Vec::new();
+        input.extend_from_slice(self.action.as_bytes());
+        input.push(0);
+        input.extend_from_slice(self.entity.as_bytes());
+        input.push(0);
+        if let Some(details) = &self.details {
+            input.extend_from_slice(details.as_bytes());
+        }
+        input
+    }
 }
 
 /// Options for querying memory.
@@ -171,11 +1070,547 @@ pub struct MemoryQueryOptions {
     pub until: Option<DateTime<Utc>>,
     pub limit: Option<i32>,
     pub offset: Option<i32>,
+    /// Opaque cursor from a previous [`MemoryPage::next_cursor`].
+    pub cursor: Option<String>,
+    /// Natural-language query matched against entry embeddings. See
+    /// [`MemoryAPI::search_semantic`](crate::MemoryAPI::search_semantic).
+    pub semantic: Option<String>,
+    /// Lowest relevance score, from 0 to 1, a semantic match may have.
+    pub min_score: Option<f64>,
 }
 
-/// Result of chain verification.
+/// A file stored alongside a memory entry.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    pub content_type: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Hex SHA-256 digest of the content.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A memory entry with its relevance to a semantic query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoredMemoryEntry {
+    pub entry: MemoryEntry,
+    /// Similarity to the query, from 0 to 1; higher is more relevant.
+    pub score: f64,
+}
+
+/// File format written by [`MemoryAPI::export`](crate::MemoryAPI::export).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    #[default]
+    Jsonl,
+    /// Comma-separated values with a header row. `tags` are joined with `;`
+    /// and `metadata` is written as a JSON object.
+    Csv,
+}
+
+/// How far [`MemoryAPI::import`](crate::MemoryAPI::import) got.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Number of entries logged, in input order.
+    pub logged: u64,
+    /// Hash of the last entry logged.
+    pub last_hash: Option<String>,
+    /// Why the import stopped early, or `None` if every line was logged.
+    pub error: Option<Error>,
+}
+
+/// A page of memory entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryPage {
+    pub entries: Vec<MemoryEntry>,
+    /// Cursor for the next page; `None` once the scan is complete.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Options for building a memory timeline.
+#[derive(Debug, Clone, Default)]
+pub struct TimelineOptions {
+    /// Which entries to include. The timeline follows cursors across pages.
+    pub filters: MemoryQueryOptions,
+    pub group_by: TimelineGroupBy,
+    /// Bucket width; defaults to hourly.
+    pub interval: Option<StatsInterval>,
+    /// Stop after this many entries. Defaults to 1000.
+    pub max_entries: Option<usize>,
+}
+
+/// How timeline entries are split into lanes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineGroupBy {
+    #[default]
+    Agent,
+    Entity,
+}
+
+/// Rate-limit budget reported by the API's `X-RateLimit-*` headers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitStatus {
+    pub limit: u64,
+    pub remaining: u64,
+    /// When the budget refills, if the API reported it.
+    #[serde(default)]
+    pub reset_at: Option<DateTime<Utc>>,
+    /// When the response carrying these headers was received.
+    pub observed_at: DateTime<Utc>,
+}
+
+/// Memory entries grouped into lanes and time buckets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timeline {
+    pub group_by: TimelineGroupBy,
+    pub interval: StatsInterval,
+    /// Lanes sorted by key.
+    pub lanes: Vec<TimelineLane>,
+}
+
+/// One agent's or entity's entries in a [`Timeline`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineLane {
+    /// The agent or entity name; `"unknown"` for entries without an agent.
+    pub key: String,
+    /// Non-empty buckets in chronological order.
+    pub buckets: Vec<TimelineBucket>,
+}
+
+/// Entries within one time bucket of a [`TimelineLane`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineBucket {
+    pub start: DateTime<Utc>,
+    pub entries: Vec<MemoryEntry>,
+}
+
+impl Timeline {
+    /// Groups `entries` into lanes and buckets.
+    pub fn from_entries(
+        entries: Vec<MemoryEntry>,
+        group_by: TimelineGroupBy,
+        interval: StatsInterval,
+    ) -> Self {
+        let mut lanes: BTreeMap<String, BTreeMap<DateTime<Utc>, Vec<MemoryEntry>>> =
+            BTreeMap::new();
+        for entry in entries {
+            let key = match group_by {
+                TimelineGroupBy::Agent => entry.agent.clone().unwrap_or_else(|| "unknown".into()),
+                TimelineGroupBy::Entity => entry.entity.clone(),
+            };
+            lanes
+                .entry(key)
+                .or_default()
+                .entry(interval.bucket_start(entry.timestamp))
+                .or_default()
+                .push(entry);
+        }
+
+        let lanes = lanes
+            .into_iter()
+            .map(|(key, buckets)| TimelineLane {
+                key,
+                buckets: buckets
+                    .into_iter()
+                    .map(|(start, mut entries)| {
+                        entries.sort_by_key(|e| e.timestamp);
+                        TimelineBucket { start, entries }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Timeline {
+            group_by,
+            interval,
+            lanes,
+        }
+    }
+
+    /// Serializes the timeline as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the timeline as a Mermaid `timeline` diagram, with one section
+    /// per lane and one `action entity` event per entry.
+    pub fn to_mermaid(&self, title: &str) -> String {
+        let period_format = match self.interval {
+            StatsInterval::Hour => "%Y-%m-%d %H:00",
+            StatsInterval::Day | StatsInterval::Week => "%Y-%m-%d",
+        };
+
+        let mut out = format!("timeline\n    title {}\n", mermaid_escape(title));
+        for lane in &self.lanes {
+            out.push_str(&format!("    section {}\n", mermaid_escape(&lane.key)));
+            for bucket in &lane.buckets {
+                let period = bucket.start.format(period_format).to_string();
+                let events: Vec<String> = bucket
+                    .entries
+                    .iter()
+                    .map(|e| mermaid_escape(&format!("{} {}", e.action, e.entity)))
+                    .collect();
+                out.push_str(&format!(
+                    "        {} : {}\n",
+                    mermaid_escape(&period),
+                    events.join(" : ")
+                ));
+            }
+        }
+        out
+    }
+}
+
+fn mermaid_escape(s: &str) -> String {
+    s.replace(':', "#58;").replace(['\n', '\r'], " ")
+}
+
+/// Delivery and acknowledgment state of a broadcast.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastStatus {
+    pub broadcast_id: String,
+    pub recipients: Vec<BroadcastRecipient>,
+}
+
+/// One agent's view of a broadcast.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastRecipient {
+    pub agent_id: String,
+    #[serde(default)]
+    pub delivered_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub acked_at: Option<DateTime<Utc>>,
+}
+
+impl BroadcastStatus {
+    /// Returns true once every recipient has acknowledged the broadcast.
+    pub fn all_acked(&self) -> bool {
+        self.recipients.iter().all(|r| r.acked_at.is_some())
+    }
+
+    /// Returns the recipients that have not acknowledged the broadcast yet.
+    pub fn pending(&self) -> impl Iterator<Item = &BroadcastRecipient> {
+        self.recipients.iter().filter(|r| r.acked_at.is_none())
+    }
+}
+
+/// A change pushed by the API over the realtime event stream.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// An agent registered with the platform.
+    AgentRegistered(Agent),
+    /// A task moved to a new status.
+    TaskStatusChanged(TaskStatusChange),
+    /// An entry was appended to the memory chain. With the `encryption`
+    /// feature, `details` and `metadata` arrive sealed.
+    MemoryAppended(MemoryEntry),
+    /// A broadcast was sent to the fleet.
+    BroadcastReceived(BroadcastMessage),
+    /// An event type this version of the SDK does not know.
+    Unknown {
+        kind: String,
+        data: serde_json::Value,
+    },
+}
+
+impl Event {
+    /// The wire name of the event type, e.g. `"task.status_changed"`.
+    pub fn kind(&self) -> &str {
+        match self {
+            Event::AgentRegistered(_) => "agent.registered",
+            Event::TaskStatusChanged(_) => "task.status_changed",
+            Event::MemoryAppended(_) => "memory.appended",
+            Event::BroadcastReceived(_) => "broadcast.received",
+            Event::Unknown { kind, .. } => kind,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct RawEvent {
+            #[serde(rename = "type")]
+            kind: String,
+            #[serde(default)]
+            data: serde_json::Value,
+        }
+
+        let raw = RawEvent::deserialize(deserializer)?;
+        let data = raw.data;
+        let event = match raw.kind.as_str() {
+            "agent.registered" => serde_json::from_value(data).map(Event::AgentRegistered),
+            "task.status_changed" => serde_json::from_value(data).map(Event::TaskStatusChanged),
+            "memory.appended" => serde_json::from_value(data).map(Event::MemoryAppended),
+            "broadcast.received" => serde_json::from_value(data).map(Event::BroadcastReceived),
+            _ => {
+                return Ok(Event::Unknown {
+                    kind: raw.kind,
+                    data,
+                })
+            }
+        };
+        event.map_err(serde::de::Error::custom)
+    }
+}
+
+/// Payload of [`Event::TaskStatusChanged`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStatusChange {
+    pub task_id: String,
+    pub status: TaskStatus,
+    #[serde(default)]
+    pub previous_status: Option<TaskStatus>,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Progress reported by the agent working on a task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskProgress {
+    pub task_id: String,
+    /// Fraction done, from 0.0 to 1.0, if the agent reports one.
+    #[serde(default)]
+    pub percent: Option<f64>,
+    #[serde(default)]
+    pub message: Option<String>,
+    pub reported_at: DateTime<Utc>,
+}
+
+/// An update received while watching a task.
+#[derive(Debug, Clone)]
+pub enum TaskUpdate {
+    /// The task moved to a new status.
+    Status(TaskStatusChange),
+    /// The assigned agent reported progress.
+    Progress(TaskProgress),
+    /// The task passed an SLA deadline without meeting it.
+    SlaBreach(TaskSlaBreach),
+}
+
+/// An SLA deadline a watched task missed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSlaBreach {
+    pub task_id: String,
+    pub kind: SlaKind,
+    /// The `respond_by` or `complete_by` time that passed.
+    pub deadline: DateTime<Utc>,
+}
+
+/// Payload of [`Event::BroadcastReceived`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastMessage {
+    pub broadcast_id: String,
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub payload: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Options for subscribing to realtime events.
+#[derive(Debug, Clone, Default)]
+pub struct EventOptions {
+    /// Only deliver events from this division. Defaults to the client's
+    /// scope, if any.
+    pub division: Option<String>,
+    /// Only deliver these event types, e.g. `"task.status_changed"`. Empty
+    /// means all types.
+    pub kinds: Vec<String>,
+}
+
+/// Options for memory statistics.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStatsOptions {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub interval: Option<StatsInterval>,
+    pub group_by: Option<MemoryGroupBy>,
+}
+
+/// Changes to an agent's synced state relative to a version.
+///
+/// When pulled with [`MemoryAPI::state_changes`](crate::MemoryAPI::state_changes),
+/// `version` is the server's current state version. When pushed with
+/// [`MemoryAPI::push_state_changes`](crate::MemoryAPI::push_state_changes),
+/// `version` is the base version the changes were computed against.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateDelta {
+    pub version: i64,
+    /// Keys added or changed, with their new values.
+    #[serde(default)]
+    pub changed: HashMap<String, serde_json::Value>,
+    /// Keys removed.
+    #[serde(default)]
+    pub removed: Vec<String>,
+}
+
+impl StateDelta {
+    /// Computes the changes that turn `old` into `new`, based on `version`.
+    pub fn diff(
+        version: i64,
+        old: &HashMap<String, serde_json::Value>,
+        new: &HashMap<String, serde_json::Value>,
+    ) -> Self {
+        let changed = new
+            .iter()
+            .filter(|(key, value)| old.get(*key) != Some(value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let removed = old
+            .keys()
+            .filter(|key| !new.contains_key(*key))
+            .cloned()
+            .collect();
+        Self {
+            version,
+            changed,
+            removed,
+        }
+    }
+
+    /// Returns true if the delta contains no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Merges the changes into a local copy of the state.
+    pub fn apply(&self, state: &mut HashMap<String, serde_json::Value>) {
+        for key in &self.removed {
+            state.remove(key);
+        }
+        for (key, value) in &self.changed {
+            state.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Result of chain verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VerifyChainResult {
     pub valid: bool,
     pub checked: i64,
+    /// Forks and gaps found while verifying; empty when `valid`.
+    #[serde(default)]
+    pub issues: Vec<ChainIssue>,
+}
+
+/// The newest entry of the memory hash chain, as signed by the API.
+///
+/// Written to external storage by
+/// [`MemoryAPI::pin_head`](crate::MemoryAPI::pin_head) and checked later with
+/// [`MemoryAPI::verify_against_pin`](crate::MemoryAPI::verify_against_pin).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainHead {
+    pub hash: String,
+    /// Number of entries in the chain up to and including `hash`.
+    pub count: i64,
+    pub timestamp: DateTime<Utc>,
+    /// The API's hex Ed25519 signature over the head. See
+    /// [`ChainHeadKey`](crate::ChainHeadKey).
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl ChainHead {
+    /// The bytes the API signs: `hash`, `count`, and `timestamp` (RFC 3339 in
+    /// UTC with milliseconds), separated by newlines.
+    pub(crate) fn signed_message(&self) -> String {
+        format!(
+            "{}\n{}\n{}",
+            self.hash,
+            self.count,
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+        )
+    }
+}
+
+/// Outcome of comparing the memory chain with an earlier pin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinVerification {
+    /// The pinned entry is still in the chain and everything after it verifies.
+    Intact,
+    /// The pin or the current head is unsigned or not signed by the key
+    /// given.
+    BadSignature,
+    /// The pinned entry is no longer at its pinned position in the chain.
+    Missing,
+    /// The chain now holds fewer entries than when it was pinned.
+    Shrunk { pinned: i64, current: i64 },
+    /// The chain from the pinned entry onwards fails verification. Only the
+    /// first issue found is reported.
+    Diverged(VerifyChainResult),
+}
+
+impl PinVerification {
+    /// Returns true if the chain is consistent with the pin.
+    pub fn is_intact(&self) -> bool {
+        matches!(self, PinVerification::Intact)
+    }
+}
+
+/// A structural problem in the memory hash chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChainIssue {
+    /// More than one entry claims the same `prev_hash`.
+    Fork {
+        prev_hash: String,
+        hashes: Vec<String>,
+    },
+    /// An entry links to a `prev_hash` that is not in the chain.
+    Gap {
+        hash: String,
+        missing_prev_hash: String,
+    },
+    /// An entry's content does not match its hash.
+    HashMismatch { hash: String, computed: String },
+}
+
+impl ChainIssue {
+    /// Finds forks and gaps in a contiguous run of entries, such as a page
+    /// returned by [`MemoryAPI::query`](crate::MemoryAPI::query).
+    ///
+    /// The oldest entry's link is not checked, since its predecessor is
+    /// expected to fall outside the run.
+    pub fn detect(entries: &[MemoryEntry]) -> Vec<ChainIssue> {
+        let mut children: HashMap<&str, Vec<&MemoryEntry>> = HashMap::new();
+        for entry in entries {
+            if let Some(prev) = entry.prev_hash.as_deref() {
+                children.entry(prev).or_default().push(entry);
+            }
+        }
+
+        let mut issues = Vec::new();
+        let mut forks: Vec<_> = children.iter().filter(|(_, c)| c.len() > 1).collect();
+        forks.sort_by_key(|(prev, _)| *prev);
+        for (prev, claimants) in forks {
+            issues.push(ChainIssue::Fork {
+                prev_hash: prev.to_string(),
+                hashes: claimants.iter().map(|e| e.hash.clone()).collect(),
+            });
+        }
+
+        let hashes: HashSet<&str> = entries.iter().map(|e| e.hash.as_str()).collect();
+        let oldest = entries.iter().min_by_key(|e| e.timestamp).map(|e| &e.hash);
+        for entry in entries {
+            if Some(&entry.hash) == oldest {
+                continue;
+            }
+            if let Some(prev) = entry.prev_hash.as_deref() {
+                if !hashes.contains(prev) {
+                    issues.push(ChainIssue::Gap {
+                        hash: entry.hash.clone(),
+                        missing_prev_hash: prev.to_string(),
+                    });
+                }
+            }
+        }
+        issues
+    }
 }
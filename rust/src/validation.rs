@@ -0,0 +1,215 @@
+use crate::errors::{Error, FieldError, ValidationErrorBody};
+use crate::types::{
+    CreateDivisionOptions, DispatchTaskOptions, LogMemoryOptions, RegisterAgentOptions,
+    ScheduleSpec, TaskPriority, UpdateAgentOptions, UpdateDivisionOptions,
+};
+use std::collections::HashMap;
+
+/// Priorities accepted by the API.
+pub const TASK_PRIORITIES: [&str; 4] = ["urgent", "high", "medium", "low"];
+
+/// Largest serialized `metadata` object the API accepts, in bytes.
+pub const MAX_METADATA_BYTES: usize = 64 * 1024;
+
+const LEVELS: std::ops::RangeInclusive<i32> = 1..=4;
+
+/// Collects field errors so all problems are reported at once.
+#[derive(Default)]
+struct Checks {
+    errors: Vec<FieldError>,
+}
+
+impl Checks {
+    fn fail(&mut self, field: &str, code: &str, message: String) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message,
+            code: Some(code.to_string()),
+        });
+    }
+
+    fn not_blank(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.fail(field, "required", format!("{} must not be empty", field));
+        }
+    }
+
+    fn level(&mut self, field: &str, level: Option<i32>) {
+        if let Some(level) = level {
+            if !LEVELS.contains(&level) {
+                self.fail(
+                    field,
+                    "out_of_range",
+                    format!("{} must be between 1 and 4, got {}", field, level),
+                );
+            }
+        }
+    }
+
+    fn capabilities(&mut self, capabilities: &[String]) {
+        for (i, capability) in capabilities.iter().enumerate() {
+            self.not_blank(&format!("capabilities[{}]", i), capability);
+        }
+    }
+
+    fn metadata(&mut self, metadata: Option<&HashMap<String, serde_json::Value>>) {
+        let size = metadata
+            .and_then(|m| serde_json::to_vec(m).ok())
+            .map_or(0, |bytes| bytes.len());
+        if size > MAX_METADATA_BYTES {
+            self.fail(
+                "metadata",
+                "too_long",
+                format!(
+                    "metadata is {} bytes, the limit is {}",
+                    size, MAX_METADATA_BYTES
+                ),
+            );
+        }
+    }
+
+    fn finish(self, what: &str) -> Result<(), Error> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(Error::Validation(ValidationErrorBody {
+            message: format!("invalid {}", what),
+            errors: self.errors,
+        }))
+    }
+}
+
+impl DispatchTaskOptions {
+    /// Checks the options locally, reporting every problem found as an
+    /// [`Error::Validation`]. Called automatically by
+    /// [`TaskAPI::dispatch`](crate::TaskAPI::dispatch).
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut checks = Checks::default();
+        checks.not_blank("title", &self.title);
+        if let Some(TaskPriority::Unknown(priority)) = &self.priority {
+            checks.fail(
+                "priority",
+                "invalid",
+                format!(
+                    "priority must be one of {}, got {:?}",
+                    TASK_PRIORITIES.join(", "),
+                    priority
+                ),
+            );
+        }
+        checks.level("target_level", self.target_level);
+        if let (Some(respond_by), Some(complete_by)) = (self.respond_by, self.complete_by) {
+            if complete_by < respond_by {
+                checks.fail(
+                    "complete_by",
+                    "invalid",
+                    "complete_by must not be earlier than respond_by".to_string(),
+                );
+            }
+        }
+        for (i, id) in self.depends_on.iter().enumerate() {
+            checks.not_blank(&format!("depends_on[{}]", i), id);
+        }
+        checks.metadata(self.metadata.as_ref());
+        checks.finish("task")
+    }
+}
+
+impl ScheduleSpec {
+    /// Checks the spec locally, reporting every problem found as an
+    /// [`Error::Validation`]. Called automatically by
+    /// [`TaskAPI::schedule`](crate::TaskAPI::schedule).
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut checks = Checks::default();
+        if let ScheduleSpec::Cron { cron, timezone } = self {
+            let fields = cron.split_whitespace().count();
+            if fields != 5 {
+                checks.fail(
+                    "cron",
+                    "invalid",
+                    format!("cron must have 5 fields, got {}", fields),
+                );
+            }
+            if let Some(timezone) = timezone {
+                checks.not_blank("timezone", timezone);
+            }
+        }
+        checks.finish("schedule")
+    }
+}
+
+impl RegisterAgentOptions {
+    /// Checks the options locally, reporting every problem found as an
+    /// [`Error::Validation`]. Called automatically by
+    /// [`AgentAPI::register`](crate::AgentAPI::register).
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut checks = Checks::default();
+        checks.not_blank("name", &self.name);
+        checks.level("level", self.level);
+        checks.capabilities(&self.capabilities);
+        checks.metadata(self.metadata.as_ref());
+        checks.finish("agent")
+    }
+}
+
+/// Checks a capability list sent on its own, as by
+/// [`AgentAPI::update_capabilities`](crate::AgentAPI::update_capabilities).
+pub(crate) fn validate_capabilities(capabilities: &[String]) -> Result<(), Error> {
+    let mut checks = Checks::default();
+    checks.capabilities(capabilities);
+    checks.finish("capabilities")
+}
+
+impl UpdateAgentOptions {
+    /// Checks the options locally, reporting every problem found as an
+    /// [`Error::Validation`]. Called automatically by
+    /// [`AgentAPI::update`](crate::AgentAPI::update).
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut checks = Checks::default();
+        if let Some(name) = &self.name {
+            checks.not_blank("name", name);
+        }
+        checks.level("level", self.level);
+        checks.metadata(self.metadata.as_ref());
+        checks.finish("agent update")
+    }
+}
+
+impl CreateDivisionOptions {
+    /// Checks the options locally, reporting every problem found as an
+    /// [`Error::Validation`]. Called automatically by
+    /// [`DivisionAPI::create`](crate::DivisionAPI::create).
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut checks = Checks::default();
+        checks.not_blank("name", &self.name);
+        checks.metadata(self.metadata.as_ref());
+        checks.finish("division")
+    }
+}
+
+impl UpdateDivisionOptions {
+    /// Checks the options locally, reporting every problem found as an
+    /// [`Error::Validation`]. Called automatically by
+    /// [`DivisionAPI::update`](crate::DivisionAPI::update).
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut checks = Checks::default();
+        if let Some(name) = &self.name {
+            checks.not_blank("name", name);
+        }
+        checks.metadata(self.metadata.as_ref());
+        checks.finish("division update")
+    }
+}
+
+impl LogMemoryOptions {
+    /// Checks the options locally, reporting every problem found as an
+    /// [`Error::Validation`]. Called automatically by
+    /// [`MemoryAPI::log`](crate::MemoryAPI::log).
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut checks = Checks::default();
+        checks.not_blank("action", &self.action);
+        checks.not_blank("entity", &self.entity);
+        checks.metadata(self.metadata.as_ref());
+        checks.finish("memory entry")
+    }
+}
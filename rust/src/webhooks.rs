@@ -0,0 +1,135 @@
+//! Verification and parsing of webhooks sent by BlackRoad.
+//!
+//! Each delivery carries an `X-BlackRoad-Timestamp` header with the Unix time
+//! it was sent and an `X-BlackRoad-Signature` header of the form
+//! `sha256=<hex>`: an HMAC-SHA256, keyed with the endpoint's signing secret,
+//! of `"{timestamp}.{body}"`. Deliveries older than five minutes are rejected
+//! so a captured request cannot be replayed.
+//!
+//! ```rust,no_run
+//! use blackroad::webhooks::{self, WebhookEvent};
+//! # fn handle(secret: &[u8], headers: &blackroad::webhooks::HeaderMap, body: &[u8]) -> Result<(), blackroad::Error> {
+//! match webhooks::parse_event(secret, headers, body)? {
+//!     WebhookEvent::TaskCompleted(task) => println!("{} finished", task.id),
+//!     WebhookEvent::AgentOffline(agent) => println!("{} went offline", agent.id),
+//!     other => println!("ignoring {}", other.kind()),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::Error;
+use crate::types::{AgentPresence, MemoryEntry, Task};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+pub use reqwest::header::HeaderMap;
+
+/// Header carrying the `sha256=<hex>` signature.
+pub const SIGNATURE_HEADER: &str = "x-blackroad-signature";
+/// Header carrying the Unix time the delivery was signed.
+pub const TIMESTAMP_HEADER: &str = "x-blackroad-timestamp";
+/// How far a delivery's timestamp may be from the current time, in seconds.
+pub const TOLERANCE_SECS: i64 = 300;
+
+/// An event delivered to a webhook endpoint.
+#[derive(Debug, Clone)]
+pub enum WebhookEvent {
+    /// A task completed.
+    TaskCompleted(Task),
+    /// An agent stopped sending heartbeats.
+    AgentOffline(AgentPresence),
+    /// An entry was logged to the memory chain.
+    MemoryLogged(MemoryEntry),
+    /// An event type this version of the SDK does not know.
+    Unknown {
+        kind: String,
+        data: serde_json::Value,
+    },
+}
+
+impl WebhookEvent {
+    /// The wire name of the event type, e.g. `"task.completed"`.
+    pub fn kind(&self) -> &str {
+        match self {
+            WebhookEvent::TaskCompleted(_) => "task.completed",
+            WebhookEvent::AgentOffline(_) => "agent.offline",
+            WebhookEvent::MemoryLogged(_) => "memory.logged",
+            WebhookEvent::Unknown { kind, .. } => kind,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WebhookEvent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct RawEvent {
+            #[serde(rename = "type")]
+            kind: String,
+            #[serde(default)]
+            data: serde_json::Value,
+        }
+
+        let raw = RawEvent::deserialize(deserializer)?;
+        let data = raw.data;
+        let event = match raw.kind.as_str() {
+            "task.completed" => serde_json::from_value(data).map(WebhookEvent::TaskCompleted),
+            "agent.offline" => serde_json::from_value(data).map(WebhookEvent::AgentOffline),
+            "memory.logged" => serde_json::from_value(data).map(WebhookEvent::MemoryLogged),
+            _ => {
+                return Ok(WebhookEvent::Unknown {
+                    kind: raw.kind,
+                    data,
+                })
+            }
+        };
+        event.map_err(serde::de::Error::custom)
+    }
+}
+
+/// Checks that `body` was signed by BlackRoad with `secret` within the last
+/// [`TOLERANCE_SECS`]. Pass the raw request body, before any parsing.
+pub fn verify_signature(secret: &[u8], headers: &HeaderMap, body: &[u8]) -> Result<(), Error> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| invalid(&format!("missing {} header", name)))
+    };
+    let timestamp = header(TIMESTAMP_HEADER)?;
+    let signature = header(SIGNATURE_HEADER)?;
+
+    let sent_at: i64 = timestamp
+        .trim()
+        .parse()
+        .map_err(|_| invalid("malformed timestamp"))?;
+    // `abs_diff` cannot overflow, however far off a forged timestamp is.
+    if Utc::now().timestamp().abs_diff(sent_at) > TOLERANCE_SECS.unsigned_abs() {
+        return Err(invalid("timestamp outside tolerance"));
+    }
+
+    let signature = signature
+        .trim()
+        .strip_prefix("sha256=")
+        .and_then(|hex| hex::decode(hex).ok())
+        .ok_or_else(|| invalid("malformed signature"))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.trim().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&signature)
+        .map_err(|_| invalid("signature mismatch"))
+}
+
+/// Verifies the delivery with [`verify_signature`] and parses its event.
+pub fn parse_event(secret: &[u8], headers: &HeaderMap, body: &[u8]) -> Result<WebhookEvent, Error> {
+    verify_signature(secret, headers, body)?;
+    Ok(serde_json::from_slice(body)?)
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Authentication(format!("invalid webhook signature: {}", reason))
+}
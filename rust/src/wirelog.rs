@@ -0,0 +1,147 @@
+use reqwest::header::{HeaderMap, AUTHORIZATION, PROXY_AUTHORIZATION};
+use reqwest::{Method, Request, StatusCode, Url};
+use serde_json::Value;
+
+const REDACTED: &str = "[REDACTED]";
+const TARGET: &str = "blackroad::wire";
+
+/// Settings for logging every request and response, for debugging what
+/// actually goes over the wire.
+///
+/// Method, URL, status, and headers are logged as `debug` events and bodies
+/// as `trace` events, all with the `blackroad::wire` target. The
+/// `Authorization` header is always redacted.
+#[derive(Debug, Clone)]
+pub struct WireLogConfig {
+    /// Log request and response bodies. Defaults to `true`.
+    pub log_bodies: bool,
+    /// JSON object keys whose values are replaced with `"[REDACTED]"`
+    /// wherever they appear in a body, compared case-insensitively.
+    /// Defaults to `access_token`, `api_key`, `client_secret`, `password`,
+    /// `refresh_token`, `secret`, and `token`.
+    pub redact_fields: Vec<String>,
+}
+
+impl Default for WireLogConfig {
+    fn default() -> Self {
+        Self {
+            log_bodies: true,
+            redact_fields: [
+                "access_token",
+                "api_key",
+                "client_secret",
+                "password",
+                "refresh_token",
+                "secret",
+                "token",
+            ]
+            .iter()
+            .map(|field| field.to_string())
+            .collect(),
+        }
+    }
+}
+
+/// Writes requests and responses to `tracing` with secrets redacted.
+#[derive(Debug)]
+pub(crate) struct WireLogger {
+    log_bodies: bool,
+    redact_fields: Vec<String>,
+}
+
+impl WireLogger {
+    pub(crate) fn new(config: WireLogConfig) -> Self {
+        Self {
+            log_bodies: config.log_bodies,
+            redact_fields: config
+                .redact_fields
+                .iter()
+                .map(|field| field.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Logs a request as it is about to be sent.
+    pub(crate) fn request(&self, request: &Request) {
+        tracing::debug!(
+            target: TARGET,
+            method = %request.method(),
+            url = %request.url(),
+            headers = %headers(request.headers()),
+            "sending BlackRoad request"
+        );
+        if !self.log_bodies {
+            return;
+        }
+        match request.body().map(|body| body.as_bytes()) {
+            Some(Some(body)) => {
+                tracing::trace!(target: TARGET, body = %self.body(body), "request body")
+            }
+            Some(None) => tracing::trace!(target: TARGET, body = "<stream>", "request body"),
+            None => {}
+        }
+    }
+
+    /// Logs the status and headers of a response.
+    pub(crate) fn response(&self, method: &Method, url: &Url, status: StatusCode, map: &HeaderMap) {
+        tracing::debug!(
+            target: TARGET,
+            method = %method,
+            url = %url,
+            status = status.as_u16(),
+            headers = %headers(map),
+            "received BlackRoad response"
+        );
+    }
+
+    /// Logs a response body once it has been read.
+    pub(crate) fn response_body(&self, body: &[u8]) {
+        if self.log_bodies {
+            tracing::trace!(target: TARGET, body = %self.body(body), "response body");
+        }
+    }
+
+    /// Renders a body for the log: JSON with the configured fields redacted,
+    /// other UTF-8 text as is, and binary bodies by size only.
+    fn body(&self, body: &[u8]) -> String {
+        if let Ok(mut value) = serde_json::from_slice::<Value>(body) {
+            self.redact(&mut value);
+            return value.to_string();
+        }
+        match std::str::from_utf8(body) {
+            Ok(text) => text.to_string(),
+            Err(_) => format!("<{} bytes>", body.len()),
+        }
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.redact_fields.contains(&key.to_ascii_lowercase()) {
+                        *value = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            _ => {}
+        }
+    }
+}
+
+/// Formats headers as `name: value` pairs, hiding credentials.
+fn headers(map: &HeaderMap) -> String {
+    map.iter()
+        .map(|(name, value)| {
+            let value = if name == AUTHORIZATION || name == PROXY_AUTHORIZATION {
+                REDACTED
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
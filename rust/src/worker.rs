@@ -0,0 +1,342 @@
+//! A runtime for agents that process tasks.
+//!
+//! A [`Worker`] claims tasks for its agent, runs an async handler on each, and
+//! marks the task completed or failed with the handler's outcome. Heartbeats
+//! reporting the worker's load are sent for as long as it runs.
+//!
+//! ```rust,no_run
+//! use blackroad::worker::Worker;
+//! use blackroad::{BlackRoadClient, ClientConfig, Task};
+//!
+//! # async fn run() -> Result<(), blackroad::Error> {
+//! let client = BlackRoadClient::new(ClientConfig::default())?;
+//! Worker::builder()
+//!     .client(client)
+//!     .agent("agent-id")
+//!     .concurrency(4)
+//!     .handler(|task: Task| async move {
+//!         println!("working on {}", task.title);
+//!         Ok::<_, std::io::Error>(Some("done".to_string()))
+//!     })
+//!     .build()?
+//!     .run()
+//!     .await
+//! # }
+//! ```
+
+use crate::agents::AgentSession;
+use crate::client::{backoff, BlackRoadClient};
+use crate::errors::{Error, ValidationErrorBody};
+use crate::types::{AgentStatus, ClaimOptions, Task};
+use futures::future::{self, BoxFuture, FutureExt};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// Longest a claim waits for work before shutdown is checked again.
+const CLAIM_WAIT: Duration = Duration::from_secs(10);
+/// Pause after a claim comes back empty, so an API that answers claims
+/// early is not polled in a tight loop.
+const IDLE_DELAY: Duration = Duration::from_secs(1);
+/// How often the agent's status is checked, so a paused agent stops
+/// claiming within this long and resumes within this long.
+const STATUS_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+type Handler =
+    Arc<dyn Fn(Task) -> BoxFuture<'static, Result<Option<String>, String>> + Send + Sync>;
+
+/// Configures a [`Worker`].
+pub struct WorkerBuilder {
+    client: Option<BlackRoadClient>,
+    agent_id: Option<String>,
+    handler: Option<Handler>,
+    claim: ClaimOptions,
+    concurrency: usize,
+    heartbeat_interval: Duration,
+}
+
+impl WorkerBuilder {
+    /// Sets the client used to claim and report tasks. Required.
+    pub fn client(mut self, client: BlackRoadClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Sets the agent that claims tasks and sends heartbeats. Required.
+    pub fn agent(mut self, agent_id: impl Into<String>) -> Self {
+        self.agent_id = Some(agent_id.into());
+        self
+    }
+
+    /// Sets the function that processes each claimed task. Required.
+    ///
+    /// `Ok` completes the task with the returned result; `Err` fails it with
+    /// the error's message. A panicking handler also fails the task.
+    pub fn handler<F, Fut, E>(mut self, handler: F) -> Self
+    where
+        F: Fn(Task) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Option<String>, E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        self.handler = Some(Arc::new(move |task| {
+            handler(task)
+                .map(|result| result.map_err(|e| e.to_string()))
+                .boxed()
+        }));
+        self
+    }
+
+    /// Restricts which tasks are claimed. With `lease_secs` set, the worker
+    /// renews each task's lease while its handler runs.
+    pub fn claim_options(mut self, opts: ClaimOptions) -> Self {
+        self.claim = opts;
+        self
+    }
+
+    /// Sets how many tasks may run at once. Defaults to 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets the time between heartbeats. Defaults to 30 seconds.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Builds the worker, failing if the client, agent, or handler is missing.
+    pub fn build(self) -> Result<Worker, Error> {
+        let missing = |field: &str| {
+            Error::Validation(ValidationErrorBody::new(format!(
+                "worker {} is required",
+                field
+            )))
+        };
+        Ok(Worker {
+            client: self.client.ok_or_else(|| missing("client"))?,
+            agent_id: self.agent_id.ok_or_else(|| missing("agent"))?,
+            handler: self.handler.ok_or_else(|| missing("handler"))?,
+            claim: self.claim,
+            concurrency: self.concurrency,
+            heartbeat_interval: self.heartbeat_interval,
+        })
+    }
+}
+
+impl fmt::Debug for WorkerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerBuilder")
+            .field("agent_id", &self.agent_id)
+            .field("claim", &self.claim)
+            .field("concurrency", &self.concurrency)
+            .field("heartbeat_interval", &self.heartbeat_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Claims and processes tasks for one agent. Create one with
+/// [`Worker::builder`].
+#[derive(Clone)]
+pub struct Worker {
+    client: BlackRoadClient,
+    agent_id: String,
+    handler: Handler,
+    claim: ClaimOptions,
+    concurrency: usize,
+    heartbeat_interval: Duration,
+}
+
+impl Worker {
+    pub fn builder() -> WorkerBuilder {
+        WorkerBuilder {
+            client: None,
+            agent_id: None,
+            handler: None,
+            claim: ClaimOptions::default(),
+            concurrency: 1,
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+        }
+    }
+
+    /// Processes tasks until an error that retrying cannot fix, such as
+    /// rejected credentials.
+    pub async fn run(self) -> Result<(), Error> {
+        self.run_until(future::pending()).await
+    }
+
+    /// Processes tasks until `shutdown` resolves, then stops claiming, waits
+    /// for tasks already running to be reported, and sends a last heartbeat
+    /// showing the agent idle.
+    pub async fn run_until(self, shutdown: impl Future<Output = ()>) -> Result<(), Error> {
+        let slots = Arc::new(Semaphore::new(self.concurrency));
+        let session = Arc::new(
+            self.client
+                .agents()
+                .start_session(&self.agent_id, self.heartbeat_interval),
+        );
+        session.set_load(0.0);
+
+        let result = self.claim_loop(&slots, &session, shutdown).await;
+        let _ = slots.acquire_many(self.concurrency as u32).await;
+        // Tasks let go of the session before their slot, so it is no longer
+        // shared, and no heartbeat can follow the final one.
+        if let Some(session) = Arc::into_inner(session) {
+            session.close().await;
+        }
+        let agent_id = &self.agent_id;
+        if let Err(_e) = self.client.agents().heartbeat(agent_id, Some(0.0)).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(agent_id = %agent_id, error = %_e, "failed to send final heartbeat");
+        }
+        result
+    }
+
+    /// Claims tasks while slots are free and the agent is not paused.
+    /// Shutdown is only observed between claims, since dropping a claim in
+    /// flight could strand a task that the API has already assigned to this
+    /// agent.
+    async fn claim_loop(
+        &self,
+        slots: &Arc<Semaphore>,
+        session: &Arc<AgentSession>,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), Error> {
+        let tasks = self.client.tasks();
+        let mut failures = 0;
+        let mut paused = false;
+        let mut status_checked: Option<crate::rt::Instant> = None;
+        tokio::pin!(shutdown);
+        loop {
+            if (&mut shutdown).now_or_never().is_some() {
+                return Ok(());
+            }
+            if status_checked.is_none_or(|at| at.elapsed() >= STATUS_CHECK_INTERVAL) {
+                match self.client.agents().get(&self.agent_id).await {
+                    Ok(agent) => {
+                        paused = agent.status == AgentStatus::Paused;
+                        status_checked = Some(crate::rt::Instant::now());
+                    }
+                    // Keep the last known state; claims report outages.
+                    Err(e) if e.is_transient() => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            if paused {
+                tokio::select! {
+                    _ = &mut shutdown => return Ok(()),
+                    _ = crate::rt::sleep(STATUS_CHECK_INTERVAL) => {}
+                }
+                continue;
+            }
+            let permit = tokio::select! {
+                _ = &mut shutdown => return Ok(()),
+                permit = Arc::clone(slots).acquire_owned() => {
+                    permit.expect("worker semaphore is never closed")
+                }
+            };
+            let claimed = tasks
+                .wait_for_task(&self.agent_id, Some(self.claim.clone()), CLAIM_WAIT)
+                .await;
+            match claimed {
+                Ok(Some(task)) => {
+                    failures = 0;
+                    let worker = self.clone();
+                    let slots = Arc::clone(slots);
+                    let session = Arc::clone(session);
+                    session.set_load(worker.load(&slots, 0));
+                    tokio::spawn(async move {
+                        worker.process(task).await;
+                        session.set_load(worker.load(&slots, 1));
+                        drop(session);
+                        drop(permit);
+                    });
+                }
+                Ok(None) => {
+                    failures = 0;
+                    drop(permit);
+                    tokio::select! {
+                        _ = &mut shutdown => return Ok(()),
+                        _ = crate::rt::sleep(IDLE_DELAY) => {}
+                    }
+                }
+                Err(e) if e.is_transient() => {
+                    drop(permit);
+                    tokio::select! {
+                        _ = &mut shutdown => return Ok(()),
+                        _ = crate::rt::sleep(backoff(failures)) => {}
+                    }
+                    failures += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Runs the handler on `task` and reports the outcome. Tasks claimed
+    /// with a lease have it renewed at half its length until the handler
+    /// returns. If a renewal finds the lease lost, the task may already be
+    /// running elsewhere, so the handler is aborted and nothing is reported.
+    async fn process(&self, task: Task) {
+        let task_id = task.id.clone();
+        let tasks = self.client.tasks();
+        let mut handler = tokio::spawn((self.handler)(task));
+        let outcome = match self.claim.lease_secs {
+            Some(secs) => {
+                let every = Duration::from_secs(secs).max(Duration::from_secs(2)) / 2;
+                loop {
+                    tokio::select! {
+                        outcome = &mut handler => break outcome,
+                        _ = crate::rt::sleep(every) => {
+                            match tasks.renew_lease(&task_id).await {
+                                Ok(_) => {}
+                                Err(Error::Conflict(_)) => {
+                                    handler.abort();
+                                    #[cfg(feature = "tracing")]
+                                    tracing::warn!(task_id = %task_id, "task lease lost; abandoning task");
+                                    return;
+                                }
+                                Err(_e) => {
+                                    #[cfg(feature = "tracing")]
+                                    tracing::warn!(task_id = %task_id, error = %_e, "failed to renew task lease");
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            None => handler.await,
+        };
+        let reported = match outcome {
+            Ok(Ok(result)) => tasks.complete(&task_id, result.as_deref()).await,
+            Ok(Err(reason)) => tasks.fail(&task_id, Some(&reason)).await,
+            Err(_) => tasks.fail(&task_id, Some("handler panicked")).await,
+        };
+        if let Err(_e) = reported {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(task_id = %task_id, error = %_e, "failed to report task outcome");
+        }
+    }
+
+    /// The fraction of task slots in use, not counting `releasing` slots
+    /// that are about to be freed.
+    fn load(&self, slots: &Semaphore, releasing: usize) -> f64 {
+        let busy = (self.concurrency - slots.available_permits().min(self.concurrency))
+            .saturating_sub(releasing);
+        busy as f64 / self.concurrency as f64
+    }
+}
+
+impl fmt::Debug for Worker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker")
+            .field("agent_id", &self.agent_id)
+            .field("claim", &self.claim)
+            .field("concurrency", &self.concurrency)
+            .field("heartbeat_interval", &self.heartbeat_interval)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,134 @@
+use blackroad::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Reads one HTTP request from `stream`, headers and body.
+async fn read_request(stream: &mut TcpStream) {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        let n = stream.read(&mut buf).await.unwrap();
+        if n == 0 {
+            return;
+        }
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length = text[..end]
+                .lines()
+                .find_map(|line| {
+                    line.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|v| v.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            if request.len() >= end + 4 + length {
+                return;
+            }
+        }
+    }
+}
+
+/// Starts a token endpoint that answers one request per entry of `bodies`
+/// with that JSON body, or never answers for `None`. Returns its URL.
+async fn token_server(bodies: Vec<Option<&'static str>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/token", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut hung = Vec::new();
+        for body in bodies {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            match body {
+                Some(body) => {
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                }
+                None => hung.push(stream),
+            }
+        }
+        std::future::pending::<()>().await;
+    });
+    url
+}
+
+#[tokio::test]
+async fn a_hung_token_endpoint_fails_within_the_timeout() {
+    let url = token_server(vec![None, None]).await;
+    let provider = ClientCredentials::new(url, "id", "secret").timeout(Duration::from_millis(200));
+
+    let started = Instant::now();
+    let (first, second) = tokio::join!(provider.credentials(), provider.credentials());
+    assert!(matches!(first, Err(Error::Connection(_))), "{:?}", first);
+    assert!(matches!(second, Err(Error::Connection(_))), "{:?}", second);
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn keeps_a_token_with_an_unrepresentable_lifetime() {
+    let url = token_server(vec![Some(
+        r#"{"access_token":"tok-1","expires_in":18446744073709551615}"#,
+    )])
+    .await;
+    let provider = ClientCredentials::new(url, "id", "secret");
+
+    assert_eq!(provider.credentials().await.unwrap(), "tok-1");
+    assert_eq!(provider.credentials().await.unwrap(), "tok-1");
+}
+
+#[tokio::test]
+async fn refreshes_a_token_before_it_expires() {
+    let url = token_server(vec![
+        Some(r#"{"access_token":"tok-1","expires_in":1}"#),
+        Some(r#"{"access_token":"tok-2","expires_in":3600}"#),
+    ])
+    .await;
+    let provider =
+        ClientCredentials::new(url, "id", "secret").refresh_before(Duration::from_secs(5));
+
+    assert_eq!(provider.credentials().await.unwrap(), "tok-1");
+    assert_eq!(provider.credentials().await.unwrap(), "tok-2");
+    assert_eq!(provider.credentials().await.unwrap(), "tok-2");
+}
+
+#[tokio::test]
+async fn replaces_a_token_the_api_rejects() {
+    let url = token_server(vec![
+        Some(r#"{"access_token":"tok-1","expires_in":3600}"#),
+        Some(r#"{"access_token":"tok-2","expires_in":3600}"#),
+    ])
+    .await;
+    let mock = MockTransport::new();
+    mock.respond(
+        Method::GET,
+        "/agents",
+        401,
+        serde_json::json!({ "error": "revoked" }),
+    )
+    .respond(
+        Method::GET,
+        "/agents",
+        200,
+        serde_json::json!({ "agents": [] }),
+    );
+    let client = BlackRoadClient::new(ClientConfig {
+        auth_provider: Some(Arc::new(ClientCredentials::new(url, "id", "secret"))),
+        transport: Some(Arc::new(mock.clone())),
+        ..Default::default()
+    })
+    .unwrap();
+
+    client.agents().list(None).await.unwrap();
+    let tokens: Vec<_> = mock
+        .requests()
+        .iter()
+        .map(|r| r.headers["authorization"].to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(tokens, ["Bearer tok-1", "Bearer tok-2"]);
+}
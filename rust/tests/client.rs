@@ -0,0 +1,408 @@
+use blackroad::*;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+fn client(mock: &MockTransport, config: ClientConfig) -> BlackRoadClient {
+    BlackRoadClient::new(ClientConfig {
+        api_key: Some("test-key".to_string()),
+        transport: Some(Arc::new(mock.clone())),
+        ..config
+    })
+    .unwrap()
+}
+
+fn task(id: &str) -> Value {
+    json!({
+        "id": id,
+        "title": "Scan fleet",
+        "status": "pending",
+        "priority": "medium",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+    })
+}
+
+fn agent(id: &str) -> Value {
+    json!({
+        "id": id,
+        "name": id,
+        "type": "worker",
+        "division": "ops",
+        "level": 1,
+        "status": "active",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+    })
+}
+
+fn dispatch_opts() -> DispatchTaskOptions {
+    DispatchTaskOptions {
+        title: "Scan fleet".to_string(),
+        ..Default::default()
+    }
+}
+
+fn header(request: &RecordedRequest, name: &str) -> Option<String> {
+    request
+        .headers
+        .get(name)
+        .map(|v| v.to_str().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn retries_server_errors_with_the_same_idempotency_key() {
+    let mock = MockTransport::new();
+    mock.respond_raw(Method::POST, "/tasks", 503, &[("retry-after", "0")], "{}")
+        .respond(Method::POST, "/tasks", 201, task("t1"));
+    let client = client(&mock, ClientConfig::default());
+
+    let task = client.tasks().dispatch(dispatch_opts()).await.unwrap();
+    assert_eq!(task.id, "t1");
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    let key = header(&requests[0], IDEMPOTENCY_KEY).unwrap();
+    assert_eq!(key.len(), 32);
+    assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(header(&requests[1], IDEMPOTENCY_KEY), Some(key));
+}
+
+#[tokio::test]
+async fn uses_a_fresh_idempotency_key_per_call() {
+    let mock = MockTransport::new();
+    mock.respond(Method::POST, "/tasks", 201, task("t1"))
+        .respond(Method::POST, "/tasks", 201, task("t2"));
+    let client = client(&mock, ClientConfig::default());
+
+    client.tasks().dispatch(dispatch_opts()).await.unwrap();
+    client.tasks().dispatch(dispatch_opts()).await.unwrap();
+
+    let requests = mock.requests();
+    assert_ne!(
+        header(&requests[0], IDEMPOTENCY_KEY),
+        header(&requests[1], IDEMPOTENCY_KEY)
+    );
+}
+
+#[tokio::test]
+async fn sends_a_caller_chosen_idempotency_key() {
+    let mock = MockTransport::new();
+    mock.respond(Method::POST, "/tasks", 201, task("t1"));
+    let client = client(&mock, ClientConfig::default());
+
+    client
+        .with_options(RequestOptions {
+            idempotency_key: Some("message-42".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .tasks()
+        .dispatch(dispatch_opts())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        header(&mock.requests()[0], IDEMPOTENCY_KEY).as_deref(),
+        Some("message-42")
+    );
+}
+
+#[tokio::test]
+async fn does_not_retry_writes_without_an_idempotency_key() {
+    let mock = MockTransport::new();
+    mock.respond_raw(Method::POST, "/tasks", 503, &[("retry-after", "0")], "{}")
+        .respond(Method::POST, "/tasks", 201, task("t1"));
+    let client = client(
+        &mock,
+        ClientConfig {
+            idempotency_keys: Some(false),
+            ..Default::default()
+        },
+    );
+
+    assert!(client.tasks().dispatch(dispatch_opts()).await.is_err());
+    assert_eq!(mock.calls(Method::POST, "/tasks"), 1);
+    assert_eq!(header(&mock.requests()[0], IDEMPOTENCY_KEY), None);
+}
+
+#[tokio::test]
+async fn sends_if_match_only_on_writes_and_never_repeats_them() {
+    let mock = MockTransport::new();
+    mock.respond(Method::GET, "/tasks/t1", 200, task("t1"))
+        .respond_raw(Method::PUT, "/tasks/t1", 503, &[("retry-after", "0")], "{}")
+        .respond(Method::PUT, "/tasks/t1", 200, task("t1"));
+    let client = client(&mock, ClientConfig::default());
+    let tasks = client.tasks().if_match("v1").unwrap();
+
+    tasks.get("t1").await.unwrap();
+    assert!(tasks.complete("t1", None).await.is_err());
+    assert_eq!(mock.calls(Method::PUT, "/tasks/t1"), 1);
+    let requests = mock.requests();
+    assert_eq!(header(&requests[0], "if-match"), None);
+    assert_eq!(header(&requests[1], "if-match").as_deref(), Some("v1"));
+    let body: Value = requests[1].json().unwrap();
+    assert_eq!(body["status"], "completed");
+}
+
+#[tokio::test]
+async fn stops_after_max_retries() {
+    let mock = MockTransport::new();
+    for _ in 0..3 {
+        mock.respond_raw(Method::GET, "/agents", 503, &[("retry-after", "0")], "{}");
+    }
+    let client = client(
+        &mock,
+        ClientConfig {
+            max_retries: Some(2),
+            ..Default::default()
+        },
+    );
+
+    assert!(client.agents().list(None).await.is_err());
+    assert_eq!(mock.calls(Method::GET, "/agents"), 2);
+}
+
+#[tokio::test]
+async fn gives_up_on_a_retry_after_beyond_the_budget() {
+    let mock = MockTransport::new();
+    mock.respond_raw(
+        Method::GET,
+        "/agents",
+        429,
+        &[("retry-after", "18446744073709551615")],
+        "{}",
+    );
+    let client = client(&mock, ClientConfig::default());
+
+    let error = client.agents().list(None).await.unwrap_err();
+    assert!(matches!(error, Error::RateLimit { .. }), "{:?}", error);
+    assert_eq!(mock.calls(Method::GET, "/agents"), 1);
+}
+
+#[tokio::test]
+async fn retries_connection_failures() {
+    let mock = MockTransport::new();
+    mock.fail(Method::GET, "/agents", "connection reset")
+        .respond(
+            Method::GET,
+            "/agents",
+            200,
+            json!({ "agents": [agent("a1")] }),
+        );
+    let client = client(&mock, ClientConfig::default());
+
+    let agents = client.agents().list(None).await.unwrap();
+    assert_eq!(agents.len(), 1);
+    assert!(mock.is_exhausted());
+}
+
+/// Holds the first request for `delay`, then answers every request from
+/// `mock`.
+#[derive(Debug)]
+struct SlowFirst {
+    mock: MockTransport,
+    delay: Duration,
+    calls: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl Transport for SlowFirst {
+    async fn execute(&self, request: reqwest::Request) -> Result<reqwest::Response, Error> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+            tokio::time::sleep(self.delay).await;
+        }
+        self.mock.execute(request).await
+    }
+}
+
+fn hedged_client(transport: &Arc<SlowFirst>) -> BlackRoadClient {
+    BlackRoadClient::new(ClientConfig {
+        api_key: Some("test-key".to_string()),
+        hedge_delay_ms: Some(50),
+        transport: Some(Arc::clone(transport) as Arc<dyn Transport>),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn hedges_a_slow_read() {
+    let mock = MockTransport::new();
+    mock.respond(
+        Method::GET,
+        "/agents",
+        200,
+        json!({ "agents": [agent("a1")] }),
+    );
+    let transport = Arc::new(SlowFirst {
+        mock,
+        delay: Duration::from_secs(30),
+        calls: AtomicUsize::new(0),
+    });
+
+    let started = std::time::Instant::now();
+    let agents = hedged_client(&transport).agents().list(None).await.unwrap();
+    assert_eq!(agents.len(), 1);
+    assert_eq!(transport.calls.load(Ordering::SeqCst), 2);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn does_not_hedge_fast_reads_or_writes() {
+    let mock = MockTransport::new();
+    mock.respond(
+        Method::GET,
+        "/agents",
+        200,
+        json!({ "agents": [agent("a1")] }),
+    )
+    .respond(Method::POST, "/tasks", 201, task("t1"));
+    let transport = Arc::new(SlowFirst {
+        mock: mock.clone(),
+        delay: Duration::ZERO,
+        calls: AtomicUsize::new(0),
+    });
+    let client = hedged_client(&transport);
+
+    client.agents().list(None).await.unwrap();
+    assert_eq!(transport.calls.load(Ordering::SeqCst), 1);
+
+    let slow = Arc::new(SlowFirst {
+        mock,
+        delay: Duration::from_millis(200),
+        calls: AtomicUsize::new(0),
+    });
+    hedged_client(&slow)
+        .tasks()
+        .dispatch(dispatch_opts())
+        .await
+        .unwrap();
+    assert_eq!(slow.calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn rejects_a_request_queue_without_slots() {
+    let config = ClientConfig {
+        api_key: Some("test-key".to_string()),
+        request_queue: Some(RequestQueueConfig {
+            max_concurrent: 0,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert!(matches!(
+        BlackRoadClient::new(config),
+        Err(Error::Validation(_))
+    ));
+}
+
+#[test]
+fn rejects_a_circuit_breaker_that_cannot_close() {
+    for breaker in [
+        CircuitBreakerConfig {
+            failure_threshold: 0,
+            ..Default::default()
+        },
+        CircuitBreakerConfig {
+            open_secs: u64::MAX,
+            ..Default::default()
+        },
+    ] {
+        let config = ClientConfig {
+            api_key: Some("test-key".to_string()),
+            circuit_breaker: Some(breaker),
+            ..Default::default()
+        };
+        assert!(matches!(
+            BlackRoadClient::new(config),
+            Err(Error::Validation(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn probes_an_open_circuit_once_per_open_period() {
+    let mock = MockTransport::new();
+    mock.respond(Method::GET, "/agents", 503, json!({}))
+        .respond(Method::GET, "/agents", 503, json!({}))
+        .respond(Method::GET, "/agents", 503, json!({}))
+        .respond(
+            Method::GET,
+            "/agents",
+            200,
+            json!({ "agents": [agent("a1")] }),
+        );
+    let client = client(
+        &mock,
+        ClientConfig {
+            max_retries: Some(1),
+            circuit_breaker: Some(CircuitBreakerConfig {
+                failure_threshold: 2,
+                open_secs: 1,
+            }),
+            ..Default::default()
+        },
+    );
+    let open = |result: Result<Vec<Agent>, Error>| matches!(result, Err(Error::CircuitOpen { .. }));
+
+    assert!(client.agents().list(None).await.is_err());
+    assert!(client.agents().list(None).await.is_err());
+    assert!(open(client.agents().list(None).await));
+    assert_eq!(mock.requests().len(), 2);
+
+    // The probe fails, so the circuit opens again without further requests.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(!open(client.agents().list(None).await));
+    assert!(open(client.agents().list(None).await));
+    assert_eq!(mock.requests().len(), 3);
+
+    // The next probe succeeds and closes it.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(client.agents().list(None).await.unwrap().len(), 1);
+    assert!(mock.is_exhausted());
+}
+
+#[tokio::test]
+async fn revalidates_cached_reads_per_scope() {
+    let mock = MockTransport::new();
+    let agents = json!({ "agents": [agent("a1")] }).to_string();
+    mock.respond_raw(
+        Method::GET,
+        "/agents",
+        200,
+        &[("etag", "\"v1\"")],
+        agents.clone(),
+    )
+    .respond_raw(Method::GET, "/agents", 304, &[("etag", "\"v1\"")], "")
+    .respond_raw(Method::GET, "/agents", 200, &[("etag", "\"v1\"")], agents);
+    let client = client(
+        &mock,
+        ClientConfig {
+            etag_cache_entries: Some(16),
+            ..Default::default()
+        },
+    );
+    let opts = || AgentListOptions {
+        status: Some(AgentStatus::Active),
+        division: Some("ops".to_string()),
+        ..Default::default()
+    };
+
+    client.agents().list(Some(opts())).await.unwrap();
+    let cached = client.agents().list(Some(opts())).await.unwrap();
+    assert_eq!(cached.len(), 1);
+    let other_org = client.with_org("org-2").unwrap();
+    other_org.agents().list(Some(opts())).await.unwrap();
+
+    let requests = mock.requests();
+    assert_eq!(header(&requests[0], "if-none-match"), None);
+    assert_eq!(
+        header(&requests[1], "if-none-match").as_deref(),
+        Some("\"v1\"")
+    );
+    assert_eq!(header(&requests[2], "if-none-match"), None);
+}
@@ -0,0 +1,171 @@
+use blackroad::*;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn client(mock: &MockTransport) -> BlackRoadClient {
+    BlackRoadClient::new(ClientConfig {
+        api_key: Some("test-key".to_string()),
+        transport: Some(Arc::new(mock.clone())),
+        max_retries: Some(1),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Returns a fresh path for an outbox file, removing any left by an earlier
+/// run.
+fn outbox_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "blackroad-outbox-{}-{}.jsonl",
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn entry(action: &str) -> LogMemoryOptions {
+    LogMemoryOptions {
+        action: action.to_string(),
+        entity: "fleet".to_string(),
+        ..Default::default()
+    }
+}
+
+fn logged(action: &str) -> Value {
+    json!({
+        "hash": "h",
+        "timestamp": "2024-01-01T00:00:00Z",
+        "action": action,
+        "entity": "fleet",
+    })
+}
+
+fn idempotency_key(request: &RecordedRequest) -> String {
+    request.headers["idempotency-key"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+fn action(request: &RecordedRequest) -> String {
+    request.json::<Value>().unwrap()["action"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn replays_queued_writes_in_order_after_a_restart() {
+    let path = outbox_path("replay");
+    let mock = MockTransport::new();
+    mock.fail(Method::POST, "/memory", "connection refused")
+        .respond(Method::POST, "/memory", 201, logged("first"))
+        .respond(Method::POST, "/memory", 201, logged("second"));
+
+    let outbox = Outbox::open(client(&mock), &path).await.unwrap();
+    for (key, action) in [("k1", "first"), ("k2", "second")] {
+        let op = OutboxOp::LogMemory(entry(action));
+        assert!(outbox.enqueue(key, op).await.unwrap());
+    }
+    let duplicate = OutboxOp::LogMemory(entry("first"));
+    assert!(!outbox.enqueue("k1", duplicate).await.unwrap());
+
+    let report = outbox.flush().await.unwrap();
+    assert!(report.delivered.is_empty());
+    assert_eq!(report.remaining, 2);
+    drop(outbox);
+
+    let outbox = Outbox::open(client(&mock), &path).await.unwrap();
+    assert_eq!(outbox.len().await, 2);
+    let report = outbox.flush().await.unwrap();
+    assert_eq!(report.delivered, ["k1", "k2"]);
+    assert_eq!(report.remaining, 0);
+
+    let requests = mock.requests();
+    let keys: Vec<_> = requests.iter().map(idempotency_key).collect();
+    assert_eq!(keys, ["k1", "k1", "k2"]);
+    let actions: Vec<_> = requests.iter().map(action).collect();
+    assert_eq!(actions, ["first", "first", "second"]);
+    assert!(
+        Outbox::open(client(&mock), &path)
+            .await
+            .unwrap()
+            .is_empty()
+            .await
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn drops_writes_the_api_rejects_and_delivers_the_rest() {
+    let path = outbox_path("rejected");
+    let mock = MockTransport::new();
+    mock.respond(
+        Method::POST,
+        "/memory",
+        422,
+        json!({ "message": "entity is archived" }),
+    )
+    .respond(Method::POST, "/memory", 201, logged("second"));
+
+    let outbox = Outbox::open(client(&mock), &path).await.unwrap();
+    outbox
+        .enqueue("k1", OutboxOp::LogMemory(entry("first")))
+        .await
+        .unwrap();
+    outbox
+        .enqueue("k2", OutboxOp::LogMemory(entry("second")))
+        .await
+        .unwrap();
+
+    let report = outbox.flush().await.unwrap();
+    assert_eq!(report.delivered, ["k2"]);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].0, "k1");
+    assert!(outbox.is_empty().await);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn logger_queues_while_offline_and_keeps_entries_in_order() {
+    let path = outbox_path("logger");
+    let mock = MockTransport::new();
+    mock.fail(Method::POST, "/memory", "connection refused")
+        .respond(
+            Method::POST,
+            "/memory",
+            422,
+            json!({ "message": "entity is archived" }),
+        )
+        .respond(Method::POST, "/memory", 201, logged("second"));
+
+    let logger = OfflineMemoryLogger::open(client(&mock), &path)
+        .await
+        .unwrap();
+    assert!(matches!(
+        logger.log(entry("first")).await.unwrap(),
+        LogOutcome::Queued
+    ));
+    assert_eq!(logger.pending().await, 1);
+
+    // The queued entry is replayed with its original key before the new one
+    // is sent, and its rejection is held for the next report.
+    assert!(matches!(
+        logger.log(entry("second")).await.unwrap(),
+        LogOutcome::Logged(_)
+    ));
+    assert_eq!(logger.pending().await, 0);
+    let requests = mock.requests();
+    let actions: Vec<_> = requests.iter().map(action).collect();
+    assert_eq!(actions, ["first", "first", "second"]);
+    assert_eq!(idempotency_key(&requests[0]), idempotency_key(&requests[1]));
+    assert_ne!(idempotency_key(&requests[1]), idempotency_key(&requests[2]));
+
+    let report = logger.flush().await.unwrap();
+    assert_eq!(report.rejected.len(), 1);
+    assert!(matches!(report.rejected[0].1, Error::Validation(_)));
+    assert!(logger.take_rejected().await.is_empty());
+    let _ = std::fs::remove_file(&path);
+}
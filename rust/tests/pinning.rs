@@ -0,0 +1,180 @@
+use blackroad::*;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use ed25519_dalek::{Signer, SigningKey};
+use std::sync::Arc;
+
+fn client(mock: &MockTransport) -> BlackRoadClient {
+    BlackRoadClient::new(ClientConfig {
+        api_key: Some("test-key".to_string()),
+        transport: Some(Arc::new(mock.clone())),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+}
+
+/// Builds a chain of `len` entries, oldest first, with correct hashes.
+fn chain(len: usize) -> Vec<MemoryEntry> {
+    let mut entries: Vec<MemoryEntry> = Vec::new();
+    for i in 0..len {
+        let mut entry = MemoryEntry {
+            hash: String::new(),
+            timestamp: at(i as i64),
+            action: "note".to_string(),
+            entity: "fleet".to_string(),
+            details: Some(format!("entry {}", i)),
+            agent: None,
+            tags: None,
+            prev_hash: entries.last().map(|e| e.hash.clone()),
+            metadata: None,
+        };
+        entry.hash = entry.compute_hash();
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Returns the head at `entries[count - 1]`, signed with `key`.
+fn head(entries: &[MemoryEntry], count: usize, key: &SigningKey) -> ChainHead {
+    let entry = &entries[count - 1];
+    let message = format!(
+        "{}\n{}\n{}",
+        entry.hash,
+        count,
+        entry.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+    );
+    ChainHead {
+        hash: entry.hash.clone(),
+        count: count as i64,
+        timestamp: entry.timestamp,
+        signature: Some(hex::encode(key.sign(message.as_bytes()).to_bytes())),
+    }
+}
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn public_key(key: &SigningKey) -> ChainHeadKey {
+    ChainHeadKey::from_bytes(key.verifying_key().as_bytes()).unwrap()
+}
+
+/// Serves `current` as the head and each entry from newest to oldest.
+fn serve(mock: &MockTransport, current: &ChainHead, entries: &[MemoryEntry]) {
+    mock.respond(
+        Method::GET,
+        "/memory/head",
+        200,
+        serde_json::to_value(current).unwrap(),
+    );
+    for entry in entries.iter().rev() {
+        mock.respond(
+            Method::GET,
+            &format!("/memory/{}", entry.hash),
+            200,
+            serde_json::to_value(entry).unwrap(),
+        );
+    }
+}
+
+#[tokio::test]
+async fn a_chain_grown_from_its_pin_is_intact() {
+    let key = signing_key();
+    let entries = chain(3);
+    let mock = MockTransport::new();
+    serve(&mock, &head(&entries, 3, &key), &entries);
+
+    let pin = head(&entries, 1, &key);
+    let result = client(&mock)
+        .memory()
+        .verify_against_pin(&pin, &public_key(&key))
+        .await
+        .unwrap();
+    assert_eq!(result, PinVerification::Intact);
+    assert!(mock.is_exhausted());
+}
+
+#[tokio::test]
+async fn an_edited_entry_diverges_from_the_pin() {
+    let key = signing_key();
+    let mut entries = chain(3);
+    entries[1].details = Some("rewritten".to_string());
+    let mock = MockTransport::new();
+    serve(&mock, &head(&entries, 3, &key), &entries);
+
+    let pin = head(&entries, 1, &key);
+    let result = client(&mock)
+        .memory()
+        .verify_against_pin(&pin, &public_key(&key))
+        .await
+        .unwrap();
+    let PinVerification::Diverged(report) = result else {
+        panic!("expected divergence, got {:?}", result);
+    };
+    assert_eq!(report.checked, 1);
+    assert!(matches!(
+        &report.issues[..],
+        [ChainIssue::HashMismatch { hash, .. }] if *hash == entries[1].hash
+    ));
+}
+
+#[tokio::test]
+async fn a_replaced_history_is_missing_the_pin() {
+    let key = signing_key();
+    let pinned = chain(2);
+    let mut rewritten = chain(3);
+    rewritten[0].details = Some("other".to_string());
+    rewritten = rewritten
+        .into_iter()
+        .scan(None::<String>, |prev, mut entry| {
+            entry.prev_hash = prev.clone();
+            entry.hash = entry.compute_hash();
+            *prev = Some(entry.hash.clone());
+            Some(entry)
+        })
+        .collect();
+    let mock = MockTransport::new();
+    serve(&mock, &head(&rewritten, 3, &key), &rewritten[1..]);
+
+    let pin = head(&pinned, 2, &key);
+    let result = client(&mock)
+        .memory()
+        .verify_against_pin(&pin, &public_key(&key))
+        .await
+        .unwrap();
+    assert_eq!(result, PinVerification::Missing);
+}
+
+#[tokio::test]
+async fn rejects_heads_signed_by_another_key_or_shrunk() {
+    let key = signing_key();
+    let entries = chain(3);
+
+    let mock = MockTransport::new();
+    let forged = head(&entries, 1, &SigningKey::from_bytes(&[9; 32]));
+    let result = client(&mock)
+        .memory()
+        .verify_against_pin(&forged, &public_key(&key))
+        .await
+        .unwrap();
+    assert_eq!(result, PinVerification::BadSignature);
+    assert!(mock.requests().is_empty());
+
+    serve(&mock, &head(&entries, 2, &key), &[]);
+    let pin = head(&entries, 3, &key);
+    let result = client(&mock)
+        .memory()
+        .verify_against_pin(&pin, &public_key(&key))
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        PinVerification::Shrunk {
+            pinned: 3,
+            current: 2
+        }
+    );
+}
@@ -0,0 +1,258 @@
+use blackroad::*;
+use futures::{StreamExt, TryStreamExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+fn client(mock: &MockTransport) -> BlackRoadClient {
+    BlackRoadClient::new(ClientConfig {
+        api_key: Some("test-key".to_string()),
+        transport: Some(Arc::new(mock.clone())),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn task(id: &str) -> Value {
+    json!({
+        "id": id,
+        "title": "Scan fleet",
+        "status": "in_progress",
+        "priority": "medium",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+    })
+}
+
+fn agent(id: &str) -> Value {
+    json!({
+        "id": id,
+        "name": id,
+        "type": "worker",
+        "division": "ops",
+        "level": 1,
+        "status": "active",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+    })
+}
+
+fn describe(update: TaskUpdate) -> String {
+    match update {
+        TaskUpdate::Status(change) => format!("status {}", change.status),
+        TaskUpdate::Progress(progress) => format!("progress {:?}", progress.percent),
+        TaskUpdate::SlaBreach(breach) => format!("breach {:?}", breach.kind),
+    }
+}
+
+#[tokio::test]
+async fn watch_reads_server_sent_events_until_the_task_ends() {
+    let mock = MockTransport::new();
+    mock.respond(Method::GET, "/tasks/t1", 200, task("t1"));
+    mock.respond_raw(
+        Method::GET,
+        "/tasks/t1/watch",
+        200,
+        &[("content-type", "text/event-stream")],
+        concat!(
+            ": connected\n\n",
+            "event: progress\n",
+            "data: {\"task_id\":\"t1\",\"percent\":0.5,\"reported_at\":\"2024-01-01T00:01:00Z\"}\n\n",
+            "event: status\n",
+            "id: 2\n",
+            "data: {\"task_id\":\"t1\",\"status\":\"completed\",\"changed_at\":\"2024-01-01T00:02:00Z\"}\n\n",
+        ),
+    );
+    let client = client(&mock);
+
+    let updates: Vec<String> = client
+        .tasks()
+        .watch("t1")
+        .map_ok(describe)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(updates, ["progress Some(0.5)", "status completed"]);
+
+    let watch = &mock.requests()[1];
+    assert_eq!(watch.headers["accept"], "text/event-stream");
+}
+
+#[tokio::test]
+async fn watch_falls_back_to_long_polling() {
+    let mock = MockTransport::new();
+    mock.respond(Method::GET, "/tasks/t1", 200, task("t1"))
+        .fail(Method::GET, "/tasks/t1/watch", "blocked by proxy")
+        .respond(
+            Method::GET,
+            "/tasks/t1/events",
+            200,
+            json!({ "events": [] }),
+        )
+        .respond(
+            Method::GET,
+            "/tasks/t1/events",
+            200,
+            json!({ "events": [{
+                "seq": 1,
+                "type": "status_changed",
+                "timestamp": "2024-01-01T00:02:00Z",
+                "status": "completed",
+                "previous_status": "in_progress",
+            }] }),
+        );
+    // Send each request once, so the blocked stream fails on the first try
+    let client = client(&mock)
+        .with_options(RequestOptions {
+            max_retries: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+
+    let updates: Vec<String> = client
+        .tasks()
+        .watch("t1")
+        .map_ok(describe)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(updates, ["status completed"]);
+
+    let polls: Vec<_> = mock
+        .requests()
+        .into_iter()
+        .filter(|r| r.url.path().ends_with("/events"))
+        .collect();
+    assert_eq!(
+        polls[1]
+            .url
+            .query_pairs()
+            .find(|(k, _)| k == "after")
+            .unwrap()
+            .1,
+        "-1"
+    );
+}
+
+#[tokio::test]
+async fn list_all_follows_offsets_then_cursors() {
+    let mock = MockTransport::new();
+    mock.respond(
+        Method::GET,
+        "/agents",
+        200,
+        json!({ "agents": [agent("a"), agent("b")] }),
+    )
+    .respond(
+        Method::GET,
+        "/agents",
+        200,
+        json!({ "agents": [agent("b"), agent("c")], "next_cursor": "page-3" }),
+    )
+    .respond(
+        Method::GET,
+        "/agents",
+        200,
+        json!({ "agents": [agent("d")] }),
+    );
+    let client = client(&mock);
+
+    let ids: Vec<String> = client
+        .agents()
+        .list_all(Some(AgentListOptions {
+            limit: Some(2),
+            ..Default::default()
+        }))
+        .map_ok(|agent| agent.id)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(ids, ["a", "b", "c", "d"]);
+
+    let queries: Vec<_> = mock
+        .requests()
+        .iter()
+        .map(|r| r.url.query().unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        queries,
+        [
+            "limit=2&offset=0",
+            "limit=2&offset=2",
+            "cursor=page-3&limit=2"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn list_all_prefetches_one_page_and_stops_at_an_error() {
+    let mock = MockTransport::new();
+    mock.respond(
+        Method::GET,
+        "/agents",
+        200,
+        json!({ "agents": [agent("a"), agent("b")] }),
+    )
+    .respond(Method::GET, "/agents", 404, json!({ "error": "gone" }));
+    let client = client(&mock);
+
+    let mut agents = Box::pin(client.agents().list_all(Some(AgentListOptions {
+        limit: Some(2),
+        ..Default::default()
+    })));
+    assert_eq!(agents.next().await.unwrap().unwrap().id, "a");
+    assert_eq!(mock.requests().len(), 2);
+    assert_eq!(agents.next().await.unwrap().unwrap().id, "b");
+    assert!(agents.next().await.unwrap().is_err());
+    assert!(agents.next().await.is_none());
+}
+
+#[tokio::test]
+async fn export_quotes_csv_fields_across_pages() {
+    let mock = MockTransport::new();
+    mock.respond(
+        Method::GET,
+        "/memory",
+        200,
+        json!({
+            "entries": [{
+                "hash": "h1",
+                "timestamp": "2024-01-01T00:00:00Z",
+                "action": "note",
+                "entity": "fleet, east",
+                "details": "said \"hi\"\nthen left",
+                "tags": ["a", "b"],
+                "metadata": { "k": "v" },
+            }],
+            "next_cursor": "page-2",
+        }),
+    )
+    .respond(
+        Method::GET,
+        "/memory",
+        200,
+        json!({
+            "entries": [{
+                "hash": "h2",
+                "timestamp": "2024-01-01T00:00:01Z",
+                "action": "note",
+                "entity": "fleet",
+                "prev_hash": "h1",
+            }],
+        }),
+    );
+    let client = client(&mock);
+
+    let mut out = Vec::new();
+    let written = client
+        .memory()
+        .export(None, ExportFormat::Csv, &mut out)
+        .await
+        .unwrap();
+    assert_eq!(written, 2);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "hash,timestamp,action,entity,details,agent,tags,prev_hash,metadata\n\
+         h1,2024-01-01T00:00:00Z,note,\"fleet, east\",\"said \"\"hi\"\"\nthen left\",,a;b,,\"{\"\"k\"\":\"\"v\"\"}\"\n\
+         h2,2024-01-01T00:00:01Z,note,fleet,,,,h1,\n"
+    );
+}
@@ -0,0 +1,90 @@
+use blackroad::webhooks::{self, HeaderMap, WebhookEvent, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use blackroad::Error;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const SECRET: &[u8] = b"whsec_test";
+const BODY: &[u8] = br#"{"type":"agent.renamed","data":{"id":"a1"}}"#;
+
+fn signed(secret: &[u8], timestamp: i64, body: &[u8]) -> HeaderMap {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body);
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+    headers.insert(
+        SIGNATURE_HEADER,
+        format!("sha256={}", signature).parse().unwrap(),
+    );
+    headers
+}
+
+fn rejected(result: Result<(), Error>, reason: &str) {
+    match result {
+        Err(Error::Authentication(message)) => assert!(message.contains(reason), "{}", message),
+        other => panic!("expected {:?} rejection, got {:?}", reason, other),
+    }
+}
+
+#[test]
+fn accepts_a_fresh_signed_delivery() {
+    let headers = signed(SECRET, Utc::now().timestamp(), BODY);
+    webhooks::verify_signature(SECRET, &headers, BODY).unwrap();
+    match webhooks::parse_event(SECRET, &headers, BODY).unwrap() {
+        WebhookEvent::Unknown { kind, data } => {
+            assert_eq!(kind, "agent.renamed");
+            assert_eq!(data["id"], "a1");
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn rejects_a_tampered_body_or_wrong_secret() {
+    let headers = signed(SECRET, Utc::now().timestamp(), BODY);
+    let tampered = br#"{"type":"agent.renamed","data":{"id":"a2"}}"#;
+    rejected(
+        webhooks::verify_signature(SECRET, &headers, tampered),
+        "signature mismatch",
+    );
+    rejected(
+        webhooks::verify_signature(b"other", &headers, BODY),
+        "signature mismatch",
+    );
+}
+
+#[test]
+fn rejects_timestamps_outside_tolerance() {
+    let now = Utc::now().timestamp();
+    for timestamp in [
+        now - webhooks::TOLERANCE_SECS - 60,
+        now + webhooks::TOLERANCE_SECS + 60,
+        i64::MIN,
+        i64::MAX,
+    ] {
+        let headers = signed(SECRET, timestamp, BODY);
+        rejected(
+            webhooks::verify_signature(SECRET, &headers, BODY),
+            "outside tolerance",
+        );
+    }
+}
+
+#[test]
+fn rejects_missing_or_malformed_headers() {
+    let mut headers = signed(SECRET, Utc::now().timestamp(), BODY);
+    headers.insert(SIGNATURE_HEADER, "md5=abc".parse().unwrap());
+    rejected(
+        webhooks::verify_signature(SECRET, &headers, BODY),
+        "malformed signature",
+    );
+
+    headers.remove(TIMESTAMP_HEADER);
+    rejected(
+        webhooks::verify_signature(SECRET, &headers, BODY),
+        "missing",
+    );
+}